@@ -13,14 +13,35 @@ use warp::{
 pub enum Error {
     ParseError(std::num::ParseIntError),
     MissingParameters,
+    InvalidParameter(String),
+    LimitTooLarge(i32),
+    NegativeOffset,
     WrongPassword,
+    AccountAlreadyExists,
     CannotDecryptToken,
     Unauthorized,
+    UnverifiedAccount,
+    /// Too many failed logins, with the seconds until the next try
+    TooManyAttempts(i64),
+    /// A login through the OpenID Connect provider did not check out,
+    /// with the reason for the log
+    OidcLoginFailed(String),
+    QuestionNotFound,
+    AnswerNotFound,
+    AccountNotFound,
+    ApiKeyNotFound,
+    SessionNotFound,
+    ReviewNotFound,
     ArgonLibraryError(ArgonError),
     DatabaseQueryError(sqlx::Error),
     MigrationError(sqlx::migrate::MigrateError),
+    MailError(String),
     ReqwestAPIError(ReqwestError),
     MiddlewareReqwestAPIError(MiddlewareReqwestError),
+    /// The moderation service did not answer in time, after the retries
+    ModerationTimeout,
+    /// The moderation service is down and posts are not taken without it
+    ModerationUnavailable,
     ClientError(APILayerError),
     ServerError(APILayerError),
 }
@@ -39,14 +60,39 @@ impl std::fmt::Display for APILayerError {
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match &*self {
+        match self {
             Error::ParseError(ref err) => {
                 write!(f, "Cannot parse parameter: {}", err)
             }
             Error::MissingParameters => write!(f, "Missing parameter"),
+            Error::InvalidParameter(name) => {
+                write!(f, "Invalid value for parameter: {}", name)
+            }
+            Error::LimitTooLarge(max) => {
+                write!(f, "Limit must not be larger than {}", max)
+            }
+            Error::NegativeOffset => write!(f, "Offset must not be negative"),
             Error::WrongPassword => write!(f, "Wrong password"),
+            Error::AccountAlreadyExists => {
+                write!(f, "Account already exists")
+            }
             Error::CannotDecryptToken => write!(f, "Cannot decrypt error"),
             Error::Unauthorized => write!(f, "No permission to change the underlying resource"),
+            Error::UnverifiedAccount => {
+                write!(f, "Email address is not verified")
+            }
+            Error::TooManyAttempts(seconds) => {
+                write!(f, "Too many failed logins, retry in {}s", seconds)
+            }
+            Error::OidcLoginFailed(reason) => {
+                write!(f, "OpenID Connect login failed: {}", reason)
+            }
+            Error::QuestionNotFound => write!(f, "Question not found"),
+            Error::AnswerNotFound => write!(f, "Answer not found"),
+            Error::AccountNotFound => write!(f, "Account not found"),
+            Error::ApiKeyNotFound => write!(f, "API key not found"),
+            Error::SessionNotFound => write!(f, "Session not found"),
+            Error::ReviewNotFound => write!(f, "Review not found"),
             Error::ArgonLibraryError(_) => {
                 write!(f, "Cannot verifiy password")
             }
@@ -54,12 +100,19 @@ impl std::fmt::Display for Error {
                 write!(f, "Cannot update, invalid data")
             }
             Error::MigrationError(_) => write!(f, "Cannot migrate data"),
+            Error::MailError(err) => write!(f, "Cannot send mail: {}", err),
             Error::ReqwestAPIError(err) => {
                 write!(f, "External API error: {}", err)
             }
             Error::MiddlewareReqwestAPIError(err) => {
                 write!(f, "External API error: {}", err)
             }
+            Error::ModerationTimeout => {
+                write!(f, "Content moderation timed out")
+            }
+            Error::ModerationUnavailable => {
+                write!(f, "Content moderation unavailable")
+            }
             Error::ClientError(err) => {
                 write!(f, "External Client error: {}", err)
             }
@@ -70,23 +123,50 @@ impl std::fmt::Display for Error {
     }
 }
 
-const DUPLICATE_KEY: u32 = 23505;
-
 impl Reject for Error {}
 impl Reject for APILayerError {}
 
 #[instrument]
 pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
+    // The only answer with a header besides the status
+    if let Some(crate::Error::TooManyAttempts(seconds)) = r.find() {
+        event!(Level::WARN, "Login blocked for {}s", seconds);
+        let reply = warp::reply::with_status(
+            "Too many failed logins, try again later".to_string(),
+            StatusCode::TOO_MANY_REQUESTS,
+        );
+        return Ok(
+            warp::reply::with_header(reply, "Retry-After", seconds.to_string()).into_response(),
+        );
+    }
+
+    error_reply(r).map(Reply::into_response)
+}
+
+/// Whether a unique violation is the one of an email address which has
+/// an account already. Postgres names the violated constraint, SQLite
+/// only the column in the message.
+fn is_taken_email(err: &dyn sqlx::error::DatabaseError) -> bool {
+    err.constraint() == Some("accounts_pkey") || err.message().ends_with("accounts.email")
+}
+
+fn error_reply(r: Rejection) -> Result<impl Reply, Rejection> {
     if let Some(crate::Error::DatabaseQueryError(e)) = r.find() {
         event!(Level::ERROR, "Database query error");
 
         match e {
             sqlx::Error::Database(err) => {
-                if err.code().unwrap().parse::<u32>().unwrap() == DUPLICATE_KEY {
+                // Postgres and SQLite report this with different codes
+                if err.is_unique_violation() && is_taken_email(err.as_ref()) {
                     Ok(warp::reply::with_status(
-                        "Account already exsists".to_string(),
+                        "Account already exists".to_string(),
                         StatusCode::UNPROCESSABLE_ENTITY,
                     ))
+                } else if err.is_unique_violation() {
+                    Ok(warp::reply::with_status(
+                        "Already exists".to_string(),
+                        StatusCode::CONFLICT,
+                    ))
                 } else {
                     Ok(warp::reply::with_status(
                         "Cannot update data".to_string(),
@@ -111,6 +191,60 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
             "No permission to change underlying resource".to_string(),
             StatusCode::UNAUTHORIZED,
         ))
+    } else if let Some(crate::Error::UnverifiedAccount) = r.find() {
+        event!(Level::WARN, "Account is not verified");
+        Ok(warp::reply::with_status(
+            "Verify your email address first".to_string(),
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(crate::Error::OidcLoginFailed(reason)) = r.find() {
+        event!(Level::WARN, "OpenID Connect login failed: {}", reason);
+        Ok(warp::reply::with_status(
+            "Login through the identity provider failed".to_string(),
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if let Some(crate::Error::AccountAlreadyExists) = r.find() {
+        event!(Level::ERROR, "Account already exists");
+        Ok(warp::reply::with_status(
+            "Account already exists".to_string(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ))
+    } else if let Some(crate::Error::QuestionNotFound) = r.find() {
+        event!(Level::WARN, "Requested question was not found");
+        Ok(warp::reply::with_status(
+            "Question not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::AnswerNotFound) = r.find() {
+        event!(Level::WARN, "Requested answer was not found");
+        Ok(warp::reply::with_status(
+            "Answer not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::AccountNotFound) = r.find() {
+        event!(Level::WARN, "Requested account was not found");
+        Ok(warp::reply::with_status(
+            "Account not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::ApiKeyNotFound) = r.find() {
+        event!(Level::WARN, "Requested API key was not found");
+        Ok(warp::reply::with_status(
+            "API key not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::SessionNotFound) = r.find() {
+        event!(Level::WARN, "Requested session was not found");
+        Ok(warp::reply::with_status(
+            "Session not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::ReviewNotFound) = r.find() {
+        event!(Level::WARN, "Requested review was not found");
+        Ok(warp::reply::with_status(
+            "Review not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
     } else if let Some(crate::Error::WrongPassword) = r.find() {
         event!(Level::ERROR, "Entered wrong password");
         Ok(warp::reply::with_status(
@@ -123,6 +257,24 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
             "Internal Server Error".to_string(),
             StatusCode::INTERNAL_SERVER_ERROR,
         ))
+    } else if let Some(crate::Error::ModerationTimeout) = r.find() {
+        event!(Level::ERROR, "Content moderation timed out");
+        Ok(warp::reply::with_status(
+            "Content moderation timed out, try again later".to_string(),
+            StatusCode::GATEWAY_TIMEOUT,
+        ))
+    } else if let Some(crate::Error::ModerationUnavailable) = r.find() {
+        event!(Level::WARN, "Post rejected, content moderation unavailable");
+        Ok(warp::reply::with_status(
+            "Content moderation unavailable, try again later".to_string(),
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else if let Some(crate::Error::MailError(e)) = r.find() {
+        event!(Level::ERROR, "Cannot send mail: {}", e);
+        Ok(warp::reply::with_status(
+            "Internal Server Error".to_string(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ))
     } else if let Some(crate::Error::ClientError(e)) = r.find() {
         event!(Level::ERROR, "{}", e);
         Ok(warp::reply::with_status(
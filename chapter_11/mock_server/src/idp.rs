@@ -0,0 +1,262 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use warp::{http, Filter, Rejection, Reply};
+
+use crate::OneshotHandler;
+
+/// The only client the mock identity provider knows
+pub const CLIENT_ID: &str = "mock-client";
+pub const CLIENT_SECRET: &str = "mock-secret";
+/// Id of the key the ID tokens are signed with
+const KEY_ID: &str = "mock";
+
+/// A code handed out by `/authorize`, waiting to be exchanged at `/token`
+#[derive(Clone, Debug)]
+struct Grant {
+    redirect_uri: String,
+    code_challenge: String,
+    nonce: Option<String>,
+    email: String,
+    email_verified: bool,
+}
+
+/// OpenID Connect provider for the tests. `/authorize` does not ask
+/// anybody, it logs in `login_hint` (an email address, the subject is
+/// derived from it) right away and redirects back with a code.
+/// `email_verified=false` makes the address unverified.
+#[derive(Clone, Debug)]
+pub struct MockIdp {
+    socket: SocketAddr,
+    grants: Arc<Mutex<HashMap<String, Grant>>>,
+}
+
+impl MockIdp {
+    pub fn new(bind_addr: SocketAddr) -> MockIdp {
+        MockIdp {
+            socket: bind_addr,
+            grants: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The issuer, which is also the base URL of every endpoint
+    pub fn issuer(&self) -> String {
+        format!("http://{}", self.socket)
+    }
+
+    /// Where clients discover the endpoints
+    pub fn discovery_url(&self) -> String {
+        format!("{}/.well-known/openid-configuration", self.issuer())
+    }
+
+    fn key_pair() -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed_unchecked(&[7; 32]).expect("Invalid seed")
+    }
+
+    fn random_code() -> String {
+        let mut bytes = [0u8; 16];
+        SystemRandom::new().fill(&mut bytes).expect("No randomness");
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    async fn discovery(issuer: String) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+            "response_types_supported": ["code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["EdDSA"],
+            "code_challenge_methods_supported": ["S256"],
+        })))
+    }
+
+    async fn jwks() -> Result<impl Reply, Rejection> {
+        let public_key = URL_SAFE_NO_PAD.encode(Self::key_pair().public_key());
+        Ok(warp::reply::json(&json!({
+            "keys": [{
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "alg": "EdDSA",
+                "use": "sig",
+                "kid": KEY_ID,
+                "x": public_key,
+            }]
+        })))
+    }
+
+    async fn authorize(
+        params: HashMap<String, String>,
+        grants: Arc<Mutex<HashMap<String, Grant>>>,
+    ) -> Result<warp::reply::Response, Rejection> {
+        let param = |name: &str| params.get(name).cloned().unwrap_or_default();
+        if param("response_type") != "code"
+            || param("client_id") != CLIENT_ID
+            || param("code_challenge_method") != "S256"
+            || param("code_challenge").is_empty()
+            || param("redirect_uri").is_empty()
+        {
+            return Ok(Self::oauth_error(
+                "invalid_request",
+                http::StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let email = params
+            .get("login_hint")
+            .cloned()
+            .unwrap_or_else(|| "alice@example.com".to_string());
+        let code = Self::random_code();
+        grants.lock().unwrap().insert(
+            code.clone(),
+            Grant {
+                redirect_uri: param("redirect_uri"),
+                code_challenge: param("code_challenge"),
+                nonce: params.get("nonce").cloned(),
+                email,
+                email_verified: param("email_verified") != "false",
+            },
+        );
+
+        let location = format!(
+            "{}?code={}&state={}",
+            param("redirect_uri"),
+            code,
+            param("state")
+        );
+        let location = location
+            .parse::<http::Uri>()
+            .map_err(|_| warp::reject::reject())?;
+        Ok(warp::redirect::found(location).into_response())
+    }
+
+    async fn token(
+        authorization: Option<String>,
+        form: HashMap<String, String>,
+        issuer: String,
+        grants: Arc<Mutex<HashMap<String, Grant>>>,
+    ) -> Result<warp::reply::Response, Rejection> {
+        let credentials = format!("{}:{}", CLIENT_ID, CLIENT_SECRET);
+        let expected = format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        );
+        if authorization.as_deref() != Some(expected.as_str()) {
+            return Ok(Self::oauth_error(
+                "invalid_client",
+                http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+
+        let field = |name: &str| form.get(name).cloned().unwrap_or_default();
+        let grant = grants.lock().unwrap().remove(&field("code"));
+        let grant = match grant {
+            Some(grant) if field("grant_type") == "authorization_code" => grant,
+            _ => {
+                return Ok(Self::oauth_error(
+                    "invalid_grant",
+                    http::StatusCode::BAD_REQUEST,
+                ))
+            }
+        };
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(field("code_verifier").as_bytes()));
+        if grant.redirect_uri != field("redirect_uri") || grant.code_challenge != challenge {
+            return Ok(Self::oauth_error(
+                "invalid_grant",
+                http::StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Clock before 1970")
+            .as_secs();
+        let header = json!({ "alg": "EdDSA", "typ": "JWT", "kid": KEY_ID });
+        let claims = json!({
+            "iss": issuer,
+            "sub": format!("mock|{}", grant.email),
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": grant.nonce,
+            "email": grant.email,
+            "email_verified": grant.email_verified,
+        });
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = Self::key_pair().sign(signing_input.as_bytes());
+        let id_token = format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature));
+
+        Ok(warp::reply::json(&json!({
+            "access_token": Self::random_code(),
+            "token_type": "Bearer",
+            "expires_in": 300,
+            "id_token": id_token,
+        }))
+        .into_response())
+    }
+
+    fn oauth_error(error: &str, status: http::StatusCode) -> warp::reply::Response {
+        warp::reply::with_status(warp::reply::json(&json!({ "error": error })), status)
+            .into_response()
+    }
+
+    fn build_routes(&self) -> impl Filter<Extract = impl Reply> + Clone {
+        let issuer = self.issuer();
+        let issuer_filter = warp::any().map(move || issuer.clone());
+        let grants = self.grants.clone();
+        let grants_filter = warp::any().map(move || grants.clone());
+
+        let discovery = warp::get()
+            .and(warp::path!(".well-known" / "openid-configuration"))
+            .and(issuer_filter.clone())
+            .and_then(Self::discovery);
+
+        let jwks = warp::get()
+            .and(warp::path("jwks"))
+            .and(warp::path::end())
+            .and_then(Self::jwks);
+
+        let authorize = warp::get()
+            .and(warp::path("authorize"))
+            .and(warp::path::end())
+            .and(warp::query())
+            .and(grants_filter.clone())
+            .and_then(Self::authorize);
+
+        let token = warp::post()
+            .and(warp::path("token"))
+            .and(warp::path::end())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::form())
+            .and(issuer_filter)
+            .and(grants_filter)
+            .and_then(Self::token);
+
+        discovery.or(jwks).or(authorize).or(token)
+    }
+
+    pub fn oneshot(&self) -> OneshotHandler {
+        let (tx, rx) = oneshot::channel::<i32>();
+        let routes = Self::build_routes(self);
+
+        let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(self.socket, async {
+            rx.await.ok();
+        });
+
+        tokio::task::spawn(server);
+
+        OneshotHandler { sender: tx }
+    }
+}
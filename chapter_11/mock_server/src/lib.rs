@@ -5,6 +5,10 @@ use std::net::SocketAddr;
 use tokio::sync::{oneshot, oneshot::Sender};
 use warp::{http, Filter, Reply, Rejection};
 
+pub mod idp;
+
+pub use idp::MockIdp;
+
 #[derive(Clone, Debug)]
 pub struct MockServer {
     socket: SocketAddr,
@@ -66,7 +70,7 @@ impl MockServer {
 
     pub fn oneshot(&self) -> OneshotHandler {
         let (tx, rx) = oneshot::channel::<i32>();
-        let routes = Self::build_routes(&self);
+        let routes = Self::build_routes(self);
 
         let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(self.socket, async {
             rx.await.ok();
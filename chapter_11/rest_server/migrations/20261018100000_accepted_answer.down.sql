@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS questions_accepted_answer_idx;
+ALTER TABLE questions
+DROP COLUMN accepted_answer_id;
@@ -0,0 +1,4 @@
+ALTER TABLE questions
+ADD COLUMN accepted_answer_id integer REFERENCES answers ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS questions_accepted_answer_idx ON questions (accepted_answer_id);
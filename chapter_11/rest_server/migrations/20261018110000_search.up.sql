@@ -0,0 +1,13 @@
+ALTER TABLE questions
+ADD COLUMN search tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A') ||
+    setweight(to_tsvector('english', content), 'B')
+) STORED;
+
+ALTER TABLE answers
+ADD COLUMN search tsvector GENERATED ALWAYS AS (
+    to_tsvector('english', content)
+) STORED;
+
+CREATE INDEX IF NOT EXISTS questions_search_idx ON questions USING GIN (search);
+CREATE INDEX IF NOT EXISTS answers_search_idx ON answers USING GIN (search);
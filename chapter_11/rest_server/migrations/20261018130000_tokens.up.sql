@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    token_hash VARCHAR(255) PRIMARY KEY,
+    session_id VARCHAR(255) NOT NULL,
+    account_id integer NOT NULL,
+    expires_on TIMESTAMP NOT NULL,
+    used boolean NOT NULL DEFAULT false,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_session_idx ON refresh_tokens (session_id);
+
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    token_id VARCHAR(255) PRIMARY KEY,
+    expires_on TIMESTAMP NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE accounts ADD CONSTRAINT accounts_id_key UNIQUE (id);
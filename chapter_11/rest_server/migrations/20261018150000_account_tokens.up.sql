@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS account_tokens (
+    token_hash VARCHAR(255) PRIMARY KEY,
+    account_id integer NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    purpose VARCHAR(32) NOT NULL,
+    expires_on TIMESTAMP NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
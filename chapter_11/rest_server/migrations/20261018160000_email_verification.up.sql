@@ -0,0 +1,4 @@
+-- Accounts from before verification existed count as verified
+ALTER TABLE accounts
+ADD COLUMN verified boolean NOT NULL DEFAULT false;
+UPDATE accounts SET verified = true;
@@ -0,0 +1,6 @@
+-- Failed logins per "email:<address>" and "ip:<address>"
+CREATE TABLE IF NOT EXISTS login_attempts (
+    key VARCHAR(320) PRIMARY KEY,
+    failures integer NOT NULL,
+    last_failure TIMESTAMP NOT NULL
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS api_keys (
+    id serial PRIMARY KEY,
+    account_id integer NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(255) NOT NULL UNIQUE,
+    -- Separated by spaces, e.g. "questions:read answers:write"
+    scopes TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
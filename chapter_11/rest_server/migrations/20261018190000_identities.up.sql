@@ -0,0 +1,8 @@
+-- Users of OpenID Connect providers, by the issuer and the subject of their ID tokens
+CREATE TABLE IF NOT EXISTS identities (
+    issuer VARCHAR(255) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    account_id integer NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (issuer, subject)
+);
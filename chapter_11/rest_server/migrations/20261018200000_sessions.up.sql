@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS sessions (
+    session_id VARCHAR(255) PRIMARY KEY,
+    account_id integer NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used TIMESTAMP NOT NULL DEFAULT NOW(),
+    user_agent TEXT,
+    ip VARCHAR(45)
+);
+
+CREATE INDEX IF NOT EXISTS sessions_account_idx ON sessions (account_id);
+
+-- Logins from before the listing, without user agent and IP address
+INSERT INTO sessions (session_id, account_id, created_on, last_used)
+SELECT session_id, account_id, MIN(created_on), MAX(created_on)
+FROM refresh_tokens
+WHERE account_id IN (SELECT id FROM accounts)
+GROUP BY session_id, account_id
+ON CONFLICT DO NOTHING;
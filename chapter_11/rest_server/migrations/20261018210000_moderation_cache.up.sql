@@ -0,0 +1,10 @@
+-- Moderation results shared by the replicas, by the base64 SHA-256 of the
+-- content
+CREATE TABLE IF NOT EXISTS moderation_cache (
+    content_hash VARCHAR(64) PRIMARY KEY,
+    moderation TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS moderation_cache_created_on_idx
+    ON moderation_cache (created_on);
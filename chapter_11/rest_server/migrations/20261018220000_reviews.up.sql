@@ -0,0 +1,22 @@
+-- Posts held back for the moderators are pending, hidden from the
+-- listings until a moderator publishes them
+ALTER TABLE questions
+ADD COLUMN pending boolean NOT NULL DEFAULT FALSE;
+ALTER TABLE answers
+ADD COLUMN pending boolean NOT NULL DEFAULT FALSE;
+
+-- The text of a pending post as posted and as censored, with the bad
+-- words found in it
+CREATE TABLE IF NOT EXISTS reviews (
+    id serial PRIMARY KEY,
+    question_id integer UNIQUE REFERENCES questions ON DELETE CASCADE,
+    answer_id integer UNIQUE REFERENCES answers ON DELETE CASCADE,
+    original_title TEXT,
+    original_content TEXT NOT NULL,
+    censored_title TEXT,
+    censored_content TEXT NOT NULL,
+    bad_words jsonb NOT NULL,
+    unchecked boolean NOT NULL DEFAULT FALSE,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((question_id IS NULL) <> (answer_id IS NULL))
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS questions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title VARCHAR (255) NOT NULL,
+    content TEXT NOT NULL,
+    -- JSON array, SQLite has no array type
+    tags TEXT,
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
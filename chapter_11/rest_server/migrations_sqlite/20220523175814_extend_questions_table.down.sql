@@ -0,0 +1,2 @@
+ALTER TABLE questions
+DROP COLUMN account_id;
\ No newline at end of file
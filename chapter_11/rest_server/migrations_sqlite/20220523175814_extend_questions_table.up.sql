@@ -0,0 +1,2 @@
+ALTER TABLE questions
+ADD COLUMN account_id INTEGER;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE answers
+DROP COLUMN account_id;
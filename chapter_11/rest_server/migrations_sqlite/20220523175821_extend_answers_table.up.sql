@@ -0,0 +1,2 @@
+ALTER TABLE answers
+ADD COLUMN account_id INTEGER;
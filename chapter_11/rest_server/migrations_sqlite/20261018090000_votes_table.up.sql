@@ -0,0 +1,22 @@
+ALTER TABLE questions
+ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE answers
+ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS question_votes (
+    question_id INTEGER NOT NULL REFERENCES questions ON DELETE CASCADE,
+    account_id INTEGER NOT NULL,
+    value INTEGER NOT NULL CHECK (value IN (-1, 1)),
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (question_id, account_id)
+);
+
+CREATE TABLE IF NOT EXISTS answer_votes (
+    answer_id INTEGER NOT NULL REFERENCES answers ON DELETE CASCADE,
+    account_id INTEGER NOT NULL,
+    value INTEGER NOT NULL CHECK (value IN (-1, 1)),
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (answer_id, account_id)
+);
+
+CREATE INDEX IF NOT EXISTS questions_score_idx ON questions (score DESC, id);
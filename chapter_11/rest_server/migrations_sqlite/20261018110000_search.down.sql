@@ -0,0 +1,4 @@
+ALTER TABLE answers
+DROP COLUMN search;
+ALTER TABLE questions
+DROP COLUMN search;
@@ -0,0 +1,7 @@
+-- SQLite has no tsvector, the search matches substrings of these instead.
+-- Generated columns added later have to be VIRTUAL.
+ALTER TABLE questions
+ADD COLUMN search TEXT GENERATED ALWAYS AS (lower(title || ' ' || content)) VIRTUAL;
+
+ALTER TABLE answers
+ADD COLUMN search TEXT GENERATED ALWAYS AS (lower(content)) VIRTUAL;
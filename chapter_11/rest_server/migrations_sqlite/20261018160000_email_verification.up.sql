@@ -0,0 +1,4 @@
+-- Accounts from before verification existed count as verified
+ALTER TABLE accounts
+ADD COLUMN verified BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE accounts SET verified = TRUE;
@@ -0,0 +1,6 @@
+-- Failed logins per "email:<address>" and "ip:<address>"
+CREATE TABLE IF NOT EXISTS login_attempts (
+    key TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL,
+    last_failure TIMESTAMP NOT NULL
+);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS sessions (
+    session_id TEXT PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    user_agent TEXT,
+    ip TEXT
+);
+
+CREATE INDEX IF NOT EXISTS sessions_account_idx ON sessions (account_id);
+
+-- Logins from before the listing, without user agent and IP address
+INSERT OR IGNORE INTO sessions (session_id, account_id, created_on, last_used)
+SELECT session_id, account_id, MIN(created_on), MAX(created_on)
+FROM refresh_tokens
+WHERE account_id IN (SELECT id FROM accounts)
+GROUP BY session_id, account_id;
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS reviews;
+ALTER TABLE answers
+DROP COLUMN pending;
+ALTER TABLE questions
+DROP COLUMN pending;
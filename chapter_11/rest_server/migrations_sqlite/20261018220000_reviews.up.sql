@@ -0,0 +1,22 @@
+-- Posts held back for the moderators are pending, hidden from the
+-- listings until a moderator publishes them
+ALTER TABLE questions
+ADD COLUMN pending BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE answers
+ADD COLUMN pending BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The text of a pending post as posted and as censored, with the bad
+-- words found in it
+CREATE TABLE IF NOT EXISTS reviews (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    question_id INTEGER UNIQUE REFERENCES questions ON DELETE CASCADE,
+    answer_id INTEGER UNIQUE REFERENCES answers ON DELETE CASCADE,
+    original_title TEXT,
+    original_content TEXT NOT NULL,
+    censored_title TEXT,
+    censored_content TEXT NOT NULL,
+    bad_words TEXT NOT NULL,
+    unchecked BOOLEAN NOT NULL DEFAULT FALSE,
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((question_id IS NULL) <> (answer_id IS NULL))
+);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS questions_created_on_idx ON questions (created_on, id);
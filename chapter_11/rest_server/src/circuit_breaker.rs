@@ -0,0 +1,187 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// When the breaker opens and how long it stays open
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Failures in a row which open the breaker
+    pub failure_threshold: u32,
+    /// How long calls are not even tried before one trial call
+    pub open_duration: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls fail right away
+    Open,
+    /// A single trial call goes through, its outcome closes or opens
+    /// the breaker again
+    HalfOpen,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    /// Failures in a row
+    pub failures: u32,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    failures: u32,
+    opened_on: Instant,
+    /// Whether the trial call of the half open breaker is under way
+    trial: bool,
+}
+
+/// Stops calling a dependency which keeps failing, so requests do not
+/// wait for its timeouts and retries, and tries it again after a while
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: &'static str,
+    config: BreakerConfig,
+    breaker: Mutex<Breaker>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, config: BreakerConfig) -> Self {
+        CircuitBreaker {
+            name,
+            config,
+            breaker: Mutex::new(Breaker {
+                state: BreakerState::Closed,
+                failures: 0,
+                opened_on: Instant::now(),
+                trial: false,
+            }),
+        }
+    }
+
+    /// Whether the next call may go through. Once the open breaker
+    /// waited long enough, the first caller makes the trial call.
+    pub fn allow(&self) -> bool {
+        let mut breaker = self.breaker.lock().expect("Breaker poisoned");
+        match breaker.state {
+            BreakerState::Closed => true,
+            BreakerState::Open
+                if breaker.opened_on.elapsed()
+                    >= self.config.open_duration =>
+            {
+                tracing::info!("{} circuit breaker half open", self.name);
+                breaker.state = BreakerState::HalfOpen;
+                breaker.trial = true;
+                true
+            }
+            BreakerState::Open => false,
+            BreakerState::HalfOpen if !breaker.trial => {
+                breaker.trial = true;
+                true
+            }
+            BreakerState::HalfOpen => false,
+        }
+    }
+
+    pub fn success(&self) {
+        let mut breaker = self.breaker.lock().expect("Breaker poisoned");
+        if breaker.state != BreakerState::Closed {
+            tracing::info!("{} circuit breaker closed", self.name);
+        }
+        breaker.state = BreakerState::Closed;
+        breaker.failures = 0;
+        breaker.trial = false;
+    }
+
+    pub fn failure(&self) {
+        let mut breaker = self.breaker.lock().expect("Breaker poisoned");
+        breaker.failures += 1;
+        breaker.trial = false;
+        let open = match breaker.state {
+            BreakerState::Closed => {
+                breaker.failures >= self.config.failure_threshold
+            }
+            BreakerState::HalfOpen => true,
+            // A call from before the breaker opened
+            BreakerState::Open => false,
+        };
+        if open {
+            tracing::warn!(
+                "{} circuit breaker open after {} failures, retrying in {}s",
+                self.name,
+                breaker.failures,
+                self.config.open_duration.as_secs()
+            );
+            breaker.state = BreakerState::Open;
+            breaker.opened_on = Instant::now();
+        }
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let breaker = self.breaker.lock().expect("Breaker poisoned");
+        BreakerStatus {
+            state: breaker.state,
+            failures: breaker.failures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod circuit_breaker_tests {
+    use std::time::Duration;
+
+    use super::{BreakerConfig, BreakerState, CircuitBreaker};
+
+    #[test]
+    fn breaker() {
+        let breaker = CircuitBreaker::new(
+            "Test",
+            BreakerConfig {
+                failure_threshold: 2,
+                open_duration: Duration::from_millis(50),
+            },
+        );
+        let state = || breaker.status().state;
+
+        // Successes reset the failures in a row
+        breaker.failure();
+        breaker.success();
+        breaker.failure();
+        assert!(breaker.allow());
+        assert_eq!(state(), BreakerState::Closed);
+        breaker.failure();
+        assert_eq!(state(), BreakerState::Open);
+        assert_eq!(breaker.status().failures, 2);
+        assert!(!breaker.allow());
+
+        // A single trial call after the wait, which fails
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow());
+        assert_eq!(state(), BreakerState::HalfOpen);
+        assert!(!breaker.allow());
+        breaker.failure();
+        assert_eq!(state(), BreakerState::Open);
+        assert!(!breaker.allow());
+
+        // and then succeeds
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow());
+        breaker.success();
+        assert_eq!(state(), BreakerState::Closed);
+        assert_eq!(breaker.status().failures, 0);
+        assert!(breaker.allow());
+    }
+}
@@ -0,0 +1,443 @@
+use std::{collections::HashMap, env, sync::Arc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use clap::ArgEnum;
+use paseto::pae::pae;
+use ring::signature::{
+    Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519,
+};
+use serde::{Deserialize, Serialize};
+
+use error_handlers::Error;
+
+use crate::types::token::{PublicKey, PublicKeys};
+
+/// Key id of the key from `PASETO_KEY` when no keyring is configured
+const DEFAULT_KEY_ID: &str = "default";
+
+const V2_LOCAL: &str = "v2.local.";
+const V2_PUBLIC: &str = "v2.public.";
+const V4_PUBLIC: &str = "v4.public.";
+
+/// Which kind of access tokens the service issues
+#[derive(ArgEnum, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenMode {
+    /// `v2.local`, encrypted with the shared key. Only this service can
+    /// read them.
+    Local,
+    /// `v2.public`, signed with Ed25519. Anybody with the public key can
+    /// verify them.
+    PublicV2,
+    /// `v4.public`, signed with Ed25519
+    PublicV4,
+}
+
+/// Footer of every access token, naming the key it was issued with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Footer {
+    kid: String,
+}
+
+/// The keys access tokens are encrypted or signed with. New tokens are
+/// issued with the active key of the token mode only, tokens of every
+/// key in the ring are accepted. Rotating means adding a new active key
+/// and removing the old one once its tokens expired.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    mode: TokenMode,
+    active: String,
+    keys: Arc<HashMap<String, Vec<u8>>>,
+    active_signing: Option<String>,
+    signing_keys: Arc<HashMap<String, Ed25519KeyPair>>,
+}
+
+impl Keyring {
+    /// Fails if the active key is not in the ring or a key does not
+    /// have the 32 bytes PASETO v2 local tokens need
+    pub fn new(
+        active: &str,
+        keys: HashMap<String, Vec<u8>>,
+    ) -> Result<Self, Error> {
+        if !keys.contains_key(active) {
+            return Err(Error::InvalidParameter(
+                "PASETO_ACTIVE_KEY".to_string(),
+            ));
+        }
+        if keys.values().any(|key| key.len() != 32) {
+            return Err(Error::InvalidParameter(
+                "PASETO_KEYS".to_string(),
+            ));
+        }
+
+        Ok(Keyring {
+            mode: TokenMode::Local,
+            active: active.to_string(),
+            keys: Arc::new(keys),
+            active_signing: None,
+            signing_keys: Arc::new(HashMap::new()),
+        })
+    }
+
+    /// Adds the Ed25519 keys of public tokens
+    pub fn with_signing_keys(
+        self,
+        active: &str,
+        keys: HashMap<String, Ed25519KeyPair>,
+    ) -> Result<Self, Error> {
+        if !keys.contains_key(active) {
+            return Err(Error::InvalidParameter(
+                "PASETO_ACTIVE_SIGNING_KEY".to_string(),
+            ));
+        }
+
+        Ok(Keyring {
+            active_signing: Some(active.to_string()),
+            signing_keys: Arc::new(keys),
+            ..self
+        })
+    }
+
+    /// Fails for the public modes if there are no signing keys
+    pub fn with_mode(self, mode: TokenMode) -> Result<Self, Error> {
+        if mode != TokenMode::Local && self.active_signing.is_none() {
+            return Err(Error::InvalidParameter(
+                "PASETO_SIGNING_KEYS".to_string(),
+            ));
+        }
+
+        Ok(Keyring { mode, ..self })
+    }
+
+    /// Reads the keys from `PASETO_KEYS`, e.g. `2026-10:key,2026-04:key`,
+    /// and the id of the active key from `PASETO_ACTIVE_KEY`. Without
+    /// `PASETO_KEYS` the single `PASETO_KEY` is used. The public modes
+    /// need `PASETO_SIGNING_KEYS` with base64url encoded Ed25519 seeds
+    /// in the same format and `PASETO_ACTIVE_SIGNING_KEY`.
+    pub fn from_env(mode: TokenMode) -> Result<Self, Error> {
+        let keyring = match env::var("PASETO_KEYS") {
+            Ok(keys) => Keyring::new(
+                &env_var("PASETO_ACTIVE_KEY")?,
+                parse_keys("PASETO_KEYS", &keys, |key| {
+                    Some(key.as_bytes().to_vec())
+                })?,
+            )?,
+            Err(_) => Keyring::new(
+                DEFAULT_KEY_ID,
+                HashMap::from([(
+                    DEFAULT_KEY_ID.to_string(),
+                    env_var("PASETO_KEY")?.into_bytes(),
+                )]),
+            )?,
+        };
+
+        let keyring = match env::var("PASETO_SIGNING_KEYS") {
+            Ok(keys) => keyring.with_signing_keys(
+                &env_var("PASETO_ACTIVE_SIGNING_KEY")?,
+                parse_keys("PASETO_SIGNING_KEYS", &keys, |seed| {
+                    let seed = URL_SAFE_NO_PAD.decode(seed).ok()?;
+                    Ed25519KeyPair::from_seed_unchecked(&seed).ok()
+                })?,
+            )?,
+            Err(_) => keyring,
+        };
+
+        keyring.with_mode(mode)
+    }
+
+    /// Turns the claims into a token of the token mode, with the id of
+    /// the active key in the footer
+    pub fn seal(&self, message: &str) -> String {
+        let token = match self.mode {
+            TokenMode::Local => paseto::v2::local_paseto(
+                message,
+                Some(&footer(&self.active)),
+                &self.keys[&self.active],
+            ),
+            TokenMode::PublicV2 => {
+                let (kid, key_pair) = self.active_signing_key();
+                paseto::v2::public_paseto(
+                    message,
+                    Some(&footer(kid)),
+                    key_pair,
+                )
+            }
+            TokenMode::PublicV4 => {
+                let (kid, key_pair) = self.active_signing_key();
+                Ok(sign_v4(message, &footer(kid), key_pair))
+            }
+        };
+
+        token.expect("Failed to construct paseto token!")
+    }
+
+    /// Encrypts with the active shared key whatever the token mode, for
+    /// data only the service itself reads back with `open`
+    pub fn encrypt(&self, message: &str) -> String {
+        paseto::v2::local_paseto(
+            message,
+            Some(&footer(&self.active)),
+            &self.keys[&self.active],
+        )
+        .expect("Failed to construct paseto token!")
+    }
+
+    /// The claims of a token issued with any key of the ring, whatever
+    /// the token mode. `None` if the key is unknown or the token was
+    /// changed.
+    pub fn open(&self, token: &str) -> Option<String> {
+        // <version>.<purpose>.<payload>.<footer>
+        let footer = token.splitn(4, '.').nth(3)?;
+        let footer =
+            String::from_utf8(URL_SAFE_NO_PAD.decode(footer).ok()?)
+                .ok()?;
+        let kid = serde_json::from_str::<Footer>(&footer).ok()?.kid;
+
+        if token.starts_with(V2_LOCAL) {
+            let key = self.keys.get(&kid)?;
+            paseto::v2::decrypt_paseto(token, Some(&footer), key).ok()
+        } else if token.starts_with(V2_PUBLIC) {
+            let key_pair = self.signing_keys.get(&kid)?;
+            paseto::v2::verify_paseto(
+                token,
+                Some(&footer),
+                key_pair.public_key().as_ref(),
+            )
+            .ok()
+        } else if token.starts_with(V4_PUBLIC) {
+            let key_pair = self.signing_keys.get(&kid)?;
+            verify_v4(token, &footer, key_pair.public_key().as_ref())
+        } else {
+            None
+        }
+    }
+
+    /// What other services need to verify public tokens
+    pub fn public_keys(&self) -> PublicKeys {
+        let mut keys = self
+            .signing_keys
+            .iter()
+            .map(|(kid, key_pair)| PublicKey {
+                kid: kid.clone(),
+                alg: "Ed25519".to_string(),
+                public_key: URL_SAFE_NO_PAD
+                    .encode(key_pair.public_key().as_ref()),
+                active: self.active_signing.as_ref() == Some(kid),
+            })
+            .collect::<Vec<_>>();
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+
+        PublicKeys {
+            token_mode: self.mode,
+            keys,
+        }
+    }
+
+    fn active_signing_key(&self) -> (&str, &Ed25519KeyPair) {
+        let kid = self
+            .active_signing
+            .as_ref()
+            .expect("public token mode without signing keys");
+        (kid, &self.signing_keys[kid])
+    }
+}
+
+fn env_var(name: &str) -> Result<String, Error> {
+    env::var(name).map_err(|_| Error::InvalidParameter(name.to_string()))
+}
+
+/// Parses `id:key` pairs separated by commas
+fn parse_keys<K>(
+    name: &str,
+    keys: &str,
+    parse: impl Fn(&str) -> Option<K>,
+) -> Result<HashMap<String, K>, Error> {
+    keys.split(',')
+        .map(|entry| {
+            let (id, key) = entry.split_once(':')?;
+            Some((id.trim().to_string(), parse(key.trim())?))
+        })
+        .collect::<Option<HashMap<_, _>>>()
+        .ok_or_else(|| Error::InvalidParameter(name.to_string()))
+}
+
+fn footer(kid: &str) -> String {
+    serde_json::to_string(&Footer {
+        kid: kid.to_string(),
+    })
+    .expect("footer is always serializable")
+}
+
+/// Signs a `v4.public` token. The paseto crate only knows v1 and v2,
+/// v4 signs the same way as v2 but adds the (empty) implicit assertion
+/// to the pre-authentication encoding.
+fn sign_v4(
+    message: &str,
+    footer: &str,
+    key_pair: &Ed25519KeyPair,
+) -> String {
+    let pre_auth = pae(&[
+        V4_PUBLIC.as_bytes(),
+        message.as_bytes(),
+        footer.as_bytes(),
+        b"",
+    ]);
+    let signature = key_pair.sign(&pre_auth);
+
+    let mut payload = message.as_bytes().to_vec();
+    payload.extend_from_slice(signature.as_ref());
+    format!(
+        "{}{}.{}",
+        V4_PUBLIC,
+        URL_SAFE_NO_PAD.encode(payload),
+        URL_SAFE_NO_PAD.encode(footer)
+    )
+}
+
+fn verify_v4(
+    token: &str,
+    footer: &str,
+    public_key: &[u8],
+) -> Option<String> {
+    let payload = token.strip_prefix(V4_PUBLIC)?.split('.').next()?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    if payload.len() < 64 {
+        return None;
+    }
+    let (message, signature) = payload.split_at(payload.len() - 64);
+
+    let pre_auth =
+        pae(&[V4_PUBLIC.as_bytes(), message, footer.as_bytes(), b""]);
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(&pre_auth, signature)
+        .ok()?;
+
+    String::from_utf8(message.to_vec()).ok()
+}
+
+/// Keyring of the tests, with the key the tests always used
+#[cfg(test)]
+pub(crate) fn test_keyring() -> Keyring {
+    Keyring::new(
+        "test",
+        HashMap::from([(
+            "test".to_string(),
+            b"RANDOM WORDS WINTER MACINTOSH PC".to_vec(),
+        )]),
+    )
+    .unwrap()
+}
+
+/// Ed25519 key pair from a fixed seed, for the tests
+#[cfg(test)]
+pub(crate) fn test_signing_key(seed: u8) -> Ed25519KeyPair {
+    Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap()
+}
+
+#[cfg(test)]
+mod keyring_tests {
+    use std::collections::HashMap;
+
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use ring::signature::KeyPair;
+
+    use super::{test_keyring, test_signing_key, Keyring, TokenMode};
+
+    #[test]
+    fn keys() {
+        let old = b"RANDOM WORDS WINTER MACINTOSH PC".to_vec();
+        let new = b"ANOTHER KEY WITH THIRTY TWO BYTE".to_vec();
+        let keys = HashMap::from([
+            ("old".to_string(), old.clone()),
+            ("new".to_string(), new.clone()),
+        ]);
+
+        assert!(Keyring::new("other", keys.clone()).is_err());
+        assert!(Keyring::new(
+            "short",
+            HashMap::from([("short".to_string(), b"short".to_vec())])
+        )
+        .is_err());
+
+        let keyring = Keyring::new("new", keys).unwrap();
+        let token = keyring.seal("message");
+        assert!(token.starts_with("v2.local."));
+        assert_eq!(keyring.open(&token).unwrap(), "message");
+
+        let old_token = paseto::v2::local_paseto(
+            "message",
+            Some(r#"{"kid":"old"}"#),
+            &old,
+        )
+        .unwrap();
+        assert_eq!(keyring.open(&old_token).unwrap(), "message");
+
+        let unknown = paseto::v2::local_paseto(
+            "message",
+            Some(r#"{"kid":"gone"}"#),
+            &old,
+        )
+        .unwrap();
+        assert!(keyring.open(&unknown).is_none());
+        assert!(keyring.open("v2.local.nofooter").is_none());
+    }
+
+    #[test]
+    fn public_tokens() {
+        assert!(test_keyring().with_mode(TokenMode::PublicV4).is_err());
+        assert!(test_keyring()
+            .with_signing_keys(
+                "other",
+                HashMap::from([("ed".to_string(), test_signing_key(1))])
+            )
+            .is_err());
+
+        let keyring = |mode: TokenMode| {
+            test_keyring()
+                .with_signing_keys(
+                    "ed",
+                    HashMap::from([
+                        ("ed".to_string(), test_signing_key(1)),
+                        ("old".to_string(), test_signing_key(2)),
+                    ]),
+                )
+                .unwrap()
+                .with_mode(mode)
+                .unwrap()
+        };
+        let v2 = keyring(TokenMode::PublicV2).seal("message");
+        let v4 = keyring(TokenMode::PublicV4).seal("message");
+        assert!(v2.starts_with("v2.public."));
+        assert!(v4.starts_with("v4.public."));
+
+        // Every mode accepts the tokens of the others
+        let local = keyring(TokenMode::Local);
+        let local_token = local.seal("message");
+        for token in [&v2, &v4, &local_token] {
+            assert_eq!(local.open(token).unwrap(), "message");
+        }
+
+        // The message of public tokens is readable, not changeable
+        let (payload, footer) =
+            v4["v4.public.".len()..].split_once('.').unwrap();
+        let mut payload = URL_SAFE_NO_PAD.decode(payload).unwrap();
+        payload[0] ^= 1;
+        let changed = format!(
+            "v4.public.{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            footer
+        );
+        assert!(local.open(&changed).is_none());
+
+        let public_keys = local.public_keys();
+        assert_eq!(public_keys.token_mode, TokenMode::Local);
+        assert_eq!(public_keys.keys.len(), 2);
+        assert_eq!(public_keys.keys[0].kid, "ed");
+        assert!(public_keys.keys[0].active);
+        assert!(!public_keys.keys[1].active);
+        assert_eq!(
+            public_keys.keys[0].public_key,
+            URL_SAFE_NO_PAD.encode(test_signing_key(1).public_key())
+        );
+    }
+}
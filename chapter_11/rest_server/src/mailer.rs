@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+
+use error_handlers::Error;
+
+/// An email to a single recipient
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers the emails the service sends, like password reset links
+#[async_trait]
+pub trait Mailer: std::fmt::Debug + Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), Error>;
+}
+
+/// Stand-in for an SMTP server which writes every mail into a file in
+/// a local directory, so mails can be read without a mail server
+#[derive(Debug, Clone)]
+pub struct LocalMailer {
+    dir: PathBuf,
+}
+
+impl LocalMailer {
+    /// The directory is created with the first mail
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LocalMailer { dir: dir.into() }
+    }
+
+    /// Every mail in the directory, oldest first
+    #[allow(dead_code)]
+    pub async fn mails(&self) -> Result<Vec<Mail>, Error> {
+        let mut files = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Vec::new())
+            }
+            Err(e) => return Err(Error::MailError(e.to_string())),
+        };
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| Error::MailError(e.to_string()))?
+        {
+            files.push(entry.path());
+        }
+        // File names start with the time the mail was sent
+        files.sort();
+
+        let mut mails = Vec::new();
+        for file in files {
+            let content = tokio::fs::read_to_string(&file)
+                .await
+                .map_err(|e| Error::MailError(e.to_string()))?;
+            let (headers, body) =
+                content.split_once("\n\n").unwrap_or((&content, ""));
+            let header = |name: &str| {
+                headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            mails.push(Mail {
+                to: header("To: "),
+                subject: header("Subject: "),
+                body: body.to_string(),
+            });
+        }
+
+        Ok(mails)
+    }
+}
+
+#[async_trait]
+impl Mailer for LocalMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| Error::MailError(e.to_string()))?;
+
+        let file = self.dir.join(format!(
+            "{:020}-{}.eml",
+            Utc::now().timestamp_micros(),
+            Uuid::new_v4()
+        ));
+        let content = format!(
+            "To: {}\nSubject: {}\n\n{}",
+            mail.to, mail.subject, mail.body
+        );
+        tokio::fs::write(&file, content)
+            .await
+            .map_err(|e| Error::MailError(e.to_string()))?;
+
+        tracing::info!(
+            "Mail to {} written to {}",
+            mail.to,
+            file.display()
+        );
+        Ok(())
+    }
+}
+
+/// Mailer for the route tests, writing into a new temporary directory
+#[cfg(test)]
+pub(crate) fn test_mailer() -> std::sync::Arc<dyn Mailer> {
+    let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::sync::Arc::new(LocalMailer::new(dir))
+}
+
+#[cfg(test)]
+mod mailer_tests {
+    use super::{LocalMailer, Mail, Mailer};
+
+    #[tokio::test]
+    async fn local_mailer() {
+        let dir =
+            std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let mailer = LocalMailer::new(&dir);
+        assert!(mailer.mails().await.unwrap().is_empty());
+
+        for subject in ["First", "Second"] {
+            mailer
+                .send(Mail {
+                    to: "test@example.com".to_string(),
+                    subject: subject.to_string(),
+                    body: "Hello\n\nWorld".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let mails = mailer.mails().await.unwrap();
+        assert_eq!(mails.len(), 2);
+        assert_eq!(mails[0].subject, "First");
+        assert_eq!(mails[1].to, "test@example.com");
+        assert_eq!(mails[1].body, "Hello\n\nWorld");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
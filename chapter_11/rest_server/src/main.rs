@@ -1,17 +1,44 @@
 #![warn(clippy::all)]
+// The route tree is deeper than the compiler allows by default
+#![recursion_limit = "256"]
 
-use clap::Parser;
-//// use dotenv::dotenv;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{ArgEnum, Parser};
 use tracing_subscriber::fmt::format::FmtSpan;
-use warp::{http::Method, Filter};
+use warp::{http::Method, Filter, Reply};
 
 use error_handlers::return_error;
 
+use crate::circuit_breaker::BreakerConfig;
+use crate::keyring::{Keyring, TokenMode};
+use crate::mailer::{LocalMailer, Mailer};
+use crate::moderation_cache::{CacheConfig, CachedModerator};
+use crate::oidc::{Oidc, OidcConfig};
+use crate::profanity::{
+    ApiLayerConfig, ApiLayerModerator, CircuitBreakerModerator,
+    ContentModerator, DegradedPolicy, DictionaryModerator, ModerationMode,
+};
+use crate::routes::authorization::{
+    require_login, require_role, require_scope, require_verified,
+};
+use crate::store::{
+    InMemoryStore, ModerationCacheStore, PostgresStore, SqliteStore, Store,
+};
+use crate::types::account::Role;
+use crate::types::api_key::Scope;
+use crate::types::pagination::PaginationConfig;
+
+mod circuit_breaker;
+mod keyring;
+mod mailer;
+mod moderation_cache;
+mod oidc;
 mod profanity;
 mod routes;
 mod store;
 mod types;
-mod config;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -37,6 +64,103 @@ struct Args {
     /// PORT number for the database connection
     #[clap(long, default_value = "8080")]
     port: u16,
+    /// Where questions, answers and accounts are stored
+    #[clap(long, arg_enum, default_value = "database")]
+    store: StoreBackend,
+    /// Largest page size clients may ask for with `limit`
+    #[clap(long, default_value = "100")]
+    max_limit: i32,
+    /// Directory the mails of the service are written to, there is no
+    /// mail server
+    #[clap(long, default_value = "mail")]
+    mail_dir: String,
+    /// Whether access tokens are encrypted with the shared key or
+    /// signed, so other services can verify them
+    #[clap(long, arg_enum, default_value = "local")]
+    token_mode: TokenMode,
+    /// Discovery document of the OpenID Connect provider, e.g.
+    /// https://idp.example.com/.well-known/openid-configuration. Without
+    /// it there is no login through the provider.
+    #[clap(long)]
+    oidc_discovery_url: Option<String>,
+    /// Client id of the service at the provider, the secret is read from
+    /// OIDC_CLIENT_SECRET
+    #[clap(long)]
+    oidc_client_id: Option<String>,
+    /// `/oidc/callback` as the browser reaches the service
+    #[clap(long, default_value = "http://localhost:8080/oidc/callback")]
+    oidc_redirect_url: String,
+    /// How questions and answers are checked for bad words
+    #[clap(long, arg_enum, default_value = "api-layer")]
+    moderation: ModerationBackend,
+    /// Word list of the dictionary moderation, a word per line. Can be
+    /// given several times.
+    #[clap(
+        long,
+        default_value = "bad_words.txt",
+        multiple_occurrences = true
+    )]
+    moderation_word_list: Vec<String>,
+    /// Where the APILayer API is, API_LAYER_URL takes precedence
+    #[clap(long, default_value = "https://api.apilayer.com")]
+    moderation_url: String,
+    /// Milliseconds to connect to the moderation API
+    #[clap(long, default_value = "2000")]
+    moderation_connect_timeout_ms: u64,
+    /// Milliseconds the moderation API may keep the service waiting for
+    /// the next part of an answer
+    #[clap(long, default_value = "5000")]
+    moderation_read_timeout_ms: u64,
+    /// Retries of a failed or timed out moderation request
+    #[clap(long, default_value = "3")]
+    moderation_retries: u32,
+    /// Milliseconds before the first retry, doubling with each retry
+    #[clap(long, default_value = "100")]
+    moderation_min_backoff_ms: u64,
+    /// Longest wait between two retries in milliseconds
+    #[clap(long, default_value = "2000")]
+    moderation_max_backoff_ms: u64,
+    /// Failed moderation requests in a row after which the API is not
+    /// called for a while
+    #[clap(long, default_value = "5")]
+    moderation_breaker_failures: u32,
+    /// Milliseconds the API is not called after too many failures
+    #[clap(long, default_value = "30000")]
+    moderation_breaker_open_ms: u64,
+    /// What happens to posts while the moderation API is unavailable
+    #[clap(long, arg_enum, default_value = "reject")]
+    moderation_degraded: DegradedPolicy,
+    /// Moderations kept in memory, 0 turns the cache off
+    #[clap(long, default_value = "1000")]
+    moderation_cache_size: usize,
+    /// Seconds a moderation is reused
+    #[clap(long, default_value = "3600")]
+    moderation_cache_ttl_secs: u64,
+    /// Share the moderations with the other replicas through the
+    /// database
+    #[clap(long)]
+    moderation_shared_cache: bool,
+    /// Whether posts with bad words are censored or held back for the
+    /// moderators
+    #[clap(long, arg_enum, default_value = "censor")]
+    moderation_mode: ModerationMode,
+}
+
+#[derive(ArgEnum, Clone, Debug, PartialEq)]
+enum StoreBackend {
+    /// The database behind DATABASE_URL, Postgres or SQLite depending
+    /// on the URL scheme
+    Database,
+    /// In memory, lost on restart
+    Memory,
+}
+
+#[derive(ArgEnum, Clone, Debug, PartialEq)]
+enum ModerationBackend {
+    /// The bad_words API of APILayer, with BAD_WORDS_API_KEY
+    ApiLayer,
+    /// The word lists of `--moderation-word-list`, no API key needed
+    Dictionary,
 }
 
 #[tokio::main]
@@ -54,50 +178,75 @@ async fn main() -> Result<(), error_handlers::Error> {
     tracing::info!("Reading .env file for environment variables...");
     dotenv::dotenv().ok();
 
-    if let Err(_) = std::env::var("BAD_WORDS_API_KEY") {
-        panic!("BadWords API key not set");
-    }
+    let moderator: Arc<dyn ContentModerator> = match args.moderation {
+        // Built once, its connections are shared by every request
+        ModerationBackend::ApiLayer => {
+            let api_layer =
+                Arc::new(ApiLayerModerator::new(ApiLayerConfig {
+                    url: std::env::var("API_LAYER_URL")
+                        .unwrap_or_else(|_| args.moderation_url.clone()),
+                    api_key: std::env::var("BAD_WORDS_API_KEY")
+                        .expect("BadWords API key not set"),
+                    connect_timeout: Duration::from_millis(
+                        args.moderation_connect_timeout_ms,
+                    ),
+                    read_timeout: Duration::from_millis(
+                        args.moderation_read_timeout_ms,
+                    ),
+                    max_retries: args.moderation_retries,
+                    min_backoff: Duration::from_millis(
+                        args.moderation_min_backoff_ms,
+                    ),
+                    max_backoff: Duration::from_millis(
+                        args.moderation_max_backoff_ms,
+                    ),
+                })?);
+            let fallback = match args.moderation_degraded {
+                DegradedPolicy::Local => {
+                    Some(DictionaryModerator::from_files(
+                        &args.moderation_word_list,
+                    )?)
+                }
+                _ => None,
+            };
+            Arc::new(CircuitBreakerModerator::new(
+                api_layer,
+                BreakerConfig {
+                    failure_threshold: args.moderation_breaker_failures,
+                    open_duration: Duration::from_millis(
+                        args.moderation_breaker_open_ms,
+                    ),
+                },
+                args.moderation_degraded,
+                fallback,
+            ))
+        }
+        ModerationBackend::Dictionary => Arc::new(
+            DictionaryModerator::from_files(&args.moderation_word_list)?,
+        ),
+    };
 
-    if let Err(_) = std::env::var("PASETO_KEY") {
-        panic!("PASETO key not set");
-    }
+    // Loaded once, tokens are issued with the active key
+    let keyring = Keyring::from_env(args.token_mode)?;
 
-    if let Err(_) = std::env::var("DATABASE_URL") {
-        panic!("DATABASE_URL key not set");
-    }
+    // The provider has to be up, its endpoints are only discovered once
+    let oidc = match args.oidc_discovery_url.clone() {
+        Some(discovery_url) => Some(
+            Oidc::discover(OidcConfig::from_env(
+                discovery_url,
+                args.oidc_client_id.clone(),
+                args.oidc_redirect_url.clone(),
+            )?)
+            .await?,
+        ),
+        None => None,
+    };
 
     let port = std::env::var("PORT")
         .ok()
         .map(|val| val.parse::<u16>())
         .unwrap_or(Ok(8080))
-        .map_err(|e| error_handlers::Error::ParseError(e))?;
-
-    let default_db_url = format!(
-        "postgres://{}:{}@{}:{}/{}",
-        args.database_username,
-        args.database_password,
-        args.database_host,
-        args.database_port,
-        args.database_name
-    );
-
-    let db_url =
-        dotenv::var("DATABASE_URL").ok().unwrap_or(default_db_url);
-    println!("Database URL: {}", db_url);
-
-    println!("Connecting to the database...");
-    let store = store::Store::new(&db_url)
-        .await
-        .map_err(|e| error_handlers::Error::DatabaseQueryError(e))?;
-
-    println!("Migrating the database...");
-    sqlx::migrate!()
-        .run(&store.clone().connection)
-        .await
-        .expect("Cannot run migrations");
-    println!("Finished migrating the database!");
-
-    let store_filter = warp::any().map(move || store.clone());
+        .map_err(error_handlers::Error::ParseError)?;
 
     tracing_subscriber::fmt()
         // Use the filter we built above to determine which traces to record.
@@ -107,6 +256,203 @@ async fn main() -> Result<(), error_handlers::Error> {
         .with_span_events(FmtSpan::CLOSE)
         .init();
 
+    let pagination = PaginationConfig {
+        max_limit: args.max_limit,
+    };
+    let mailer: Arc<dyn Mailer> =
+        Arc::new(LocalMailer::new(&args.mail_dir));
+
+    match args.store {
+        StoreBackend::Database => {
+            if std::env::var("DATABASE_URL").is_err() {
+                panic!("DATABASE_URL key not set");
+            }
+
+            let default_db_url = format!(
+                "postgres://{}:{}@{}:{}/{}",
+                args.database_username,
+                args.database_password,
+                args.database_host,
+                args.database_port,
+                args.database_name
+            );
+
+            let db_url =
+                dotenv::var("DATABASE_URL").ok().unwrap_or(default_db_url);
+            println!("Database URL: {}", db_url);
+
+            println!("Connecting to the database...");
+            match db_url.split(':').next() {
+                Some("sqlite") => {
+                    let store = SqliteStore::new(&db_url).await.map_err(
+                        error_handlers::Error::DatabaseQueryError,
+                    )?;
+
+                    println!("Migrating the database...");
+                    sqlx::migrate!("./migrations_sqlite")
+                        .run(&store.connection)
+                        .await
+                        .expect("Cannot run migrations");
+                    println!("Finished migrating the database!");
+
+                    let moderator = cached_moderator(
+                        moderator,
+                        &args,
+                        Some(Arc::new(store.clone())),
+                    );
+                    serve(
+                        store,
+                        port,
+                        pagination,
+                        mailer,
+                        moderator,
+                        args.moderation_mode,
+                        keyring,
+                        oidc,
+                    )
+                    .await;
+                }
+                Some("postgres") | Some("postgresql") => {
+                    let store =
+                        PostgresStore::new(&db_url).await.map_err(
+                            error_handlers::Error::DatabaseQueryError,
+                        )?;
+
+                    println!("Migrating the database...");
+                    sqlx::migrate!()
+                        .run(&store.clone().connection)
+                        .await
+                        .expect("Cannot run migrations");
+                    println!("Finished migrating the database!");
+
+                    let moderator = cached_moderator(
+                        moderator,
+                        &args,
+                        Some(Arc::new(store.clone())),
+                    );
+                    serve(
+                        store,
+                        port,
+                        pagination,
+                        mailer,
+                        moderator,
+                        args.moderation_mode,
+                        keyring,
+                        oidc,
+                    )
+                    .await;
+                }
+                _ => panic!("Unsupported database URL: {}", db_url),
+            }
+        }
+        StoreBackend::Memory => {
+            println!("Using the in-memory store, data is lost on restart");
+            let moderator = cached_moderator(moderator, &args, None);
+            serve(
+                InMemoryStore::new(),
+                port,
+                pagination,
+                mailer,
+                moderator,
+                args.moderation_mode,
+                keyring,
+                oidc,
+            )
+            .await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Puts the moderation cache in front of the moderator, shared through
+/// the database with `--moderation-shared-cache`
+fn cached_moderator(
+    moderator: Arc<dyn ContentModerator>,
+    args: &Args,
+    database: Option<Arc<dyn ModerationCacheStore>>,
+) -> Arc<dyn ContentModerator> {
+    let shared = match database {
+        Some(database) if args.moderation_shared_cache => Some(database),
+        None if args.moderation_shared_cache => {
+            panic!("The shared moderation cache needs a database");
+        }
+        _ => None,
+    };
+    if args.moderation_cache_size == 0 && shared.is_none() {
+        return moderator;
+    }
+
+    Arc::new(CachedModerator::new(
+        moderator,
+        CacheConfig {
+            capacity: args.moderation_cache_size,
+            ttl: Duration::from_secs(args.moderation_cache_ttl_secs),
+        },
+        shared,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn serve<S: Store>(
+    store: S,
+    port: u16,
+    pagination: PaginationConfig,
+    mailer: Arc<dyn Mailer>,
+    moderator: Arc<dyn ContentModerator>,
+    moderation_mode: ModerationMode,
+    keyring: Keyring,
+    oidc: Option<Oidc>,
+) {
+    let routes = build_routes(
+        store,
+        pagination,
+        mailer,
+        moderator,
+        moderation_mode,
+        keyring,
+        oidc,
+    );
+
+    tracing::info!(
+        "Q&A service build ID {}",
+        env!("RUST_WEB_DEV_VERSION")
+    );
+
+    warp::serve(routes).run(([0, 0, 0, 0], port)).await;
+}
+
+fn build_routes<S: Store>(
+    store: S,
+    pagination: PaginationConfig,
+    mailer: Arc<dyn Mailer>,
+    moderator: Arc<dyn ContentModerator>,
+    moderation_mode: ModerationMode,
+    keyring: Keyring,
+    oidc: Option<Oidc>,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+{
+    let auth =
+        routes::authentication::auth(store.clone(), keyring.clone());
+    // API keys only get to the routes of their scopes
+    let logged_in = require_login(auth.clone());
+    // Accounts have to verify their email address before they post
+    let posting = require_verified(auth.clone());
+    let questions_write =
+        require_scope(posting.clone(), Scope::QuestionsWrite);
+    let answers_write = require_scope(posting, Scope::AnswersWrite);
+    let store_filter = warp::any().map(move || store.clone());
+    let pagination_filter = warp::any().map(move || pagination);
+    let mailer_filter = warp::any().map(move || mailer.clone());
+    let moderator_filter = warp::any().map(move || moderator.clone());
+    let moderation_mode_filter = warp::any().map(move || moderation_mode);
+    let keyring_filter = warp::any().map(move || keyring.clone());
+    // The OIDC routes are not found without a provider
+    let oidc_filter = warp::any().and_then(move || {
+        let oidc = oidc.clone();
+        async move { oidc.ok_or_else(warp::reject::not_found) }
+    });
+
     let cors = warp::cors()
         .allow_any_origin()
         .allow_header("content-type")
@@ -121,15 +467,27 @@ async fn main() -> Result<(), error_handlers::Error> {
         .and(warp::path("questions"))
         .and(warp::path::end())
         .and(warp::query())
+        .and(pagination_filter)
         .and(store_filter.clone())
         .and_then(routes::question::get_questions);
 
+    let get_question = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination_filter)
+        .and(store_filter.clone())
+        .and_then(routes::question::get_question);
+
     let update_question = warp::put()
         .and(warp::path("questions"))
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(questions_write.clone())
         .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(moderation_mode_filter)
         .and(warp::body::json())
         .and_then(routes::question::update_question);
 
@@ -137,57 +495,429 @@ async fn main() -> Result<(), error_handlers::Error> {
         .and(warp::path("questions"))
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(require_scope(auth.clone(), Scope::QuestionsWrite))
         .and(store_filter.clone())
         .and_then(routes::question::delete_question);
 
     let add_question = warp::post()
         .and(warp::path("questions"))
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(questions_write.clone())
         .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(moderation_mode_filter)
         .and(warp::body::json())
         .and_then(routes::question::add_question);
 
+    let get_answers = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination_filter)
+        .and(store_filter.clone())
+        .and_then(routes::answer::get_answers);
+
+    let get_answer = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(routes::answer::get_answer);
+
     let add_answer = warp::post()
         .and(warp::path("answers"))
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(answers_write.clone())
         .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(moderation_mode_filter)
         .and(warp::body::form())
         .and_then(routes::answer::add_answer);
 
+    let update_answer = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(answers_write.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(moderation_mode_filter)
+        .and(warp::body::json())
+        .and_then(routes::answer::update_answer);
+
+    let delete_answer = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(require_scope(auth.clone(), Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and_then(routes::answer::delete_answer);
+
+    let vote_question = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(questions_write.clone())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::vote::vote_question);
+
+    let retract_question_vote = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(questions_write.clone())
+        .and(store_filter.clone())
+        .and_then(routes::vote::retract_question_vote);
+
+    let vote_answer = warp::post()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(answers_write.clone())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::vote::vote_answer);
+
+    let retract_answer_vote = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(answers_write.clone())
+        .and(store_filter.clone())
+        .and_then(routes::vote::retract_answer_vote);
+
+    let accept_answer = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(questions_write.clone())
+        .and(store_filter.clone())
+        .and_then(routes::question::accept_answer);
+
+    let unaccept_answer = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(questions_write.clone())
+        .and(store_filter.clone())
+        .and_then(routes::question::unaccept_answer);
+
+    let search = warp::get()
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination_filter)
+        .and(store_filter.clone())
+        .and_then(routes::search::search);
+
+    let get_tags = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(routes::tag::get_tags);
+
+    let status = warp::get()
+        .and(warp::path("status"))
+        .and(warp::path::end())
+        .and(moderator_filter)
+        .and_then(routes::status::get_status);
+
+    let get_reviews = warp::get()
+        .and(warp::path("reviews"))
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Moderator))
+        .and(store_filter.clone())
+        .and_then(routes::review::get_reviews);
+
+    let approve_review = warp::post()
+        .and(warp::path("reviews"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("approve"))
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Moderator))
+        .and(store_filter.clone())
+        .and_then(routes::review::approve_review);
+
+    let edit_review = warp::put()
+        .and(warp::path("reviews"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Moderator))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::review::edit_review);
+
+    let reject_review = warp::post()
+        .and(warp::path("reviews"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("reject"))
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Moderator))
+        .and(store_filter.clone())
+        .and_then(routes::review::reject_review);
+
     let registration = warp::post()
         .and(warp::path("registration"))
         .and(warp::path::end())
         .and(store_filter.clone())
+        .and(mailer_filter.clone())
         .and(warp::body::json())
         .and_then(routes::authentication::register);
 
+    let verify = warp::get()
+        .and(warp::path("verify"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::authentication::verify);
+
+    let resend_verification = warp::post()
+        .and(warp::path("verify"))
+        .and(warp::path("resend"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and_then(routes::authentication::resend_verification);
+
     let login = warp::post()
         .and(warp::path("login"))
         .and(warp::path::end())
         .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(warp::addr::remote())
+        .and(warp::header::optional("user-agent"))
         .and(warp::body::json())
         .and_then(routes::authentication::login);
 
-    let routes = get_questions
+    let forgot_password = warp::post()
+        .and(warp::path("password"))
+        .and(warp::path("forgot"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(mailer_filter)
+        .and(warp::body::json())
+        .and_then(routes::password::forgot_password);
+
+    let reset_password = warp::post()
+        .and(warp::path("password"))
+        .and(warp::path("reset"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::password::reset_password);
+
+    let change_password = warp::put()
+        .and(warp::path("account"))
+        .and(warp::path("password"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::password::change_password);
+
+    let get_accounts = warp::get()
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(routes::account::get_accounts);
+
+    let set_role = warp::put()
+        .and(warp::path("accounts"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::account::set_role);
+
+    let delete_account = warp::delete()
+        .and(warp::path("accounts"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(require_role(logged_in.clone(), Role::Admin))
+        .and(store_filter.clone())
+        .and_then(routes::account::delete_account);
+
+    let refresh = warp::post()
+        .and(warp::path("token"))
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::authentication::refresh);
+
+    let public_keys = warp::get()
+        .and(warp::path(".well-known"))
+        .and(warp::path("paseto-keys"))
+        .and(warp::path::end())
+        .and(keyring_filter.clone())
+        .and_then(routes::authentication::public_keys);
+
+    let oidc_login = warp::get()
+        .and(warp::path("oidc"))
+        .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(oidc_filter.clone())
+        .and(keyring_filter.clone())
+        .and_then(routes::oidc::login);
+
+    let oidc_callback = warp::get()
+        .and(warp::path("oidc"))
+        .and(warp::path("callback"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::cookie::optional("oidc_login"))
+        .and(store_filter.clone())
+        .and(keyring_filter)
+        .and(oidc_filter)
+        .and(warp::addr::remote())
+        .and(warp::header::optional("user-agent"))
+        .and_then(routes::oidc::callback);
+
+    let logout = warp::post()
+        .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and_then(routes::authentication::logout);
+
+    let get_sessions = warp::get()
+        .and(warp::path("account"))
+        .and(warp::path("sessions"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and_then(routes::session::get_sessions);
+
+    let delete_session = warp::delete()
+        .and(warp::path("account"))
+        .and(warp::path("sessions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and_then(routes::session::delete_session);
+
+    let delete_other_sessions = warp::delete()
+        .and(warp::path("account"))
+        .and(warp::path("sessions"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and_then(routes::session::delete_other_sessions);
+
+    let add_api_key = warp::post()
+        .and(warp::path("account"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::api_key::add_api_key);
+
+    let get_api_keys = warp::get()
+        .and(warp::path("account"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::end())
+        .and(logged_in.clone())
+        .and(store_filter.clone())
+        .and_then(routes::api_key::get_api_keys);
+
+    let delete_api_key = warp::delete()
+        .and(warp::path("account"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(logged_in)
+        .and(store_filter)
+        .and_then(routes::api_key::delete_api_key);
+
+    let questions = get_questions
+        .or(get_question)
         .or(update_question)
         .or(add_question)
         .or(delete_question)
+        .or(get_answers)
+        .or(get_answer)
         .or(add_answer)
-        .or(registration)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(vote_question)
+        .or(retract_question_vote)
+        .or(vote_answer)
+        .or(retract_answer_vote)
+        .or(accept_answer)
+        .or(unaccept_answer)
+        .or(search)
+        .or(get_tags)
+        .or(status)
+        .or(get_reviews)
+        .or(approve_review)
+        .or(edit_review)
+        .or(reject_review)
+        // Boxed in groups, unboxed the request future is too large for
+        // the stack of a test thread
+        .boxed();
+
+    let accounts = registration
+        .or(verify)
+        .or(resend_verification)
         .or(login)
+        .or(refresh)
+        .or(public_keys)
+        .or(oidc_login)
+        .or(oidc_callback)
+        .or(logout)
+        .or(forgot_password)
+        .or(reset_password)
+        .or(change_password)
+        .or(get_accounts)
+        .or(set_role)
+        .or(delete_account)
+        .or(get_sessions)
+        .or(delete_session)
+        .or(delete_other_sessions)
+        .or(add_api_key)
+        .or(get_api_keys)
+        .or(delete_api_key)
+        .boxed();
+
+    questions
+        .or(accounts)
         .with(cors)
         .with(warp::trace::request())
-        .recover(return_error);
-
-    tracing::info!(
-        "Q&A service build ID {}",
-        env!("RUST_WEB_DEV_VERSION")
-    );
-
-    warp::serve(routes).run(([0, 0, 0, 0], port)).await;
+        .recover(return_error)
+}
 
-    Ok(())
+/// Routes of the tests, with the test mailer, moderator and keyring,
+/// censoring and without OIDC
+#[cfg(test)]
+fn test_routes<S: Store>(
+    store: S,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+{
+    build_routes(
+        store,
+        PaginationConfig::default(),
+        mailer::test_mailer(),
+        profanity::test_moderator(),
+        ModerationMode::Censor,
+        keyring::test_keyring(),
+        None,
+    )
 }
@@ -0,0 +1,350 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hashlink::LruCache;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use error_handlers::Error;
+
+use crate::profanity::{ContentModerator, Moderation, ModerationStatus};
+use crate::store::ModerationCacheStore;
+
+/// How many moderations are kept and for how long
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    pub capacity: usize,
+    pub ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            capacity: 1000,
+            ttl: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CacheStatus {
+    pub entries: usize,
+    /// Found in the cache of this instance
+    pub hits: u64,
+    /// Found in the cache shared by the replicas
+    pub shared_hits: u64,
+    pub misses: u64,
+}
+
+/// Remembers the moderations of contents by their hash, so unchanged
+/// titles and contents are not sent to the moderation service again.
+/// The least recently used moderations make room for new ones. With a
+/// shared store the replicas reuse the moderations of each other.
+#[derive(Debug)]
+pub struct CachedModerator {
+    moderator: Arc<dyn ContentModerator>,
+    ttl: Duration,
+    entries: Mutex<LruCache<String, (Instant, Moderation)>>,
+    shared: Option<Arc<dyn ModerationCacheStore>>,
+    hits: AtomicU64,
+    shared_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CachedModerator {
+    pub fn new(
+        moderator: Arc<dyn ContentModerator>,
+        config: CacheConfig,
+        shared: Option<Arc<dyn ModerationCacheStore>>,
+    ) -> Self {
+        CachedModerator {
+            moderator,
+            ttl: config.ttl,
+            entries: Mutex::new(LruCache::new(config.capacity)),
+            shared,
+            hits: AtomicU64::new(0),
+            shared_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self, content_hash: &str) -> Option<Moderation> {
+        let mut entries = self.entries.lock().expect("Cache poisoned");
+        match entries.get(content_hash) {
+            Some((cached_on, moderation))
+                if cached_on.elapsed() < self.ttl =>
+            {
+                Some(moderation.clone())
+            }
+            Some(_) => {
+                entries.remove(content_hash);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, content_hash: String, moderation: Moderation) {
+        self.entries
+            .lock()
+            .expect("Cache poisoned")
+            .insert(content_hash, (Instant::now(), moderation));
+    }
+
+    /// Failures of the shared cache are misses, posting works without it
+    async fn get_shared(&self, content_hash: &str) -> Option<Moderation> {
+        let shared = self.shared.as_ref()?;
+        let fresh_after = Utc::now().naive_utc() - self.chrono_ttl();
+        match shared.get_moderation(content_hash, fresh_after).await {
+            Ok(moderation) => moderation
+                .and_then(|json| serde_json::from_str(&json).ok()),
+            Err(e) => {
+                tracing::warn!(
+                    "Shared moderation cache unavailable: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    async fn put_shared(
+        &self,
+        content_hash: &str,
+        moderation: &Moderation,
+    ) {
+        let Some(shared) = self.shared.as_ref() else {
+            return;
+        };
+        let now = Utc::now().naive_utc();
+        let json = serde_json::to_string(moderation)
+            .expect("Cannot serialize moderation");
+        if let Err(e) = shared
+            .put_moderation(
+                content_hash,
+                &json,
+                now,
+                now - self.chrono_ttl(),
+            )
+            .await
+        {
+            tracing::warn!("Shared moderation cache unavailable: {}", e);
+        }
+    }
+
+    fn chrono_ttl(&self) -> chrono::Duration {
+        chrono::Duration::from_std(self.ttl)
+            .unwrap_or(chrono::Duration::MAX)
+    }
+
+    /// Counts the lookup and traces the counters
+    fn record(&self, outcome: &str, counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        tracing::info!(
+            target: "moderation_cache",
+            hits = self.hits.load(Ordering::Relaxed),
+            shared_hits = self.shared_hits.load(Ordering::Relaxed),
+            misses = self.misses.load(Ordering::Relaxed),
+            "Moderation cache {}",
+            outcome
+        );
+    }
+
+    pub fn cache_status(&self) -> CacheStatus {
+        CacheStatus {
+            entries: self.entries.lock().expect("Cache poisoned").len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            shared_hits: self.shared_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// SHA-256 of the content, base64url encoded
+fn content_hash(content: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(content.as_bytes()))
+}
+
+#[async_trait]
+impl ContentModerator for CachedModerator {
+    async fn moderate(
+        &self,
+        content: String,
+    ) -> Result<Moderation, Error> {
+        let content_hash = content_hash(&content);
+        if let Some(moderation) = self.get(&content_hash) {
+            self.record("hit", &self.hits);
+            return Ok(moderation);
+        }
+        if let Some(moderation) = self.get_shared(&content_hash).await {
+            self.record("shared hit", &self.shared_hits);
+            self.insert(content_hash, moderation.clone());
+            return Ok(moderation);
+        }
+        self.record("miss", &self.misses);
+
+        let moderation = self.moderator.moderate(content).await?;
+        // Stand-ins while the service is down are not remembered
+        if !moderation.degraded {
+            self.put_shared(&content_hash, &moderation).await;
+            self.insert(content_hash, moderation.clone());
+        }
+        Ok(moderation)
+    }
+
+    fn status(&self) -> ModerationStatus {
+        ModerationStatus {
+            cache: Some(self.cache_status()),
+            ..self.moderator.status()
+        }
+    }
+}
+
+#[cfg(test)]
+mod moderation_cache_tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use super::{CacheConfig, CachedModerator};
+    use crate::profanity::{
+        ContentModerator, DictionaryModerator, Moderation,
+        ModerationStatus,
+    };
+    use crate::store::SqliteStore;
+    use error_handlers::Error;
+
+    /// Dictionary which counts how often it is asked
+    #[derive(Debug)]
+    struct Counting {
+        calls: AtomicU64,
+        degraded: bool,
+    }
+
+    impl Counting {
+        fn new(degraded: bool) -> Arc<Self> {
+            Arc::new(Counting {
+                calls: AtomicU64::new(0),
+                degraded,
+            })
+        }
+
+        fn calls(&self) -> u64 {
+            self.calls.load(Ordering::Relaxed)
+        }
+    }
+
+    #[async_trait]
+    impl ContentModerator for Counting {
+        async fn moderate(
+            &self,
+            content: String,
+        ) -> Result<Moderation, Error> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let moderation = DictionaryModerator::new(vec!["shit".into()])
+                .moderate(content)
+                .await?;
+            Ok(Moderation {
+                degraded: self.degraded,
+                ..moderation
+            })
+        }
+
+        fn status(&self) -> ModerationStatus {
+            DictionaryModerator::new(Vec::new()).status()
+        }
+    }
+
+    async fn censor(moderator: &CachedModerator, content: &str) -> String {
+        moderator
+            .moderate(content.to_string())
+            .await
+            .unwrap()
+            .censored_content
+    }
+
+    #[tokio::test]
+    async fn cache() {
+        let counting = Counting::new(false);
+        let moderator = CachedModerator::new(
+            counting.clone(),
+            CacheConfig {
+                capacity: 2,
+                ttl: Duration::from_millis(100),
+            },
+            None,
+        );
+
+        assert_eq!(censor(&moderator, "shit").await, "****");
+        assert_eq!(censor(&moderator, "shit").await, "****");
+        assert_eq!(counting.calls(), 1);
+
+        // The least recently used content makes room
+        censor(&moderator, "a").await;
+        censor(&moderator, "shit").await;
+        censor(&moderator, "b").await;
+        assert_eq!(counting.calls(), 3);
+        censor(&moderator, "shit").await;
+        assert_eq!(counting.calls(), 3);
+        censor(&moderator, "a").await;
+        assert_eq!(counting.calls(), 4);
+
+        // and the expired one
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        censor(&moderator, "shit").await;
+        assert_eq!(counting.calls(), 5);
+
+        let status = moderator.status();
+        assert_eq!(status.backend, "dictionary");
+        let cache = status.cache.unwrap();
+        assert_eq!((cache.hits, cache.misses), (3, 5));
+        assert_eq!(cache.entries, 2);
+
+        // Stand-ins of the degraded policy are not cached
+        let counting = Counting::new(true);
+        let moderator = CachedModerator::new(
+            counting.clone(),
+            CacheConfig::default(),
+            None,
+        );
+        censor(&moderator, "shit").await;
+        censor(&moderator, "shit").await;
+        assert_eq!(counting.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn shared() {
+        let store = SqliteStore::new("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&store.connection)
+            .await
+            .unwrap();
+        let store = Arc::new(store);
+
+        let counting = Counting::new(false);
+        let replica = || {
+            CachedModerator::new(
+                counting.clone(),
+                CacheConfig::default(),
+                Some(store.clone()),
+            )
+        };
+        let (first, second) = (replica(), replica());
+
+        assert_eq!(censor(&first, "shit happens").await, "**** happens");
+        assert_eq!(censor(&second, "shit happens").await, "**** happens");
+        assert_eq!(censor(&second, "shit happens").await, "**** happens");
+        assert_eq!(counting.calls(), 1);
+        let cache = second.cache_status();
+        assert_eq!(
+            (cache.hits, cache.shared_hits, cache.misses),
+            (1, 1, 0)
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use std::env;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ED25519,
+    RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use error_handlers::Error;
+
+use crate::types::oidc::{
+    IdTokenClaims, JwkSet, PendingLogin, ProviderMetadata, ProviderTokens,
+};
+
+/// Where and as which client the service logs in at the OpenID Connect
+/// provider
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// `/oidc/callback` of the service, as the browser reaches it. Has
+    /// to be registered with the provider.
+    pub redirect_url: String,
+}
+
+impl OidcConfig {
+    /// The client secret comes from `OIDC_CLIENT_SECRET`, like the other
+    /// secrets of the service
+    pub fn from_env(
+        discovery_url: String,
+        client_id: Option<String>,
+        redirect_url: String,
+    ) -> Result<Self, Error> {
+        let client_id = client_id.ok_or_else(|| {
+            Error::InvalidParameter("oidc-client-id".to_string())
+        })?;
+        let client_secret =
+            env::var("OIDC_CLIENT_SECRET").map_err(|_| {
+                Error::InvalidParameter("OIDC_CLIENT_SECRET".to_string())
+            })?;
+
+        Ok(OidcConfig {
+            discovery_url,
+            client_id,
+            client_secret,
+            redirect_url,
+        })
+    }
+}
+
+/// Client of the OpenID Connect provider. The endpoints are discovered
+/// once at startup, the signing keys are fetched for every login so
+/// the provider can rotate them.
+#[derive(Debug, Clone)]
+pub struct Oidc {
+    config: OidcConfig,
+    provider: ProviderMetadata,
+    client: reqwest::Client,
+}
+
+impl Oidc {
+    pub async fn discover(config: OidcConfig) -> Result<Self, Error> {
+        let client = reqwest::Client::new();
+        let provider = client
+            .get(&config.discovery_url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(Error::ReqwestAPIError)?
+            .json::<ProviderMetadata>()
+            .await
+            .map_err(Error::ReqwestAPIError)?;
+        tracing::info!("OpenID Connect provider {}", provider.issuer);
+
+        Ok(Oidc {
+            config,
+            provider,
+            client,
+        })
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.provider.issuer
+    }
+
+    pub fn redirect_url(&self) -> &str {
+        &self.config.redirect_url
+    }
+
+    /// Where the browser logs in at the provider
+    pub fn authorization_url(&self, login: &PendingLogin) -> String {
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", &self.config.client_id),
+            ("redirect_uri", &self.config.redirect_url),
+            ("scope", "openid email"),
+            ("state", &login.state),
+            ("nonce", &login.nonce),
+            ("code_challenge", &code_challenge(&login.code_verifier)),
+            ("code_challenge_method", "S256"),
+        ])
+        .expect("Cannot encode query");
+        let separator =
+            if self.provider.authorization_endpoint.contains('?') {
+                '&'
+            } else {
+                '?'
+            };
+
+        format!(
+            "{}{}{}",
+            self.provider.authorization_endpoint, separator, query
+        )
+    }
+
+    /// Exchanges the code from the redirect for an ID token and checks
+    /// the token
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        login: &PendingLogin,
+    ) -> Result<IdTokenClaims, Error> {
+        let res = self
+            .client
+            .post(&self.provider.token_endpoint)
+            .basic_auth(
+                &self.config.client_id,
+                Some(&self.config.client_secret),
+            )
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.config.redirect_url),
+                ("code_verifier", &login.code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(Error::ReqwestAPIError)?;
+        if !res.status().is_success() {
+            return Err(Error::OidcLoginFailed(format!(
+                "token endpoint answered {}",
+                res.status()
+            )));
+        }
+        let tokens = res
+            .json::<ProviderTokens>()
+            .await
+            .map_err(Error::ReqwestAPIError)?;
+
+        let keys = self
+            .client
+            .get(&self.provider.jwks_uri)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(Error::ReqwestAPIError)?
+            .json::<JwkSet>()
+            .await
+            .map_err(Error::ReqwestAPIError)?;
+
+        verify_id_token(
+            &tokens.id_token,
+            &keys,
+            &self.provider.issuer,
+            &self.config.client_id,
+            &login.nonce,
+            Utc::now().timestamp(),
+        )
+    }
+}
+
+/// PKCE S256 challenge of a verifier
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+/// Checks the signature, issuer, audience, expiry and nonce of an ID
+/// token. RS256 and EdDSA are accepted, `none` and the shared secret
+/// algorithms never are.
+fn verify_id_token(
+    id_token: &str,
+    keys: &JwkSet,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+    now: i64,
+) -> Result<IdTokenClaims, Error> {
+    let invalid = |reason: &str| {
+        Error::OidcLoginFailed(format!("ID token {}", reason))
+    };
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| invalid("is malformed"))
+    };
+
+    // <header>.<claims>.<signature>, the signature covers the first two
+    let (signed, signature) = id_token
+        .rsplit_once('.')
+        .ok_or_else(|| invalid("is malformed"))?;
+    let (header, claims) = signed
+        .split_once('.')
+        .ok_or_else(|| invalid("is malformed"))?;
+    let header: JwtHeader = serde_json::from_slice(&decode(header)?)
+        .map_err(|_| invalid("is malformed"))?;
+    let signature = decode(signature)?;
+
+    let key = keys
+        .keys
+        .iter()
+        .find(|key| header.kid.is_none() || key.kid == header.kid)
+        .ok_or_else(|| invalid("is signed with an unknown key"))?;
+    let component = |value: &Option<String>| {
+        decode(value.as_deref().unwrap_or_default())
+    };
+    let verified = match header.alg.as_str() {
+        "RS256" if key.kty == "RSA" => RsaPublicKeyComponents {
+            n: component(&key.n)?,
+            e: component(&key.e)?,
+        }
+        .verify(&RSA_PKCS1_2048_8192_SHA256, signed.as_bytes(), &signature)
+        .is_ok(),
+        "EdDSA"
+            if key.kty == "OKP"
+                && key.crv.as_deref() == Some("Ed25519") =>
+        {
+            UnparsedPublicKey::new(&ED25519, component(&key.x)?)
+                .verify(signed.as_bytes(), &signature)
+                .is_ok()
+        }
+        _ => false,
+    };
+    if !verified {
+        return Err(invalid("signature is invalid"));
+    }
+
+    let claims: IdTokenClaims = serde_json::from_slice(&decode(claims)?)
+        .map_err(|_| invalid("is malformed"))?;
+    if claims.iss != issuer {
+        return Err(invalid("is from another issuer"));
+    }
+    if !claims.aud.contains(client_id) {
+        return Err(invalid("is for another client"));
+    }
+    if claims.exp <= now {
+        return Err(invalid("expired"));
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(invalid("is for another login"));
+    }
+
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod oidc_tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use ring::signature::KeyPair;
+    use serde_json::json;
+
+    use super::{code_challenge, verify_id_token};
+    use crate::keyring::test_signing_key;
+    use crate::types::oidc::{Jwk, JwkSet};
+
+    const ISSUER: &str = "https://idp.example.com";
+    const NOW: i64 = 1_800_000_000;
+
+    fn keys() -> JwkSet {
+        JwkSet {
+            keys: vec![Jwk {
+                kid: Some("1".to_string()),
+                kty: "OKP".to_string(),
+                crv: Some("Ed25519".to_string()),
+                n: None,
+                e: None,
+                x: Some(
+                    URL_SAFE_NO_PAD
+                        .encode(test_signing_key(1).public_key()),
+                ),
+            }],
+        }
+    }
+
+    fn id_token(
+        header: serde_json::Value,
+        claims: serde_json::Value,
+    ) -> String {
+        let signed = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = test_signing_key(1).sign(signed.as_bytes());
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn claims() -> serde_json::Value {
+        json!({
+            "iss": ISSUER,
+            "sub": "248289761001",
+            "aud": ["client", "other"],
+            "exp": NOW + 60,
+            "nonce": "nonce",
+            "email": "jane@example.com",
+            "email_verified": true,
+        })
+    }
+
+    #[test]
+    fn id_tokens() {
+        let header = json!({ "alg": "EdDSA", "kid": "1" });
+        let verify = |token: &str| {
+            verify_id_token(token, &keys(), ISSUER, "client", "nonce", NOW)
+        };
+
+        let verified =
+            verify(&id_token(header.clone(), claims())).unwrap();
+        assert_eq!(verified.sub, "248289761001");
+        assert_eq!(verified.email.as_deref(), Some("jane@example.com"));
+        assert!(verified.email_verified);
+
+        let changed = |key: &str, value: serde_json::Value| {
+            let mut claims = claims();
+            claims[key] = value;
+            id_token(header.clone(), claims)
+        };
+        assert!(
+            verify(&changed("iss", json!("https://evil.com"))).is_err()
+        );
+        assert!(verify(&changed("aud", json!("other"))).is_err());
+        assert!(verify(&changed("exp", json!(NOW))).is_err());
+        assert!(verify(&changed("nonce", json!("replayed"))).is_err());
+
+        // Changed claims under the old signature
+        let token = id_token(header.clone(), claims());
+        let (_, signature) = token.rsplit_once('.').unwrap();
+        let forged = changed("sub", json!("admin"));
+        let (forged, _) = forged.rsplit_once('.').unwrap();
+        assert!(verify(&format!("{}.{}", forged, signature)).is_err());
+
+        // Unsigned tokens and unknown keys
+        assert!(
+            verify(&id_token(json!({ "alg": "none" }), claims())).is_err()
+        );
+        assert!(verify(&id_token(
+            json!({ "alg": "EdDSA", "kid": "2" }),
+            claims()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn pkce() {
+        // RFC 7636, appendix B
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+}
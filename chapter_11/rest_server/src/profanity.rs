@@ -1,95 +1,575 @@
-use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use clap::ArgEnum;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{
+    policies::ExponentialBackoff, RetryTransientMiddleware,
+};
 use serde::{Deserialize, Serialize};
-use reqwest_middleware::ClientBuilder;
-use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
+
+use error_handlers::Error;
+
+use crate::circuit_breaker::{
+    BreakerConfig, BreakerStatus, CircuitBreaker,
+};
+use crate::moderation_cache::CacheStatus;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct APIResponse {
-    message: String
+    message: String,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct BadWord {
-    original: String,
-    word: String,
-    deviations: i64,
-    info: i64,
+/// A bad word found in the content, offsets in characters
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BadWord {
+    /// The word as written in the content
+    pub original: String,
+    /// The word of the list it matched
+    pub word: String,
+    /// How many characters of `original` differ from `word`, through
+    /// leetspeak or repeated letters
+    pub deviations: i64,
+    /// Category APILayer puts the word in, the dictionary has none
+    #[serde(default)]
+    pub info: i64,
+    #[serde(default)]
+    pub start: i64,
+    #[serde(default)]
+    pub end: i64,
     #[serde(rename = "replacedLen")]
-    replaced_len: i64,
+    pub replaced_len: i64,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct BadWordsResponse {
-    content: String,
-    bad_words_total: i64,
-    bad_words_list: Vec<BadWord>,
-    censored_content: String,
-}
-
-pub async fn check_profanity(content: String) -> Result<String, error_handlers::Error> {
-    // We are already checking if the ENV VARIABLE is set inside main.rs, so safe to unwrap here
-    let api_key = env::var("BAD_WORDS_API_KEY").expect("BAD WORDS API KEY NOT SET");
-    let api_layer_url = env::var("API_LAYER_URL").expect("APILAYER URL NOT SET");
-
-    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-    let client = ClientBuilder::new(reqwest::Client::new())
-        // Trace HTTP requests. See the tracing crate to make use of these traces.
-        // Retry failed requests.
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
-
-    let res = client
-        .post(format!(
-            "{}/bad_words?censor_character=*",
-            api_layer_url
-        ))
-        .header("apikey", api_key)
-        .body(content)
-        .send()
-        .await
-        .map_err(error_handlers::Error::MiddlewareReqwestAPIError)?;
-
-    if !res.status().is_success() {
-        if res.status().is_client_error() {
-            let err = transform_error(res).await;
-            return Err(error_handlers::Error::ClientError(err));
-        } else {
-            let err = transform_error(res).await;
-            return Err(error_handlers::Error::ServerError(err));
-        }
-    }
-
-    match res.json::<BadWordsResponse>()
-        .await {
-        Ok(res) => Ok(res.censored_content),
-        Err(e) => Err(error_handlers::Error::ReqwestAPIError(e)),
-    }
-}
-
-async fn transform_error(res: reqwest::Response) -> error_handlers::APILayerError {
+/// The result of moderating a text, shaped like the answer of the
+/// APILayer bad_words API
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Moderation {
+    pub content: String,
+    pub bad_words_total: i64,
+    pub bad_words_list: Vec<BadWord>,
+    pub censored_content: String,
+    /// Not checked because the moderation was unavailable, has to be
+    /// reviewed
+    #[serde(default)]
+    pub unchecked: bool,
+    /// Made by the degraded policy instead of the moderation service,
+    /// so it is not cached
+    #[serde(skip)]
+    pub degraded: bool,
+}
+
+/// Which moderation runs and how it is doing
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ModerationStatus {
+    pub backend: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breaker: Option<BreakerStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded_policy: Option<DegradedPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheStatus>,
+}
+
+/// What happens to posts while the moderation is unavailable
+#[derive(
+    ArgEnum, Deserialize, Serialize, Clone, Copy, Debug, PartialEq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DegradedPolicy {
+    /// Posting fails
+    Reject,
+    /// Posts are taken uncensored and flagged for review
+    Flag,
+    /// Posts are censored with the local word lists
+    Local,
+}
+
+/// What happens to posts in which the moderation finds bad words
+#[derive(
+    ArgEnum, Deserialize, Serialize, Clone, Copy, Debug, PartialEq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ModerationMode {
+    /// The bad words are masked and the post is published
+    Censor,
+    /// The post is held back until a moderator approves, edits or
+    /// rejects it, as are the posts taken unchecked
+    Review,
+}
+
+/// Finds bad words in the questions and answers users post
+#[async_trait]
+pub trait ContentModerator: std::fmt::Debug + Send + Sync {
+    async fn moderate(&self, content: String)
+        -> Result<Moderation, Error>;
+
+    fn status(&self) -> ModerationStatus;
+}
+
+/// Where the APILayer API is and how patiently it is called
+#[derive(Debug, Clone)]
+pub struct ApiLayerConfig {
+    pub url: String,
+    pub api_key: String,
+    /// For setting up the connection
+    pub connect_timeout: Duration,
+    /// Between two reads of the answer
+    pub read_timeout: Duration,
+    /// Retries of a failed request after the first try
+    pub max_retries: u32,
+    /// Wait before the first retry, doubling up to `max_backoff`
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ApiLayerConfig {
+    fn default() -> Self {
+        ApiLayerConfig {
+            url: "https://api.apilayer.com".to_string(),
+            api_key: String::new(),
+            connect_timeout: Duration::from_secs(2),
+            read_timeout: Duration::from_secs(5),
+            max_retries: 3,
+            min_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Moderates through the bad_words API of APILayer. The client is built
+/// once, so connections to the API are reused.
+#[derive(Debug, Clone)]
+pub struct ApiLayerModerator {
+    url: String,
+    api_key: String,
+    client: ClientWithMiddleware,
+}
+
+impl ApiLayerModerator {
+    pub fn new(config: ApiLayerConfig) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout)
+            .build()
+            .map_err(Error::ReqwestAPIError)?;
+        let retry_policy = ExponentialBackoff::builder()
+            .retry_bounds(config.min_backoff, config.max_backoff)
+            .build_with_max_retries(config.max_retries);
+        let client = ClientBuilder::new(client)
+            // Retries failed requests and timeouts
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        Ok(ApiLayerModerator {
+            url: config.url,
+            api_key: config.api_key,
+            client,
+        })
+    }
+}
+
+/// Timeouts are told apart from the other failures of the API
+fn request_error(e: reqwest_middleware::Error) -> Error {
+    match e {
+        reqwest_middleware::Error::Reqwest(e) if e.is_timeout() => {
+            Error::ModerationTimeout
+        }
+        e => Error::MiddlewareReqwestAPIError(e),
+    }
+}
+
+#[async_trait]
+impl ContentModerator for ApiLayerModerator {
+    async fn moderate(
+        &self,
+        content: String,
+    ) -> Result<Moderation, Error> {
+        let res = self
+            .client
+            .post(format!("{}/bad_words?censor_character=*", self.url))
+            .header("apikey", &self.api_key)
+            .body(content)
+            .send()
+            .await
+            .map_err(request_error)?;
+
+        if !res.status().is_success() {
+            if res.status().is_client_error() {
+                let err = transform_error(res).await;
+                return Err(Error::ClientError(err));
+            } else {
+                let err = transform_error(res).await;
+                return Err(Error::ServerError(err));
+            }
+        }
+
+        res.json::<Moderation>().await.map_err(|e| {
+            if e.is_timeout() {
+                Error::ModerationTimeout
+            } else {
+                Error::ReqwestAPIError(e)
+            }
+        })
+    }
+
+    fn status(&self) -> ModerationStatus {
+        ModerationStatus {
+            backend: "api-layer".to_string(),
+            breaker: None,
+            degraded_policy: None,
+            cache: None,
+        }
+    }
+}
+
+async fn transform_error(
+    res: reqwest::Response,
+) -> error_handlers::APILayerError {
     error_handlers::APILayerError {
         status: res.status().as_u16(),
         message: res.json::<APIResponse>().await.unwrap().message,
     }
 }
 
+/// Characters which stand in for letters, like `sh1t` or `@ss`
+const LEETSPEAK: [(char, char); 9] = [
+    ('0', 'o'),
+    ('1', 'i'),
+    ('3', 'e'),
+    ('4', 'a'),
+    ('5', 's'),
+    ('7', 't'),
+    ('@', 'a'),
+    ('$', 's'),
+    ('!', 'i'),
+];
+
+/// Moderates offline with word lists. Masks like APILayer with
+/// `censor_character=*` and sees through simple leetspeak and
+/// repeated letters.
+#[derive(Debug, Clone)]
+pub struct DictionaryModerator {
+    /// Lowercase, in the order of the lists
+    words: Vec<String>,
+}
+
+impl DictionaryModerator {
+    pub fn new(words: Vec<String>) -> Self {
+        let words = words
+            .into_iter()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        DictionaryModerator { words }
+    }
+
+    /// Word lists with a word per line, `#` starts a comment line
+    pub fn from_files(paths: &[String]) -> Result<Self, Error> {
+        let mut words = Vec::new();
+        for path in paths {
+            let list = std::fs::read_to_string(path).map_err(|e| {
+                tracing::error!("Cannot read word list {}: {}", path, e);
+                Error::InvalidParameter(format!(
+                    "moderation-word-list {}",
+                    path
+                ))
+            })?;
+            words.extend(
+                list.lines()
+                    .filter(|line| !line.trim_start().starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        tracing::info!(
+            "{} words in the moderation word lists",
+            words.len()
+        );
+
+        Ok(DictionaryModerator::new(words))
+    }
+
+    /// The word of the list the token stands for, with the deviations
+    fn matching_word(&self, token: &str) -> Option<(&str, i64)> {
+        let mut substitutions = 0;
+        let normalized: String = token
+            .chars()
+            .flat_map(char::to_lowercase)
+            .map(|c| match LEETSPEAK.iter().find(|(leet, _)| *leet == c) {
+                Some((_, letter)) => {
+                    substitutions += 1;
+                    *letter
+                }
+                None => c,
+            })
+            .collect();
+
+        self.words.iter().find_map(|word| {
+            repeated_letters(&normalized, word)
+                .map(|repeated| (word.as_str(), substitutions + repeated))
+        })
+    }
+}
+
+/// How many letters `token` repeats to spell `word`, like `fuuuck`.
+/// Letters `word` doubles have to be at least doubled, `as` is not
+/// `ass`.
+fn repeated_letters(token: &str, word: &str) -> Option<i64> {
+    let (token, word) = (runs(token), runs(word));
+    if token.len() != word.len() {
+        return None;
+    }
+
+    let mut repeated = 0;
+    for ((token_char, token_len), (word_char, word_len)) in
+        token.into_iter().zip(word)
+    {
+        if token_char != word_char || token_len < word_len {
+            return None;
+        }
+        repeated += (token_len - word_len) as i64;
+    }
+    Some(repeated)
+}
+
+/// Runs of the same character with their length
+fn runs(text: &str) -> Vec<(char, usize)> {
+    let mut runs: Vec<(char, usize)> = Vec::new();
+    for c in text.chars() {
+        match runs.last_mut() {
+            Some((last, len)) if *last == c => *len += 1,
+            _ => runs.push((c, 1)),
+        }
+    }
+    runs
+}
+
+/// Words of the text as character ranges. Leetspeak characters belong
+/// to words, except `!` at their end.
+fn tokens(chars: &[char]) -> Vec<(usize, usize)> {
+    let is_word_char = |c: char| {
+        c.is_alphanumeric() || LEETSPEAK.iter().any(|(leet, _)| *leet == c)
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in chars.iter().copied().chain([' ']).enumerate() {
+        match (start, is_word_char(c)) {
+            (None, true) => start = Some(i),
+            (Some(from), false) => {
+                let mut end = i;
+                while end > from && chars[end - 1] == '!' {
+                    end -= 1;
+                }
+                if end > from {
+                    tokens.push((from, end));
+                }
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    tokens
+}
+
+#[async_trait]
+impl ContentModerator for DictionaryModerator {
+    async fn moderate(
+        &self,
+        content: String,
+    ) -> Result<Moderation, Error> {
+        let mut censored: Vec<char> = content.chars().collect();
+        let mut bad_words = Vec::new();
+
+        for (start, end) in tokens(&censored) {
+            let original: String = censored[start..end].iter().collect();
+            if let Some((word, deviations)) = self.matching_word(&original)
+            {
+                censored[start..end].fill('*');
+                bad_words.push(BadWord {
+                    original,
+                    word: word.to_string(),
+                    deviations,
+                    info: 0,
+                    start: start as i64,
+                    end: end as i64,
+                    replaced_len: (end - start) as i64,
+                });
+            }
+        }
+
+        Ok(Moderation {
+            bad_words_total: bad_words.len() as i64,
+            bad_words_list: bad_words,
+            censored_content: censored.into_iter().collect(),
+            content,
+            unchecked: false,
+            degraded: false,
+        })
+    }
+
+    fn status(&self) -> ModerationStatus {
+        ModerationStatus {
+            backend: "dictionary".to_string(),
+            breaker: None,
+            degraded_policy: None,
+            cache: None,
+        }
+    }
+}
+
+/// Whether the error means the moderation service is unavailable,
+/// rather than refusing the request
+fn is_unavailable(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::ModerationTimeout
+            | Error::MiddlewareReqwestAPIError(_)
+            | Error::ReqwestAPIError(_)
+            | Error::ServerError(_)
+    )
+}
+
+/// Puts a circuit breaker in front of a moderation service. While the
+/// service is unavailable posts are handled by the degraded policy.
+#[derive(Debug)]
+pub struct CircuitBreakerModerator {
+    moderator: Arc<dyn ContentModerator>,
+    breaker: CircuitBreaker,
+    policy: DegradedPolicy,
+    /// Censors with `DegradedPolicy::Local`
+    fallback: Option<DictionaryModerator>,
+}
+
+impl CircuitBreakerModerator {
+    /// `fallback` is needed for `DegradedPolicy::Local`, without it
+    /// posts are rejected
+    pub fn new(
+        moderator: Arc<dyn ContentModerator>,
+        config: BreakerConfig,
+        policy: DegradedPolicy,
+        fallback: Option<DictionaryModerator>,
+    ) -> Self {
+        CircuitBreakerModerator {
+            moderator,
+            breaker: CircuitBreaker::new("Moderation", config),
+            policy,
+            fallback,
+        }
+    }
+
+    async fn degraded(
+        &self,
+        content: String,
+    ) -> Result<Moderation, Error> {
+        match (self.policy, &self.fallback) {
+            (DegradedPolicy::Local, Some(fallback)) => Ok(Moderation {
+                degraded: true,
+                ..fallback.moderate(content).await?
+            }),
+            (DegradedPolicy::Flag, _) => Ok(Moderation {
+                censored_content: content.clone(),
+                content,
+                bad_words_total: 0,
+                bad_words_list: Vec::new(),
+                unchecked: true,
+                degraded: true,
+            }),
+            _ => Err(Error::ModerationUnavailable),
+        }
+    }
+}
+
+#[async_trait]
+impl ContentModerator for CircuitBreakerModerator {
+    async fn moderate(
+        &self,
+        content: String,
+    ) -> Result<Moderation, Error> {
+        if !self.breaker.allow() {
+            return self.degraded(content).await;
+        }
+
+        match self.moderator.moderate(content.clone()).await {
+            Err(e) if is_unavailable(&e) => {
+                tracing::error!("Moderation unavailable: {}", e);
+                self.breaker.failure();
+                self.degraded(content).await
+            }
+            result => {
+                self.breaker.success();
+                result
+            }
+        }
+    }
+
+    fn status(&self) -> ModerationStatus {
+        ModerationStatus {
+            breaker: Some(self.breaker.status()),
+            degraded_policy: Some(self.policy),
+            ..self.moderator.status()
+        }
+    }
+}
+
+/// Logs a post taken without moderation, moderators review these
+pub fn flag_for_review(kind: &str, id: i32) {
+    tracing::warn!(
+        target: "moderation_review",
+        "{} {} posted unchecked, flagged for review",
+        kind,
+        id
+    );
+}
+
+/// Moderator for the route tests, with a short word list
+#[cfg(test)]
+pub(crate) fn test_moderator() -> std::sync::Arc<dyn ContentModerator> {
+    std::sync::Arc::new(DictionaryModerator::new(vec![
+        "shit".to_string(),
+        "shitty".to_string(),
+    ]))
+}
+
 #[cfg(test)]
 mod profanity_tests {
-    use super::{check_profanity, env};
+    use std::time::Duration;
+
+    use super::{
+        ApiLayerConfig, ApiLayerModerator, CircuitBreakerModerator,
+        ContentModerator, DegradedPolicy, DictionaryModerator,
+    };
+    use crate::circuit_breaker::{BreakerConfig, BreakerState};
+    use error_handlers::Error;
     use mock_server::{MockServer, OneshotHandler};
 
+    /// The content with the bad words masked with `*`
+    async fn censor(
+        moderator: &dyn ContentModerator,
+        content: &str,
+    ) -> Result<String, Error> {
+        Ok(moderator
+            .moderate(content.to_string())
+            .await?
+            .censored_content)
+    }
+
     #[tokio::test]
     async fn run() {
         let handler = run_mock();
-        censor_profane_words().await;
-        no_profane_words().await;
+        let moderator = ApiLayerModerator::new(ApiLayerConfig {
+            url: "http://127.0.0.1:3030".to_string(),
+            api_key: "YES".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        censor_profane_words(&moderator).await;
+        no_profane_words(&moderator).await;
         let _ = handler.sender.send(1);
     }
 
     fn run_mock() -> OneshotHandler {
-        env::set_var("API_LAYER_URL", "http://127.0.0.1:3030");
-        env::set_var("BAD_WORDS_API_KEY", "YES");
-
         let socket = "127.0.0.1:3030"
             .to_string()
             .parse()
@@ -99,15 +579,141 @@ mod profanity_tests {
         mock.oneshot()
     }
 
-    async fn censor_profane_words() {
+    async fn censor_profane_words(moderator: &ApiLayerModerator) {
         let content = "This is a shitty sentence".to_string();
-        let censored_content = check_profanity(content).await;
+        let censored_content = censor(moderator, &content).await;
         assert_eq!(censored_content.unwrap(), "this is a ****** sentence");
     }
 
-    async fn no_profane_words() {
+    async fn no_profane_words(moderator: &ApiLayerModerator) {
         let content = "this is a sentence".to_string();
-        let censored_content = check_profanity(content).await;
+        let censored_content = censor(moderator, &content).await;
         assert_eq!(censored_content.unwrap(), "");
     }
+
+    #[tokio::test]
+    async fn timeout() {
+        // Takes requests and never answers
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                connections.push(socket);
+            }
+        });
+
+        let moderator = ApiLayerModerator::new(ApiLayerConfig {
+            url,
+            read_timeout: Duration::from_millis(100),
+            max_retries: 1,
+            min_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+            ..Default::default()
+        })
+        .unwrap();
+        let result = censor(&moderator, "a sentence").await;
+        assert!(matches!(result, Err(Error::ModerationTimeout)));
+    }
+
+    /// APILayer behind a breaker which opens with the first failure
+    async fn unavailable(
+        policy: DegradedPolicy,
+        fallback: Option<DictionaryModerator>,
+    ) -> CircuitBreakerModerator {
+        // Nobody listens on the port anymore
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let api_layer = ApiLayerModerator::new(ApiLayerConfig {
+            url,
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        CircuitBreakerModerator::new(
+            std::sync::Arc::new(api_layer),
+            BreakerConfig {
+                failure_threshold: 1,
+                open_duration: Duration::from_secs(60),
+            },
+            policy,
+            fallback,
+        )
+    }
+
+    #[tokio::test]
+    async fn degraded() {
+        let moderator = unavailable(DegradedPolicy::Reject, None).await;
+        assert_eq!(moderator.status().breaker.unwrap().failures, 0);
+        for _ in 0..2 {
+            let result = censor(&moderator, "a shit sentence").await;
+            assert!(matches!(result, Err(Error::ModerationUnavailable)));
+        }
+        let status = moderator.status();
+        assert_eq!(status.backend, "api-layer");
+        assert_eq!(status.degraded_policy, Some(DegradedPolicy::Reject));
+        // The second call did not reach the API
+        let breaker = status.breaker.unwrap();
+        assert_eq!(breaker.state, BreakerState::Open);
+        assert_eq!(breaker.failures, 1);
+
+        let moderator = unavailable(DegradedPolicy::Flag, None).await;
+        let moderation = moderator
+            .moderate("a shit sentence".to_string())
+            .await
+            .unwrap();
+        assert!(moderation.unchecked && moderation.degraded);
+        assert_eq!(moderation.censored_content, "a shit sentence");
+
+        let fallback = DictionaryModerator::new(vec!["shit".to_string()]);
+        let moderator =
+            unavailable(DegradedPolicy::Local, Some(fallback)).await;
+        let moderation = moderator
+            .moderate("a shit sentence".to_string())
+            .await
+            .unwrap();
+        assert!(!moderation.unchecked && moderation.degraded);
+        assert_eq!(moderation.censored_content, "a **** sentence");
+    }
+
+    #[tokio::test]
+    async fn dictionary() {
+        let moderator = DictionaryModerator::new(vec![
+            "shitty".to_string(),
+            "ass".to_string(),
+            "fuck".to_string(),
+        ]);
+        let censor = |content| censor(&moderator, content);
+
+        assert_eq!(
+            censor("This is a Shitty sentence!").await.unwrap(),
+            "This is a ****** sentence!"
+        );
+        assert_eq!(
+            censor("this is a sentence").await.unwrap(),
+            "this is a sentence"
+        );
+        // Leetspeak and repeated letters, but not other words
+        assert_eq!(
+            censor("Fuuuck, sh1tty @ss! as class").await.unwrap(),
+            "******, ****** ***! as class"
+        );
+
+        let moderation = moderator
+            .moderate("Grüße, sh1tty".to_string())
+            .await
+            .unwrap();
+        assert_eq!(moderation.bad_words_total, 1);
+        let bad_word = &moderation.bad_words_list[0];
+        assert_eq!(bad_word.original, "sh1tty");
+        assert_eq!(bad_word.word, "shitty");
+        assert_eq!(bad_word.deviations, 1);
+        assert_eq!((bad_word.start, bad_word.end), (7, 13));
+        assert_eq!(bad_word.replaced_len, 6);
+        assert_eq!(moderation.censored_content, "Grüße, ******");
+    }
 }
@@ -0,0 +1,206 @@
+use warp::http::StatusCode;
+
+use crate::routes::authentication::end_account_sessions;
+use crate::store::Store;
+use crate::types::account::{AccountId, AccountInfo, NewRole, Session};
+
+/// Every account, for admins
+pub async fn get_accounts<S: Store>(
+    _session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_accounts().await {
+        Ok(accounts) => {
+            let accounts: Vec<AccountInfo> =
+                accounts.into_iter().map(AccountInfo::from).collect();
+            Ok(warp::reply::json(&accounts))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Changes the role of an account. Tokens carry the role, so every
+/// login of the account is ended and the new role takes effect with
+/// its next login.
+pub async fn set_role<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+    new_role: NewRole,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    tracing::event!(
+        tracing::Level::INFO,
+        admin = session.account_id.0,
+        account_id = id,
+        role = new_role.role.as_str(),
+        "Changing role"
+    );
+
+    match store.set_role(&AccountId(id), new_role.role).await {
+        Ok(Some(account)) => {
+            end_account_sessions(&store, &AccountId(id), None).await?;
+            Ok(warp::reply::json(&AccountInfo::from(account)))
+        }
+        Ok(None) => Err(warp::reject::custom(
+            error_handlers::Error::AccountNotFound,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Deletes an account after ending its logins, so its access and
+/// refresh tokens stop working right away
+pub async fn delete_account<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    tracing::event!(
+        tracing::Level::INFO,
+        admin = session.account_id.0,
+        account_id = id,
+        "Deleting account"
+    );
+
+    end_account_sessions(&store, &AccountId(id), None).await?;
+    match store.delete_account(&AccountId(id)).await {
+        Ok(true) => Ok(warp::reply::with_status(
+            format!("Account {} deleted", id),
+            StatusCode::OK,
+        )),
+        Ok(false) => Err(warp::reject::custom(
+            error_handlers::Error::AccountNotFound,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod account_tests {
+
+    use crate::routes::authentication::test_token;
+    use crate::store::{AccountStore, InMemoryStore};
+    use crate::types::account::{Account, AccountId, AccountInfo, Role};
+    use crate::types::token::TokenPair;
+
+    #[tokio::test]
+    async fn admins_manage_accounts() {
+        let store = InMemoryStore::new();
+        for email in ["admin@example.com", "user@example.com"] {
+            store
+                .add_account(Account {
+                    id: None,
+                    email: email.to_string(),
+                    password: "hash".to_string(),
+                    role: Role::User,
+                    verified: false,
+                })
+                .await
+                .unwrap();
+        }
+        let filter = crate::test_routes(store);
+        let admin = test_token(AccountId(1), Role::Admin);
+        let moderator = test_token(AccountId(2), Role::Moderator);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/accounts")
+            .header("Authorization", &moderator)
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/accounts/2/role")
+            .header("Authorization", &admin)
+            .json(&serde_json::json!({ "role": "moderator" }))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/accounts")
+            .header("Authorization", &admin)
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let accounts: Vec<AccountInfo> =
+            serde_json::from_slice(res.body()).unwrap();
+        let roles: Vec<Role> = accounts.iter().map(|a| a.role).collect();
+        assert_eq!(roles, vec![Role::User, Role::Moderator]);
+
+        let delete = |id: i32| {
+            warp::test::request()
+                .method("DELETE")
+                .path(&format!("/accounts/{}", id))
+                .header("Authorization", &admin)
+                .reply(&filter)
+        };
+        assert_eq!(delete(2).await.status(), 200);
+        assert_eq!(delete(2).await.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn role_changes_end_logins() {
+        let filter = crate::test_routes(InMemoryStore::new());
+        let admin = test_token(AccountId(42), Role::Admin);
+        let account = serde_json::json!({
+            "email": "moderator@example.com",
+            "password": "secret",
+        });
+        let res = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account)
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let login = || async {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/login")
+                .json(&account)
+                .reply(&filter)
+                .await;
+            assert_eq!(res.status(), 200);
+            serde_json::from_slice::<TokenPair>(res.body())
+                .unwrap()
+                .access_token
+        };
+        let sessions = |token: String| {
+            warp::test::request()
+                .path("/account/sessions")
+                .header("Authorization", token)
+                .reply(&filter)
+        };
+        let set_role = |role: &'static str| {
+            warp::test::request()
+                .method("PUT")
+                .path("/accounts/1/role")
+                .header("Authorization", &admin)
+                .json(&serde_json::json!({ "role": role }))
+                .reply(&filter)
+        };
+
+        assert_eq!(set_role("moderator").await.status(), 200);
+        let moderator = login().await;
+        assert_eq!(sessions(moderator.clone()).await.status(), 200);
+
+        // The moderator token stops working once the role is taken away
+        assert_eq!(set_role("user").await.status(), 200);
+        assert_eq!(sessions(moderator).await.status(), 401);
+
+        let user = login().await;
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/accounts/1")
+            .header("Authorization", &admin)
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(sessions(user).await.status(), 401);
+    }
+}
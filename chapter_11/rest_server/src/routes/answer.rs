@@ -1,30 +1,281 @@
-use warp::http::StatusCode;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::profanity::check_profanity;
+use tracing::{event, instrument, Level};
+
+use crate::profanity::{ContentModerator, ModerationMode};
+use crate::routes::authorization::authorize_owner;
+use crate::routes::review::{posted_status, review_for};
 use crate::store::Store;
 use crate::types::account::Session;
-use crate::types::answer::NewAnswer;
+use crate::types::answer::{Answer, NewAnswer};
+use crate::types::pagination::{
+    extract_pagination, Pagination, PaginationConfig,
+};
+use crate::types::review::ReviewTarget;
+
+#[instrument]
+pub async fn get_answers<S: Store>(
+    question_id: i32,
+    params: HashMap<String, String>,
+    config: PaginationConfig,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "practical_rust_book",
+        Level::INFO,
+        "querying answers of question {}",
+        question_id
+    );
+    let mut pagination = Pagination::default();
+
+    if !params.is_empty() {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params, &config)?;
+    }
+
+    if store.get_question(question_id).await?.is_none() {
+        return Err(warp::reject::custom(
+            error_handlers::Error::QuestionNotFound,
+        ));
+    }
+
+    match store
+        .get_answers(question_id, pagination.limit, pagination.offset)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn get_answer<S: Store>(
+    id: i32,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_answer(id).await? {
+        Some(answer) => Ok(warp::reply::json(&answer)),
+        None => Err(warp::reject::custom(
+            error_handlers::Error::AnswerNotFound,
+        )),
+    }
+}
 
-pub async fn add_answer(
+pub async fn add_answer<S: Store>(
     session: Session,
-    store: Store,
+    store: S,
+    moderator: Arc<dyn ContentModerator>,
+    mode: ModerationMode,
     new_answer: NewAnswer,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let account_id = session.account_id;
-    let content = match check_profanity(new_answer.content).await {
+    // Pending questions cannot be answered before they are published
+    if store
+        .get_question(new_answer.question_id.0)
+        .await?
+        .is_none()
+    {
+        return Err(warp::reject::custom(
+            error_handlers::Error::QuestionNotFound,
+        ));
+    }
+
+    let content = match moderator.moderate(new_answer.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
     let answer = NewAnswer {
-        content,
+        content: content.censored_content.clone(),
         question_id: new_answer.question_id,
     };
 
-    match store.add_answer(answer, account_id).await {
-        Ok(_) => {
-            Ok(warp::reply::with_status("Answer added", StatusCode::OK))
+    let review = review_for(mode, None, &content);
+    let pending = review.is_some();
+    match store.add_answer(answer, account_id, review).await {
+        Ok(answer) => {
+            let status = posted_status(
+                ReviewTarget::Answer(answer.id.clone()),
+                pending,
+                content.unchecked,
+            );
+            Ok(warp::reply::with_status(
+                warp::reply::json(&answer),
+                status,
+            ))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn update_answer<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+    moderator: Arc<dyn ContentModerator>,
+    mode: ModerationMode,
+    answer: Answer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let is_owner = store.is_answer_owner(id, &session.account_id).await?;
+    let owner = authorize_owner(&session, "update answer", is_owner)?;
+
+    let content = match moderator.moderate(answer.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let answer = Answer {
+        content: content.censored_content.clone(),
+        ..answer
+    };
+
+    let review = review_for(mode, None, &content);
+    let pending = review.is_some();
+    match store.update_answer(answer, id, owner, review).await {
+        Ok(Some(res)) => {
+            let status = posted_status(
+                ReviewTarget::Answer(res.id.clone()),
+                pending,
+                content.unchecked,
+            );
+            Ok(warp::reply::with_status(warp::reply::json(&res), status))
         }
+        Ok(None) => Err(warp::reject::custom(
+            error_handlers::Error::AnswerNotFound,
+        )),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+pub async fn delete_answer<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let is_owner = store.is_answer_owner(id, &session.account_id).await?;
+    let owner = authorize_owner(&session, "delete answer", is_owner)?;
+
+    match store.delete_answer(id, owner).await {
+        Ok(Some(res)) => Ok(warp::reply::json(&res)),
+        Ok(None) => Err(warp::reject::custom(
+            error_handlers::Error::AnswerNotFound,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod answer_tests {
+    use crate::routes::authentication::test_token;
+    use crate::store::{AnswerStore, InMemoryStore, QuestionStore};
+    use crate::types::account::{AccountId, Role};
+    use crate::types::answer::{Answer, NewAnswer};
+    use crate::types::question::NewQuestion;
+
+    async fn seeded_store() -> InMemoryStore {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(
+                NewQuestion {
+                    title: "First question".to_string(),
+                    content: "How does this work?".to_string(),
+                    tags: None,
+                },
+                AccountId(1),
+                None,
+            )
+            .await
+            .unwrap();
+        for content in ["First answer", "Second answer"] {
+            store
+                .add_answer(
+                    NewAnswer {
+                        content: content.to_string(),
+                        question_id: question.id.clone(),
+                    },
+                    AccountId(2),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn get_answers() {
+        let filter = crate::test_routes(seeded_store().await);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions/1/answers")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let answers: Vec<Answer> =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(answers.len(), 2);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/answers/2")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let answer: Answer = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(answer.content, "Second answer");
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/answers/42")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn delete_answer_checks_owner() {
+        let store = seeded_store().await;
+        let filter = crate::test_routes(store.clone());
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/answers/1")
+            .header("Authorization", test_token(AccountId(1), Role::User))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/answers/1")
+            .header("Authorization", test_token(AccountId(2), Role::User))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let answer: Answer = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(answer.content, "First answer");
+        assert!(store.get_answer(1).await.unwrap().is_none());
+
+        let moderator = test_token(AccountId(3), Role::Moderator);
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/answers/1")
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/answers/1")
+            .header("Authorization", moderator)
+            .json(&serde_json::json!({
+                "id": 1,
+                "content": "Edited",
+                "question_id": 1,
+            }))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+}
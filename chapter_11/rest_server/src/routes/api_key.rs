@@ -0,0 +1,172 @@
+use warp::{Rejection, Reply};
+
+use error_handlers::Error;
+
+use crate::routes::authentication::{hash_token, random_token};
+use crate::store::Store;
+use crate::types::account::Session;
+use crate::types::api_key::{ApiKeyId, CreatedApiKey, NewApiKey};
+
+/// Start of every API key, so leaked keys are easy to search for
+const KEY_PREFIX: &str = "qa_";
+/// How much of the key the listing shows
+const SHOWN_LENGTH: usize = KEY_PREFIX.len() + 8;
+
+/// Creates an API key for the logged in account. The answer is the
+/// only time the key is shown.
+pub async fn add_api_key<S: Store>(
+    session: Session,
+    store: S,
+    new_key: NewApiKey,
+) -> Result<impl Reply, Rejection> {
+    if new_key.scopes.is_empty() {
+        return Err(warp::reject::custom(Error::InvalidParameter(
+            "scopes".to_string(),
+        )));
+    }
+
+    let key = format!("{}{}", KEY_PREFIX, random_token());
+    let api_key = store
+        .add_api_key(
+            &session.account_id,
+            new_key,
+            &key[..SHOWN_LENGTH],
+            &hash_token(&key),
+        )
+        .await?;
+
+    Ok(warp::reply::json(&CreatedApiKey { api_key, key }))
+}
+
+/// The API keys of the logged in account
+pub async fn get_api_keys<S: Store>(
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let api_keys = store.get_api_keys(&session.account_id).await?;
+    Ok(warp::reply::json(&api_keys))
+}
+
+/// Revokes an API key of the logged in account
+pub async fn delete_api_key<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    if !store
+        .delete_api_key(&ApiKeyId(id), &session.account_id)
+        .await?
+    {
+        return Err(warp::reject::custom(Error::ApiKeyNotFound));
+    }
+
+    Ok(warp::reply::json(&format!("API key {} revoked", id)))
+}
+
+#[cfg(test)]
+mod api_key_tests {
+    use crate::routes::authentication::test_token;
+    use crate::store::{AccountStore, InMemoryStore, QuestionStore};
+    use crate::types::account::{Account, AccountId, Role};
+    use crate::types::api_key::{ApiKey, CreatedApiKey};
+    use crate::types::question::NewQuestion;
+
+    #[tokio::test]
+    async fn api_keys() {
+        let store = InMemoryStore::new();
+        store
+            .add_account(Account {
+                id: None,
+                email: "bot@example.com".to_string(),
+                password: "hash".to_string(),
+                role: Role::User,
+                verified: true,
+            })
+            .await
+            .unwrap();
+        for account in [1, 2] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: "Title".to_string(),
+                        content: "Content".to_string(),
+                        tags: None,
+                    },
+                    AccountId(account),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let routes = crate::test_routes(store);
+        let token = test_token(AccountId(1), Role::User);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/account/api-keys")
+            .header("Authorization", &token)
+            .json(&serde_json::json!({ "name": "ci", "scopes": [] }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 422);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/account/api-keys")
+            .header("Authorization", &token)
+            .json(&serde_json::json!({
+                "name": "ci",
+                "scopes": ["questions:write"],
+            }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let created: CreatedApiKey =
+            serde_json::from_slice(res.body()).unwrap();
+        assert!(created.key.starts_with(&created.api_key.prefix));
+        let header = format!("ApiKey {}", created.key);
+
+        let request = |method: &str, path: &str, auth: &str| {
+            warp::test::request()
+                .method(method)
+                .path(path)
+                .header("Authorization", auth)
+                .body(r#"{"vote": "up"}"#)
+                .reply(&routes)
+        };
+        // The scope covers questions, not answers or the account
+        assert_eq!(
+            request("POST", "/questions/2/vote", &header).await.status(),
+            200
+        );
+        assert_eq!(
+            request("DELETE", "/answers/1", &header).await.status(),
+            401
+        );
+        assert_eq!(
+            request("GET", "/account/api-keys", &header).await.status(),
+            401
+        );
+        assert_eq!(
+            request("POST", "/questions/2/vote", "ApiKey qa_unknown")
+                .await
+                .status(),
+            401
+        );
+
+        let res = request("GET", "/account/api-keys", &token).await;
+        let api_keys: Vec<ApiKey> =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(api_keys, vec![created.api_key.clone()]);
+
+        // Only the owner revokes a key
+        let other = test_token(AccountId(2), Role::User);
+        let path = format!("/account/api-keys/{}", created.api_key.id.0);
+        assert_eq!(request("DELETE", &path, &other).await.status(), 404);
+        assert_eq!(request("DELETE", &path, &token).await.status(), 200);
+        assert_eq!(
+            request("POST", "/questions/2/vote", &header).await.status(),
+            401
+        );
+    }
+}
@@ -1,81 +1,316 @@
 use argon2::{self, Config};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::prelude::*;
 use rand::Rng;
-use std::{env, future};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use uuid::Uuid;
 use warp::{Filter, Rejection, Reply};
 
+use crate::keyring::Keyring;
+use crate::mailer::{Mail, Mailer};
 use crate::store::Store;
-use crate::types::account::{Account, AccountId, Session};
+use crate::types::{
+    account::{Account, AccountId, Role, Session, SessionInfo},
+    login_attempt::ATTEMPT_WINDOW,
+    token::{
+        AccountToken, RefreshRequest, RefreshToken, TokenPair,
+        TokenPurpose,
+    },
+};
 
-pub async fn register(
-    store: Store,
+/// Access tokens can not be revoked before they expire unless they are
+/// put on the revocation list, so they are kept short-lived
+pub(crate) const ACCESS_TOKEN_LIFETIME: chrono::Duration =
+    chrono::Duration::minutes(15);
+/// How long a refresh token can be exchanged for a new token pair
+const REFRESH_TOKEN_LIFETIME: chrono::Duration =
+    chrono::Duration::days(30);
+/// Scheme of the `Authorization` header for API keys. Access tokens
+/// are sent without a scheme.
+const API_KEY_SCHEME: &str = "ApiKey ";
+/// How exact the last use of a login is, saves a write per request
+const LAST_USE_PRECISION: chrono::Duration = chrono::Duration::minutes(1);
+/// How long the link of a verification mail works
+const VERIFICATION_TOKEN_LIFETIME: chrono::Duration =
+    chrono::Duration::days(2);
+
+/// Creates an unverified account and mails it a verification link
+pub async fn register<S: Store>(
+    store: S,
+    mailer: Arc<dyn Mailer>,
     account: Account,
 ) -> Result<impl Reply, Rejection> {
     let hashed_password = hash_password(account.password.as_bytes());
 
+    // New accounts are always plain users, admins hand out other roles
     let account = Account {
         id: account.id,
         email: account.email,
         password: hashed_password,
+        role: Role::User,
+        verified: false,
     };
 
-    match store.add_account(account).await {
-        Ok(_) => Ok(warp::reply::json(&"Account added".to_string())),
-        Err(e) => Err(warp::reject::custom(e)),
+    store.add_account(account.clone()).await?;
+    let account = store.get_account(account.email).await?;
+    // Without the mail the address can never be verified, so the
+    // account is deleted again and the address can register anew
+    let sent = send_verification(&store, mailer.as_ref(), &account).await;
+    if let Err(e) = sent {
+        if let Some(id) = &account.id {
+            store.delete_account(id).await?;
+        }
+        return Err(warp::reject::custom(e));
+    }
+
+    Ok(warp::reply::json(&"Account added".to_string()))
+}
+
+/// Sends a new verification mail, for when the last one got lost or
+/// expired
+pub async fn resend_verification<S: Store>(
+    session: Session,
+    store: S,
+    mailer: Arc<dyn Mailer>,
+) -> Result<impl Reply, Rejection> {
+    let account =
+        match store.get_account_by_id(&session.account_id).await? {
+            Some(account) => account,
+            None => {
+                return Err(warp::reject::custom(
+                    error_handlers::Error::AccountNotFound,
+                ))
+            }
+        };
+    if account.verified {
+        return Ok(warp::reply::json(
+            &"Account already verified".to_string(),
+        ));
+    }
+
+    send_verification(&store, mailer.as_ref(), &account).await?;
+    Ok(warp::reply::json(&"Verification mail sent".to_string()))
+}
+
+async fn send_verification<S: Store>(
+    store: &S,
+    mailer: &dyn Mailer,
+    account: &Account,
+) -> Result<(), error_handlers::Error> {
+    let token = random_token();
+    store
+        .add_account_token(AccountToken {
+            token_hash: hash_token(&token),
+            account_id: account.id.clone().expect("id not found"),
+            purpose: TokenPurpose::EmailVerification,
+            expires_on: (Utc::now() + VERIFICATION_TOKEN_LIFETIME)
+                .naive_utc(),
+        })
+        .await?;
+
+    mailer
+        .send(Mail {
+            to: account.email.clone(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Open /verify?token={} to verify your email address. \
+                The link expires in {} hours.\n",
+                token,
+                VERIFICATION_TOKEN_LIFETIME.num_hours()
+            ),
+        })
+        .await
+}
+
+/// Marks the account of the token as verified. The tokens of the
+/// account still say unverified, the next refresh picks it up.
+pub async fn verify<S: Store>(
+    params: HashMap<String, String>,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let invalid = || {
+        warp::reject::custom(error_handlers::Error::InvalidParameter(
+            "token".to_string(),
+        ))
+    };
+
+    let token = params.get("token").ok_or_else(invalid)?;
+    let token = store
+        .take_account_token(
+            &hash_token(token),
+            TokenPurpose::EmailVerification,
+        )
+        .await?;
+    let token = match token {
+        Some(token) if token.expires_on >= Utc::now().naive_utc() => token,
+        _ => return Err(invalid()),
+    };
+
+    if !store.set_verified(&token.account_id).await? {
+        return Err(warp::reject::custom(
+            error_handlers::Error::AccountNotFound,
+        ));
     }
+
+    Ok(warp::reply::json(&"Account verified".to_string()))
 }
 
-pub async fn login(
-    store: Store,
+/// Logs in with email address and password. Failed logins are counted
+/// per email address and per IP address, once either had too many the
+/// login is refused with the time until the next try.
+pub async fn login<S: Store>(
+    store: S,
+    keyring: Keyring,
+    remote: Option<SocketAddr>,
+    user_agent: Option<String>,
     login: Account,
 ) -> Result<impl Reply, Rejection> {
-    match store.get_account(login.email).await {
+    let now = Utc::now().naive_utc();
+    let mut keys = vec![format!("email:{}", login.email.to_lowercase())];
+    if let Some(remote) = remote {
+        keys.push(format!("ip:{}", remote.ip()));
+    }
+
+    for key in &keys {
+        let wait = store
+            .get_login_attempts(key)
+            .await?
+            .and_then(|attempts| attempts.retry_after(now));
+        if let Some(wait) = wait {
+            // Rounded up, a client waiting as long as told gets through
+            let seconds = (wait.num_milliseconds() + 999) / 1000;
+            return Err(warp::reject::custom(
+                error_handlers::Error::TooManyAttempts(seconds),
+            ));
+        }
+    }
+
+    let account = match store.get_account(login.email).await {
         Ok(account) => match verify_password(
             &account.password,
             login.password.as_bytes(),
         ) {
-            Ok(verified) => {
-                if verified {
-                    Ok(warp::reply::json(&issue_token(
-                        account.id.expect("id not found"),
-                    )))
-                } else {
-                    Err(warp::reject::custom(
-                        error_handlers::Error::WrongPassword,
-                    ))
-                }
+            Ok(verified) => verified.then_some(account),
+            Err(e) => {
+                return Err(warp::reject::custom(
+                    error_handlers::Error::ArgonLibraryError(e),
+                ))
             }
-            Err(e) => Err(warp::reject::custom(
-                error_handlers::Error::ArgonLibraryError(e),
-            )),
         },
-        Err(_) => {
-            Err(warp::reject::custom(error_handlers::Error::WrongPassword))
+        // Unknown accounts count as failed logins, a failing database
+        // does not
+        Err(error_handlers::Error::DatabaseQueryError(
+            sqlx::Error::RowNotFound,
+        )) => None,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let account = match account {
+        Some(account) => account,
+        None => {
+            for key in &keys {
+                store
+                    .add_failed_login(key, now, now - ATTEMPT_WINDOW)
+                    .await?;
+            }
+            return Err(warp::reject::custom(
+                error_handlers::Error::WrongPassword,
+            ));
         }
+    };
+
+    // Only the address starts over, other accounts behind the same IP
+    // address do not make up for failures
+    store.clear_login_attempts(&keys[0]).await?;
+
+    match start_session(&store, &keyring, &account, user_agent, remote)
+        .await
+    {
+        Ok(tokens) => Ok(warp::reply::json(&tokens)),
+        Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
-fn hash_password(password: &[u8]) -> String {
+/// Starts a new login of the account on the device the request came
+/// from and issues its first token pair
+pub(crate) async fn start_session<S: Store>(
+    store: &S,
+    keyring: &Keyring,
+    account: &Account,
+    user_agent: Option<String>,
+    remote: Option<SocketAddr>,
+) -> Result<TokenPair, error_handlers::Error> {
+    let now = Utc::now().naive_utc().trunc_subsecs(6);
+    let session_id = Uuid::new_v4().to_string();
+    store
+        .add_session(SessionInfo {
+            session_id: session_id.clone(),
+            account_id: account.id.clone().expect("id not found"),
+            created_on: now,
+            last_used: now,
+            user_agent,
+            ip: remote.map(|remote| remote.ip().to_string()),
+            current: false,
+        })
+        .await?;
+
+    issue_token_pair(store, keyring, account, session_id).await
+}
+
+/// Ends a login: its refresh tokens are deleted and its id goes on the
+/// revocation list until its last access token expired
+pub(crate) async fn end_session<S: Store>(
+    store: &S,
+    session_id: &str,
+) -> Result<(), error_handlers::Error> {
+    store.revoke_session(session_id).await?;
+    store
+        .revoke_token(
+            session_id,
+            (Utc::now() + ACCESS_TOKEN_LIFETIME).naive_utc(),
+        )
+        .await
+}
+
+/// Ends every login of an account except `except` the same way
+pub(crate) async fn end_account_sessions<S: Store>(
+    store: &S,
+    account_id: &AccountId,
+    except: Option<&str>,
+) -> Result<(), error_handlers::Error> {
+    store
+        .revoke_account_sessions(
+            account_id,
+            except,
+            (Utc::now() + ACCESS_TOKEN_LIFETIME).naive_utc(),
+        )
+        .await
+}
+
+pub(crate) fn hash_password(password: &[u8]) -> String {
     let salt = rand::thread_rng().gen::<[u8; 32]>();
     let config = Config::default();
     argon2::hash_encoded(password, &salt, &config).unwrap()
 }
 
-fn verify_password(
+pub(crate) fn verify_password(
     hash: &str,
     password: &[u8],
 ) -> Result<bool, argon2::Error> {
     argon2::verify_encoded(hash, password)
 }
 
+/// Checks a token with the key named in its footer. Local and public
+/// tokens are both accepted, whatever the token mode.
 pub fn verify_token(
+    keyring: &Keyring,
     token: String,
 ) -> Result<Session, error_handlers::Error> {
-    let key = env::var("PASETO_KEY").unwrap();
-    let token = paseto::tokens::validate_local_token(
-        &token,
-        None,
-        key.as_bytes(),
+    let message = keyring
+        .open(&token)
+        .ok_or(error_handlers::Error::CannotDecryptToken)?;
+    let token = paseto::tokens::validate_potential_json_blob(
+        &message,
         &paseto::tokens::TimeBackend::Chrono,
     )
     .map_err(|_| error_handlers::Error::CannotDecryptToken)?;
@@ -83,46 +318,265 @@ pub fn verify_token(
         .map_err(|_| error_handlers::Error::CannotDecryptToken)
 }
 
-fn issue_token(account_id: AccountId) -> String {
-    let key = env::var("PASETO_KEY").unwrap();
-
+/// Short-lived access token of a login. Carries what the account may
+/// do at the time the token is issued.
+pub(crate) fn issue_token(
+    keyring: &Keyring,
+    account: &Account,
+    session_id: &str,
+) -> String {
     let current_date_time = Utc::now();
-    let dt = current_date_time + chrono::Duration::days(1);
+    let dt = current_date_time + ACCESS_TOKEN_LIFETIME;
+
+    let claims = serde_json::json!({
+        "exp": dt,
+        "nbf": current_date_time,
+        "account_id": account.id,
+        "token_id": Uuid::new_v4().to_string(),
+        "session_id": session_id,
+        "role": account.role,
+        "verified": account.verified,
+    });
+    keyring.seal(&claims.to_string())
+}
+
+/// The public keys of the public token modes, so other services can
+/// verify sessions without the shared key
+pub async fn public_keys(
+    keyring: Keyring,
+) -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&keyring.public_keys()))
+}
+
+/// Random token for refresh tokens and the tokens sent by mail
+pub(crate) fn random_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// The tokens from `random_token` can not be guessed, so a plain hash
+/// is enough to store them and still find them by the token
+pub(crate) fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Issues a new access token and refresh token for a login and stores
+/// the hash of the refresh token
+pub(crate) async fn issue_token_pair<S: Store>(
+    store: &S,
+    keyring: &Keyring,
+    account: &Account,
+    session_id: String,
+) -> Result<TokenPair, error_handlers::Error> {
+    let refresh_token = random_token();
+    let access_token = issue_token(keyring, account, &session_id);
+
+    store
+        .add_refresh_token(RefreshToken {
+            token_hash: hash_token(&refresh_token),
+            session_id,
+            account_id: account.id.clone().expect("id not found"),
+            expires_on: (Utc::now() + REFRESH_TOKEN_LIFETIME).naive_utc(),
+            used: false,
+        })
+        .await?;
+
+    Ok(TokenPair {
+        access_token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_LIFETIME.num_seconds(),
+    })
+}
+
+/// Exchanges a refresh token for a new token pair. Every refresh token
+/// can be used once. Presenting one a second time means it leaked, so
+/// the whole login is revoked.
+pub async fn refresh<S: Store>(
+    store: S,
+    keyring: Keyring,
+    request: RefreshRequest,
+) -> Result<impl Reply, Rejection> {
+    let token_hash = hash_token(&request.refresh_token);
+    let token = match store.use_refresh_token(&token_hash).await {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::Unauthorized,
+            ))
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    if token.used {
+        tracing::event!(
+            tracing::Level::WARN,
+            session_id = token.session_id.as_str(),
+            "Refresh token reused, revoking the session"
+        );
+        if let Err(e) = end_session(&store, &token.session_id).await {
+            return Err(warp::reject::custom(e));
+        }
+        return Err(warp::reject::custom(
+            error_handlers::Error::Unauthorized,
+        ));
+    }
+    if token.expires_on < Utc::now().naive_utc() {
+        return Err(warp::reject::custom(
+            error_handlers::Error::Unauthorized,
+        ));
+    }
+
+    // The role or the verification may have changed since the last
+    // token, and the account may be gone
+    let account = match store.get_account_by_id(&token.account_id).await {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::Unauthorized,
+            ))
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let now = Utc::now().naive_utc();
+    store
+        .touch_session(&token.session_id, now, now - LAST_USE_PRECISION)
+        .await?;
 
-    paseto::tokens::PasetoBuilder::new()
-        .set_encryption_key(&Vec::from(key.as_bytes()))
-        .set_expiration(&dt)
-        .set_claim("account_id", serde_json::json!(account_id))
-        .build()
-        .expect("Failed to construct paseto token w/ builder!")
+    match issue_token_pair(&store, &keyring, &account, token.session_id)
+        .await
+    {
+        Ok(tokens) => Ok(warp::reply::json(&tokens)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Ends the login of the request, with every access and refresh token
+/// of it
+pub async fn logout<S: Store>(
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    match end_session(&store, &session.session_id).await {
+        Ok(_) => Ok(warp::reply::json(&"Logged out".to_string())),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
 }
 
-pub fn auth(
+/// Extracts the session from the `Authorization` header, which holds
+/// either an access token or `ApiKey <key>`. Tokens on the revocation
+/// list are rejected, as are the tokens of ended logins. Records the
+/// last use of the login.
+pub fn auth<S: Store>(
+    store: S,
+    keyring: Keyring,
 ) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
-    warp::header::<String>("Authorization").and_then(|token: String| {
-        let token = match verify_token(token) {
-            Ok(t) => t,
-            Err(_) => {
-                return future::ready(Err(warp::reject::custom(
-                    error_handlers::Error::Unauthorized,
-                )))
+    warp::header::<String>("Authorization")
+        .and(warp::any().map(move || store.clone()))
+        .and(warp::any().map(move || keyring.clone()))
+        .and_then(|token: String, store: S, keyring: Keyring| async move {
+            if let Some(key) = token.strip_prefix(API_KEY_SCHEME) {
+                return api_key_session(&store, key).await;
             }
-        };
 
-        future::ready(Ok(token))
+            let session = match verify_token(&keyring, token) {
+                Ok(session) => session,
+                Err(_) => {
+                    return Err(warp::reject::custom(
+                        error_handlers::Error::Unauthorized,
+                    ))
+                }
+            };
+
+            for id in [&session.token_id, &session.session_id] {
+                if store.is_token_revoked(id).await? {
+                    return Err(warp::reject::custom(
+                        error_handlers::Error::Unauthorized,
+                    ));
+                }
+            }
+
+            let now = Utc::now().naive_utc();
+            store
+                .touch_session(
+                    &session.session_id,
+                    now,
+                    now - LAST_USE_PRECISION,
+                )
+                .await?;
+            Ok(session)
+        })
+}
+
+/// Session of a request with an API key. It lasts for the request only
+/// and has the role and verification the account has now.
+async fn api_key_session<S: Store>(
+    store: &S,
+    key: &str,
+) -> Result<Session, Rejection> {
+    let unauthorized =
+        || warp::reject::custom(error_handlers::Error::Unauthorized);
+
+    let api_key = store
+        .get_api_key_by_hash(&hash_token(key))
+        .await?
+        .ok_or_else(unauthorized)?;
+    let account = store
+        .get_account_by_id(&api_key.account_id)
+        .await?
+        .ok_or_else(unauthorized)?;
+
+    let now = Utc::now();
+    let id = format!("api-key-{}", api_key.id.0);
+    Ok(Session {
+        exp: now,
+        account_id: api_key.account_id,
+        nbf: now,
+        token_id: id.clone(),
+        session_id: id,
+        role: account.role,
+        verified: account.verified,
+        scopes: Some(api_key.scopes),
     })
 }
 
+/// Access token of a verified account with the given role, for the
+/// route tests
+#[cfg(test)]
+pub(crate) fn test_token(
+    account_id: crate::types::account::AccountId,
+    role: Role,
+) -> String {
+    let account = Account {
+        id: Some(account_id),
+        email: "test@example.com".to_string(),
+        password: String::new(),
+        role,
+        verified: true,
+    };
+    issue_token(&crate::keyring::test_keyring(), &account, "session")
+}
+
 #[cfg(test)]
 mod authentication_tests {
-    use super::{auth, env, issue_token, AccountId};
+    use super::{
+        auth, issue_token, test_token, verify_token, RefreshRequest, Role,
+        TokenPair,
+    };
+    use crate::keyring::{
+        test_keyring, test_signing_key, Keyring, TokenMode,
+    };
+    use crate::mailer::LocalMailer;
+    use crate::store::{AccountStore, InMemoryStore, QuestionStore};
+    use crate::types::account::{Account, AccountId};
+    use crate::types::pagination::PaginationConfig;
+    use crate::types::question::NewQuestion;
+    use std::{collections::HashMap, env, sync::Arc};
 
     #[tokio::test]
     async fn post_questions_auth() {
-        env::set_var("PASETO_KEY", "RANDOM WORDS WINTER MACINTOSH PC");
-        let token = issue_token(AccountId(3));
+        let token = test_token(AccountId(3), Role::User);
 
-        let filter = auth();
+        let filter = auth(InMemoryStore::new(), test_keyring());
 
         let res = warp::test::request()
             .header("Authorization", token)
@@ -130,4 +584,390 @@ mod authentication_tests {
 
         assert_eq!(res.await.unwrap().account_id, AccountId(3));
     }
+
+    #[tokio::test]
+    async fn refresh_and_logout() {
+        let store = InMemoryStore::new();
+        let routes = crate::test_routes(store.clone());
+        let account = Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: "secret".to_string(),
+            role: Role::Admin,
+            verified: false,
+        };
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&account)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let login: TokenPair = serde_json::from_slice(res.body()).unwrap();
+        // Registration ignores the role in the request
+        let session =
+            verify_token(&test_keyring(), login.access_token.clone())
+                .unwrap();
+        assert_eq!(session.role, Role::User);
+
+        let refresh = |token: &str| {
+            warp::test::request()
+                .method("POST")
+                .path("/token/refresh")
+                .json(&RefreshRequest {
+                    refresh_token: token.to_string(),
+                })
+                .reply(&routes)
+        };
+
+        // Rotating the refresh token invalidates the old one
+        let res = refresh(&login.refresh_token).await;
+        assert_eq!(res.status(), 200);
+        let rotated: TokenPair =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_ne!(rotated.refresh_token, login.refresh_token);
+
+        // Reusing it revokes the whole login, including the new tokens
+        assert_eq!(refresh(&login.refresh_token).await.status(), 401);
+        assert_eq!(refresh(&rotated.refresh_token).await.status(), 401);
+        for access_token in [&login.access_token, &rotated.access_token] {
+            let res = warp::test::request()
+                .path("/account/sessions")
+                .header("Authorization", access_token)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 401);
+        }
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&account)
+            .reply(&routes)
+            .await;
+        let login: TokenPair = serde_json::from_slice(res.body()).unwrap();
+        let filter = auth(store, test_keyring());
+        assert!(warp::test::request()
+            .header("Authorization", &login.access_token)
+            .filter(&filter)
+            .await
+            .is_ok());
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/logout")
+            .header("Authorization", &login.access_token)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        assert!(warp::test::request()
+            .header("Authorization", &login.access_token)
+            .filter(&filter)
+            .await
+            .is_err());
+        assert_eq!(refresh(&login.refresh_token).await.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn email_verification() {
+        let dir = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let mailer = LocalMailer::new(&dir);
+        let store = InMemoryStore::new();
+        store
+            .add_question(
+                NewQuestion {
+                    title: "Title".to_string(),
+                    content: "Content".to_string(),
+                    tags: None,
+                },
+                AccountId(2),
+                None,
+            )
+            .await
+            .unwrap();
+        let routes = crate::build_routes(
+            store,
+            PaginationConfig::default(),
+            Arc::new(mailer.clone()),
+            crate::profanity::test_moderator(),
+            crate::profanity::ModerationMode::Censor,
+            crate::keyring::test_keyring(),
+            None,
+        );
+        let account = Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: "secret".to_string(),
+            role: Role::User,
+            verified: true,
+        };
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&account)
+            .reply(&routes)
+            .await;
+        let login: TokenPair = serde_json::from_slice(res.body()).unwrap();
+        // Registration ignores the verified flag of the request
+        let session =
+            verify_token(&test_keyring(), login.access_token.clone())
+                .unwrap();
+        assert!(!session.verified);
+
+        let vote = |token: &str| {
+            warp::test::request()
+                .method("POST")
+                .path("/questions/1/vote")
+                .header("Authorization", token)
+                .body(r#"{"vote": "up"}"#)
+                .reply(&routes)
+        };
+        assert_eq!(vote(&login.access_token).await.status(), 403);
+
+        let mails = mailer.mails().await.unwrap();
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].to, "test@example.com");
+        let path = mails[0]
+            .body
+            .split_whitespace()
+            .find(|word| word.starts_with("/verify?token="))
+            .unwrap();
+
+        let verify = |path: &str| {
+            warp::test::request()
+                .method("GET")
+                .path(path)
+                .reply(&routes)
+        };
+        assert_eq!(verify("/verify?token=unknown").await.status(), 422);
+        assert_eq!(verify(path).await.status(), 200);
+        // Tokens work once
+        assert_eq!(verify(path).await.status(), 422);
+
+        // The next token pair carries the verification
+        let res = warp::test::request()
+            .method("POST")
+            .path("/token/refresh")
+            .json(&RefreshRequest {
+                refresh_token: login.refresh_token,
+            })
+            .reply(&routes)
+            .await;
+        let refreshed: TokenPair =
+            serde_json::from_slice(res.body()).unwrap();
+        assert!(
+            verify_token(&test_keyring(), refreshed.access_token.clone())
+                .unwrap()
+                .verified
+        );
+        assert_eq!(vote(&refreshed.access_token).await.status(), 200);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn key_rotation() {
+        let old = b"RANDOM WORDS WINTER MACINTOSH PC".to_vec();
+        let new = b"ANOTHER KEY WITH THIRTY TWO BYTE".to_vec();
+        let keyring = |active: &str, keys: &[(&str, &Vec<u8>)]| {
+            Keyring::new(
+                active,
+                keys.iter()
+                    .map(|(id, key)| (id.to_string(), key.to_vec()))
+                    .collect(),
+            )
+            .unwrap()
+        };
+        let account = Account {
+            id: Some(AccountId(1)),
+            email: "test@example.com".to_string(),
+            password: String::new(),
+            role: Role::User,
+            verified: true,
+        };
+
+        let before = keyring("old", &[("old", &old)]);
+        let token = issue_token(&before, &account, "session");
+
+        // Tokens of the old key stay valid until it is removed
+        let rotated = keyring("new", &[("old", &old), ("new", &new)]);
+        assert!(verify_token(&rotated, token.clone()).is_ok());
+        let issued = issue_token(&rotated, &account, "session");
+        assert!(verify_token(&before, issued.clone()).is_err());
+
+        let after = keyring("new", &[("new", &new)]);
+        assert!(verify_token(&after, token).is_err());
+        assert!(verify_token(&after, issued).is_ok());
+    }
+
+    #[tokio::test]
+    async fn registration_without_mail() {
+        // The mail directory cannot be created where a file is
+        let file = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::write(&file, "").unwrap();
+        let store = InMemoryStore::new();
+        let register = |mailer: LocalMailer| {
+            let routes = crate::build_routes(
+                store.clone(),
+                PaginationConfig::default(),
+                Arc::new(mailer),
+                crate::profanity::test_moderator(),
+                crate::profanity::ModerationMode::Censor,
+                crate::keyring::test_keyring(),
+                None,
+            );
+            async move {
+                warp::test::request()
+                    .method("POST")
+                    .path("/registration")
+                    .json(&serde_json::json!({
+                        "email": "test@example.com",
+                        "password": "secret",
+                    }))
+                    .reply(&routes)
+                    .await
+            }
+        };
+
+        let res = register(LocalMailer::new(&file)).await;
+        assert_eq!(res.status(), 500);
+        assert!(store
+            .get_account("test@example.com".to_string())
+            .await
+            .is_err());
+        std::fs::remove_file(file).unwrap();
+
+        let dir = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let res = register(LocalMailer::new(&dir)).await;
+        assert_eq!(res.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn public_tokens() {
+        let keyring = test_keyring()
+            .with_signing_keys(
+                "ed",
+                HashMap::from([("ed".to_string(), test_signing_key(1))]),
+            )
+            .unwrap()
+            .with_mode(TokenMode::PublicV4)
+            .unwrap();
+        let store = InMemoryStore::new();
+        let routes = crate::build_routes(
+            store.clone(),
+            PaginationConfig::default(),
+            crate::mailer::test_mailer(),
+            crate::profanity::test_moderator(),
+            crate::profanity::ModerationMode::Censor,
+            keyring.clone(),
+            None,
+        );
+        let account = Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: "secret".to_string(),
+            role: Role::User,
+            verified: false,
+        };
+
+        for path in ["/registration", "/login"] {
+            let res = warp::test::request()
+                .method("POST")
+                .path(path)
+                .json(&account)
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 200);
+            if path == "/login" {
+                let login: TokenPair =
+                    serde_json::from_slice(res.body()).unwrap();
+                assert!(login.access_token.starts_with("v4.public."));
+                assert!(warp::test::request()
+                    .header("Authorization", &login.access_token)
+                    .filter(&auth(store.clone(), keyring.clone()))
+                    .await
+                    .is_ok());
+            }
+        }
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/.well-known/paseto-keys")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+        let keys: serde_json::Value =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(keys["token_mode"], "public-v4");
+        assert_eq!(keys["keys"][0]["kid"], "ed");
+        assert_eq!(keys["keys"][0]["alg"], "Ed25519");
+    }
+
+    #[tokio::test]
+    async fn login_backoff() {
+        let routes = crate::test_routes(InMemoryStore::new());
+        let account = |email: &str, password: &str| Account {
+            id: None,
+            email: email.to_string(),
+            password: password.to_string(),
+            role: Role::User,
+            verified: false,
+        };
+        let res = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account("test@example.com", "secret"))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let login = |email: &str, password: &str, ip: &str| {
+            warp::test::request()
+                .method("POST")
+                .path("/login")
+                .remote_addr(format!("{}:4000", ip).parse().unwrap())
+                .json(&account(email, password))
+                .reply(&routes)
+        };
+
+        for _ in 0..3 {
+            let res = login("test@example.com", "wrong", "10.0.0.1").await;
+            assert_eq!(res.status(), 401);
+        }
+        // Even the right password has to wait now, from any address
+        let res = login("test@example.com", "secret", "10.0.0.2").await;
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.headers()["Retry-After"], "1");
+        // And so does every address from the same IP
+        let res = login("other@example.com", "wrong", "10.0.0.1").await;
+        assert_eq!(res.status(), 429);
+        let res = login("other@example.com", "wrong", "10.0.0.3").await;
+        assert_eq!(res.status(), 401);
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let res = login("test@example.com", "secret", "10.0.0.2").await;
+        assert_eq!(res.status(), 200);
+        // The successful login starts the address over
+        let res = login("test@example.com", "wrong", "10.0.0.2").await;
+        assert_eq!(res.status(), 401);
+        let res = login("test@example.com", "secret", "10.0.0.2").await;
+        assert_eq!(res.status(), 200);
+    }
 }
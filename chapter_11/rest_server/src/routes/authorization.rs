@@ -0,0 +1,165 @@
+use warp::{Filter, Rejection};
+
+use crate::types::account::{AccountId, Role, Session};
+use crate::types::api_key::Scope;
+
+/// Logs an authorization decision and turns a denial into a rejection
+pub(crate) fn authorize(
+    session: &Session,
+    action: &str,
+    allowed: bool,
+) -> Result<(), Rejection> {
+    tracing::event!(
+        tracing::Level::INFO,
+        account_id = session.account_id.0,
+        role = session.role.as_str(),
+        action,
+        allowed,
+        "Authorization decision"
+    );
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(warp::reject::custom(error_handlers::Error::Unauthorized))
+    }
+}
+
+/// Owners may change their own questions and answers, moderators
+/// anybody's. Returns the account the store has to restrict the change
+/// to, `None` for moderators.
+pub(crate) fn authorize_owner(
+    session: &Session,
+    action: &str,
+    is_owner: bool,
+) -> Result<Option<AccountId>, Rejection> {
+    let moderator = session.role >= Role::Moderator;
+    authorize(session, action, is_owner || moderator)?;
+
+    if moderator {
+        Ok(None)
+    } else {
+        Ok(Some(session.account_id.clone()))
+    }
+}
+
+/// Passes the session of `auth` on if the account has at least the
+/// given role, e.g. `require_role(auth.clone(), Role::Admin)`
+pub fn require_role<F>(
+    auth: F,
+    role: Role,
+) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (Session,), Error = Rejection> + Clone,
+{
+    auth.and_then(move |session: Session| async move {
+        let action = format!("{} route", role.as_str());
+        authorize(&session, &action, session.role >= role)?;
+        Ok::<_, Rejection>(session)
+    })
+}
+
+/// Passes the session of `auth` on if the account verified its email
+/// address, for the routes which post content
+pub fn require_verified<F>(
+    auth: F,
+) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (Session,), Error = Rejection> + Clone,
+{
+    auth.and_then(|session: Session| async move {
+        if authorize(&session, "post content", session.verified).is_err() {
+            return Err(warp::reject::custom(
+                error_handlers::Error::UnverifiedAccount,
+            ));
+        }
+        Ok::<_, Rejection>(session)
+    })
+}
+
+/// Passes the session of `auth` on if it may use the routes of the
+/// scope. Logins may use every route, API keys need the scope.
+pub fn require_scope<F>(
+    auth: F,
+    scope: Scope,
+) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (Session,), Error = Rejection> + Clone,
+{
+    auth.and_then(move |session: Session| async move {
+        let allowed = session
+            .scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope));
+        authorize(&session, scope.as_str(), allowed)?;
+        Ok::<_, Rejection>(session)
+    })
+}
+
+/// Passes the session of `auth` on if it is a login, for the routes no
+/// API key may use, like the ones managing the account and its keys
+pub fn require_login<F>(
+    auth: F,
+) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (Session,), Error = Rejection> + Clone,
+{
+    auth.and_then(|session: Session| async move {
+        authorize(&session, "login route", session.scopes.is_none())?;
+        Ok::<_, Rejection>(session)
+    })
+}
+
+#[cfg(test)]
+mod authorization_tests {
+    use super::{authorize_owner, require_role, AccountId, Role, Session};
+    use crate::keyring::test_keyring;
+    use crate::routes::authentication::{auth, test_token};
+    use crate::store::InMemoryStore;
+
+    fn session(role: Role) -> Session {
+        Session {
+            exp: chrono::Utc::now(),
+            account_id: AccountId(1),
+            nbf: chrono::Utc::now(),
+            token_id: "token".to_string(),
+            session_id: "session".to_string(),
+            role,
+            verified: true,
+            scopes: None,
+        }
+    }
+
+    #[test]
+    fn owners_and_moderators() {
+        assert_eq!(
+            authorize_owner(&session(Role::User), "update", true).unwrap(),
+            Some(AccountId(1))
+        );
+        assert!(authorize_owner(&session(Role::User), "update", false)
+            .is_err());
+        for role in [Role::Moderator, Role::Admin] {
+            assert_eq!(
+                authorize_owner(&session(role), "update", false).unwrap(),
+                None
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn roles() {
+        let filter = require_role(
+            auth(InMemoryStore::new(), test_keyring()),
+            Role::Moderator,
+        );
+        let request = |role: Role| {
+            warp::test::request()
+                .header("Authorization", test_token(AccountId(1), role))
+                .filter(&filter)
+        };
+
+        assert!(request(Role::User).await.is_err());
+        assert!(request(Role::Moderator).await.is_ok());
+        assert!(request(Role::Admin).await.is_ok());
+    }
+}
@@ -1,3 +1,14 @@
+pub(crate) mod account;
 pub(crate) mod answer;
-pub(crate) mod question;
+pub(crate) mod api_key;
 pub(crate) mod authentication;
+pub(crate) mod authorization;
+pub(crate) mod oidc;
+pub(crate) mod password;
+pub(crate) mod question;
+pub(crate) mod review;
+pub(crate) mod search;
+pub(crate) mod session;
+pub(crate) mod status;
+pub(crate) mod tag;
+pub(crate) mod vote;
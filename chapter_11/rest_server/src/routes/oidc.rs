@@ -0,0 +1,343 @@
+use std::net::SocketAddr;
+
+use chrono::{Duration, Utc};
+use warp::{http::Uri, Rejection, Reply};
+
+use error_handlers::Error;
+
+use crate::keyring::Keyring;
+use crate::oidc::Oidc;
+use crate::routes::authentication::{
+    hash_password, random_token, start_session,
+};
+use crate::store::Store;
+use crate::types::{
+    account::{Account, Role},
+    oidc::{CallbackQuery, IdTokenClaims, PendingLogin},
+};
+
+/// Cookie with the encrypted `PendingLogin`
+const LOGIN_COOKIE: &str = "oidc_login";
+/// How long the user has to log in at the provider
+const LOGIN_LIFETIME: Duration = Duration::minutes(10);
+
+/// Starts a login through the OpenID Connect provider. Redirects the
+/// browser to the provider and keeps what the callback checks in a
+/// cookie.
+pub async fn login(
+    oidc: Oidc,
+    keyring: Keyring,
+) -> Result<impl Reply, Rejection> {
+    let login = PendingLogin {
+        state: random_token(),
+        nonce: random_token(),
+        code_verifier: random_token(),
+        expires_on: (Utc::now() + LOGIN_LIFETIME).timestamp(),
+    };
+    let location =
+        oidc.authorization_url(&login).parse::<Uri>().map_err(|_| {
+            Error::OidcLoginFailed(
+                "invalid authorization endpoint".to_string(),
+            )
+        })?;
+    let cookie = keyring.encrypt(
+        &serde_json::to_string(&login).expect("Cannot serialize login"),
+    );
+
+    Ok(warp::reply::with_header(
+        warp::redirect::found(location),
+        "Set-Cookie",
+        login_cookie(&oidc, &cookie, LOGIN_LIFETIME.num_seconds()),
+    ))
+}
+
+fn login_cookie(oidc: &Oidc, value: &str, max_age: i64) -> String {
+    // Lax, the provider redirects back with a plain GET
+    let mut cookie = format!(
+        "{}={}; Path=/oidc; Max-Age={}; HttpOnly; SameSite=Lax",
+        LOGIN_COOKIE, value, max_age
+    );
+    if oidc.redirect_url().starts_with("https://") {
+        cookie.push_str("; Secure");
+    }
+    cookie
+}
+
+/// Where the provider redirects back to. Checks the login against the
+/// cookie, exchanges the code for the ID token and logs the linked
+/// account in like `/login` does.
+pub async fn callback<S: Store>(
+    query: CallbackQuery,
+    cookie: Option<String>,
+    store: S,
+    keyring: Keyring,
+    oidc: Oidc,
+    remote: Option<SocketAddr>,
+    user_agent: Option<String>,
+) -> Result<impl Reply, Rejection> {
+    let failed = |reason: &str| Error::OidcLoginFailed(reason.to_string());
+
+    if let Some(error) = query.error {
+        return Err(failed(&format!("provider answered {}", error)).into());
+    }
+    let login = cookie
+        .and_then(|cookie| keyring.open(&cookie))
+        .and_then(|login| {
+            serde_json::from_str::<PendingLogin>(&login).ok()
+        })
+        .ok_or_else(|| failed("no login in progress"))?;
+    if login.expires_on < Utc::now().timestamp() {
+        return Err(failed("login expired").into());
+    }
+    // Otherwise anybody could finish their login in the browser of
+    // somebody else
+    if query.state.as_deref() != Some(login.state.as_str()) {
+        return Err(failed("state does not match").into());
+    }
+    let code = query.code.ok_or_else(|| failed("no code"))?;
+
+    let claims = oidc.exchange_code(&code, &login).await?;
+    let account = linked_account(&store, oidc.issuer(), &claims).await?;
+    tracing::info!(
+        "Account {:?} logged in through {}",
+        account.id,
+        oidc.issuer()
+    );
+
+    let tokens =
+        start_session(&store, &keyring, &account, user_agent, remote)
+            .await?;
+    Ok(warp::reply::with_header(
+        warp::reply::json(&tokens),
+        "Set-Cookie",
+        login_cookie(&oidc, "", 0),
+    ))
+}
+
+/// The account of the user at the provider. The first login links the
+/// user to the account with the same email address, or to a new one.
+/// Only verified addresses are linked, anybody can claim the others.
+async fn linked_account<S: Store>(
+    store: &S,
+    issuer: &str,
+    claims: &IdTokenClaims,
+) -> Result<Account, Error> {
+    if let Some(account_id) =
+        store.get_identity(issuer, &claims.sub).await?
+    {
+        return store
+            .get_account_by_id(&account_id)
+            .await?
+            .ok_or(Error::AccountNotFound);
+    }
+
+    let email = match &claims.email {
+        Some(email) if claims.email_verified => email.clone(),
+        _ => {
+            return Err(Error::OidcLoginFailed(
+                "no verified email address".to_string(),
+            ))
+        }
+    };
+    let account = match store.get_account(email.clone()).await {
+        // The provider vouches for the address
+        Ok(account) if !account.verified => {
+            let id = account.id.clone().expect("id not found");
+            store.set_verified(&id).await?;
+            Account {
+                verified: true,
+                ..account
+            }
+        }
+        Ok(account) => account,
+        Err(_) => {
+            store
+                .add_account(Account {
+                    id: None,
+                    email: email.clone(),
+                    // Nobody knows it, the account logs in through the
+                    // provider or resets its password
+                    password: hash_password(random_token().as_bytes()),
+                    role: Role::User,
+                    verified: true,
+                })
+                .await?;
+            store.get_account(email).await?
+        }
+    };
+
+    store
+        .add_identity(
+            issuer,
+            &claims.sub,
+            account.id.as_ref().expect("id not found"),
+        )
+        .await?;
+    Ok(account)
+}
+
+#[cfg(test)]
+mod oidc_tests {
+    use mock_server::idp::{CLIENT_ID, CLIENT_SECRET};
+    use mock_server::MockIdp;
+    use warp::http::StatusCode;
+
+    use crate::keyring::test_keyring;
+    use crate::oidc::{Oidc, OidcConfig};
+    use crate::routes::authentication::verify_token;
+    use crate::store::{AccountStore, InMemoryStore};
+    use crate::types::account::{Account, AccountId, Role};
+    use crate::types::pagination::PaginationConfig;
+    use crate::types::token::TokenPair;
+
+    const REDIRECT_URL: &str = "http://localhost:8080/oidc/callback";
+
+    /// Cookie and provider URL of a started login
+    async fn start_login<F>(routes: &F) -> (String, String)
+    where
+        F: warp::Filter + 'static,
+        F::Extract: warp::Reply + Send,
+    {
+        let res = warp::test::request()
+            .method("GET")
+            .path("/oidc/login")
+            .reply(routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::FOUND);
+        let header =
+            |name: &str| res.headers()[name].to_str().unwrap().to_string();
+        let cookie = header("set-cookie");
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        (cookie, header("location"))
+    }
+
+    /// Logs in at the mock provider, which redirects back with the
+    /// path and query of the callback
+    async fn authorize(location: &str, params: &str) -> String {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let res = client
+            .get(format!("{}&{}", location, params))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FOUND.as_u16());
+        let location = res.headers()["location"].to_str().unwrap();
+        location
+            .strip_prefix("http://localhost:8080")
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn login() {
+        let mock = MockIdp::new("127.0.0.1:3031".parse().unwrap());
+        let handler = mock.oneshot();
+        let oidc = Oidc::discover(OidcConfig {
+            discovery_url: mock.discovery_url(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            redirect_url: REDIRECT_URL.to_string(),
+        })
+        .await
+        .unwrap();
+
+        let store = InMemoryStore::new();
+        store
+            .add_account(Account {
+                id: None,
+                email: "jane@example.com".to_string(),
+                password: "hash".to_string(),
+                role: Role::User,
+                verified: false,
+            })
+            .await
+            .unwrap();
+        let routes = crate::build_routes(
+            store.clone(),
+            PaginationConfig::default(),
+            crate::mailer::test_mailer(),
+            crate::profanity::test_moderator(),
+            crate::profanity::ModerationMode::Censor,
+            test_keyring(),
+            Some(oidc),
+        );
+        let callback = |path: String, cookie: Option<String>| {
+            let mut request =
+                warp::test::request().method("GET").path(&path);
+            if let Some(cookie) = cookie {
+                request = request.header("Cookie", cookie);
+            }
+            request.reply(&routes)
+        };
+
+        // The first login links the account with the address
+        for _ in 0..2 {
+            let (cookie, location) = start_login(&routes).await;
+            let path =
+                authorize(&location, "login_hint=jane@example.com").await;
+            let res = callback(path, Some(cookie)).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let tokens: TokenPair =
+                serde_json::from_slice(res.body()).unwrap();
+            let session =
+                verify_token(&test_keyring(), tokens.access_token)
+                    .unwrap();
+            assert_eq!(session.account_id, AccountId(1));
+            assert!(session.verified);
+        }
+
+        // A new address gets a new account
+        let (cookie, location) = start_login(&routes).await;
+        let path =
+            authorize(&location, "login_hint=joe@example.com").await;
+        assert_eq!(callback(path, Some(cookie)).await.status(), 200);
+        let account = store
+            .get_account("joe@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.id, Some(AccountId(2)));
+        assert!(account.verified);
+
+        // Unverified addresses are not linked
+        let (cookie, location) = start_login(&routes).await;
+        let path = authorize(
+            &location,
+            "login_hint=eve@example.com&email_verified=false",
+        )
+        .await;
+        assert_eq!(callback(path, Some(cookie)).await.status(), 401);
+
+        // The callback only finishes the login of the same browser
+        let (cookie, location) = start_login(&routes).await;
+        let path =
+            authorize(&location, "login_hint=jane@example.com").await;
+        assert_eq!(callback(path.clone(), None).await.status(), 401);
+        let (other_cookie, _) = start_login(&routes).await;
+        assert_eq!(
+            callback(path.clone(), Some(other_cookie)).await.status(),
+            401
+        );
+        assert_eq!(
+            callback(path.clone(), Some(cookie.clone())).await.status(),
+            200
+        );
+        // Codes work once
+        assert_eq!(callback(path, Some(cookie)).await.status(), 401);
+
+        let _ = handler.sender.send(1);
+    }
+
+    #[tokio::test]
+    async fn not_configured() {
+        let routes = crate::test_routes(InMemoryStore::new());
+        let res = warp::test::request()
+            .method("GET")
+            .path("/oidc/login")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use warp::{Rejection, Reply};
+
+use error_handlers::Error;
+
+use crate::mailer::{Mail, Mailer};
+use crate::routes::authentication::{
+    end_account_sessions, hash_password, hash_token, random_token,
+    verify_password,
+};
+use crate::store::Store;
+use crate::types::account::{
+    ChangePassword, ForgotPassword, ResetPassword, Session,
+};
+use crate::types::token::{AccountToken, TokenPurpose};
+
+/// How long the token of a password reset mail works
+const RESET_TOKEN_LIFETIME: chrono::Duration = chrono::Duration::hours(1);
+
+/// Sends a password reset token to the email address of the account.
+/// Answers the same whether there is such an account or not, so the
+/// route can not be used to find out which addresses are registered.
+pub async fn forgot_password<S: Store>(
+    store: S,
+    mailer: Arc<dyn Mailer>,
+    request: ForgotPassword,
+) -> Result<impl Reply, Rejection> {
+    let reply = warp::reply::json(
+        &"If the account exists, a reset mail was sent".to_string(),
+    );
+
+    let account = match store.get_account(request.email).await {
+        Ok(account) => account,
+        Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {
+            return Ok(reply)
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let token = random_token();
+    store
+        .add_account_token(AccountToken {
+            token_hash: hash_token(&token),
+            account_id: account.id.expect("id not found"),
+            purpose: TokenPurpose::PasswordReset,
+            expires_on: (Utc::now() + RESET_TOKEN_LIFETIME).naive_utc(),
+        })
+        .await?;
+
+    mailer
+        .send(Mail {
+            to: account.email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Use this token with POST /password/reset to choose a \
+                new password. It works once and expires in {} minutes.\n\n\
+                {}\n",
+                RESET_TOKEN_LIFETIME.num_minutes(),
+                token
+            ),
+        })
+        .await?;
+
+    Ok(reply)
+}
+
+/// Sets a new password with the token from the reset mail. Logs the
+/// account out everywhere, whoever knew the old password may have a
+/// login.
+pub async fn reset_password<S: Store>(
+    store: S,
+    request: ResetPassword,
+) -> Result<impl Reply, Rejection> {
+    let token = store
+        .take_account_token(
+            &hash_token(&request.token),
+            TokenPurpose::PasswordReset,
+        )
+        .await?;
+    let token = match token {
+        Some(token) if token.expires_on >= Utc::now().naive_utc() => token,
+        _ => {
+            return Err(warp::reject::custom(Error::InvalidParameter(
+                "token".to_string(),
+            )))
+        }
+    };
+
+    let password = hash_password(request.password.as_bytes());
+    if !store.set_password(&token.account_id, password).await? {
+        return Err(warp::reject::custom(Error::AccountNotFound));
+    }
+    end_account_sessions(&store, &token.account_id, None).await?;
+
+    Ok(warp::reply::json(&"Password changed".to_string()))
+}
+
+/// Changes the password of the logged in account. Other logins of the
+/// account are logged out, the current one stays.
+pub async fn change_password<S: Store>(
+    session: Session,
+    store: S,
+    request: ChangePassword,
+) -> Result<impl Reply, Rejection> {
+    let account = match store
+        .get_account_by_id(&session.account_id)
+        .await?
+    {
+        Some(account) => account,
+        None => return Err(warp::reject::custom(Error::AccountNotFound)),
+    };
+
+    match verify_password(
+        &account.password,
+        request.current_password.as_bytes(),
+    ) {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err(warp::reject::custom(Error::WrongPassword))
+        }
+        Err(e) => {
+            return Err(warp::reject::custom(Error::ArgonLibraryError(e)))
+        }
+    }
+
+    let password = hash_password(request.new_password.as_bytes());
+    store.set_password(&session.account_id, password).await?;
+    end_account_sessions(
+        &store,
+        &session.account_id,
+        Some(&session.session_id),
+    )
+    .await?;
+
+    Ok(warp::reply::json(&"Password changed".to_string()))
+}
+
+#[cfg(test)]
+mod password_tests {
+    use std::env;
+    use std::sync::Arc;
+
+    use crate::mailer::LocalMailer;
+    use crate::store::InMemoryStore;
+    use crate::types::account::{Account, Role};
+    use crate::types::pagination::PaginationConfig;
+    use crate::types::token::TokenPair;
+
+    #[tokio::test]
+    async fn forgot_and_reset_password() {
+        let dir = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let mailer = LocalMailer::new(&dir);
+        let routes = crate::build_routes(
+            InMemoryStore::new(),
+            PaginationConfig::default(),
+            Arc::new(mailer.clone()),
+            crate::profanity::test_moderator(),
+            crate::profanity::ModerationMode::Censor,
+            crate::keyring::test_keyring(),
+            None,
+        );
+        let account = |password: &str| Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: password.to_string(),
+            role: Role::User,
+            verified: false,
+        };
+        let post = |path: &str, body: serde_json::Value| {
+            warp::test::request()
+                .method("POST")
+                .path(path)
+                .json(&body)
+                .reply(&routes)
+        };
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account("old"))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        // Registration sent the verification mail
+        assert_eq!(mailer.mails().await.unwrap().len(), 1);
+
+        let login = |password: &str| {
+            warp::test::request()
+                .method("POST")
+                .path("/login")
+                .json(&account(password))
+                .reply(&routes)
+        };
+        let sessions = |token: &str| {
+            warp::test::request()
+                .path("/account/sessions")
+                .header("Authorization", token)
+                .reply(&routes)
+        };
+        let res = login("old").await;
+        let old: TokenPair = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(sessions(&old.access_token).await.status(), 200);
+
+        // Unknown addresses get the same answer and no mail
+        let unknown = serde_json::json!({ "email": "nobody@example.com" });
+        assert_eq!(post("/password/forgot", unknown).await.status(), 200);
+        assert_eq!(mailer.mails().await.unwrap().len(), 1);
+
+        let known = serde_json::json!({ "email": "test@example.com" });
+        assert_eq!(post("/password/forgot", known).await.status(), 200);
+        let mails = mailer.mails().await.unwrap();
+        assert_eq!(mails.len(), 2);
+        assert_eq!(mails[1].to, "test@example.com");
+        let token = mails[1].body.trim().lines().last().unwrap();
+
+        let reset =
+            serde_json::json!({ "token": token, "password": "new" });
+        assert_eq!(
+            post("/password/reset", reset.clone()).await.status(),
+            200
+        );
+        // Tokens work once
+        assert_eq!(post("/password/reset", reset).await.status(), 422);
+        // Access tokens from before the reset stop working right away
+        assert_eq!(sessions(&old.access_token).await.status(), 401);
+
+        assert_eq!(login("old").await.status(), 401);
+        let res = login("new").await;
+        assert_eq!(res.status(), 200);
+        let tokens: TokenPair =
+            serde_json::from_slice(res.body()).unwrap();
+        let res = login("new").await;
+        let other: TokenPair = serde_json::from_slice(res.body()).unwrap();
+
+        let change = |current: &str| {
+            warp::test::request()
+                .method("PUT")
+                .path("/account/password")
+                .header("Authorization", &tokens.access_token)
+                .json(&serde_json::json!({
+                    "current_password": current,
+                    "new_password": "newer",
+                }))
+                .reply(&routes)
+        };
+        assert_eq!(change("wrong").await.status(), 401);
+        assert_eq!(change("new").await.status(), 200);
+        assert_eq!(login("newer").await.status(), 200);
+        // Only the login which changed the password stays
+        assert_eq!(sessions(&tokens.access_token).await.status(), 200);
+        assert_eq!(sessions(&other.access_token).await.status(), 401);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
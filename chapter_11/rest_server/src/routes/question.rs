@@ -1,113 +1,661 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use tracing::{event, instrument, Level};
-use warp::http::StatusCode;
+use warp::http::{header::LINK, StatusCode};
+use warp::reply::{Reply, Response};
 
-use crate::profanity::check_profanity;
+use crate::profanity::{ContentModerator, ModerationMode};
+use crate::routes::authorization::{authorize, authorize_owner};
+use crate::routes::review::{posted_status, review_for};
 use crate::store::Store;
 use crate::types::account::Session;
-use crate::types::pagination::{extract_pagination, Pagination};
-use crate::types::question::{NewQuestion, Question};
+use crate::types::answer::AnswerId;
+use crate::types::pagination::{
+    extract_cursor_pagination, extract_pagination, link_header,
+    CursorPage, CursorPagination, Page, Pagination, PaginationConfig,
+};
+use crate::types::question::{
+    extract_question_query, NewQuestion, Question, QuestionQuery,
+    QuestionSort, QuestionWithAnswers,
+};
+use crate::types::review::ReviewTarget;
+use crate::types::tag::normalize_tags;
 
+/// Lists the questions. Paginated responses carry a `Link` header with
+/// the first, previous, next and last page. With `envelope=true` the
+/// questions are wrapped in a `Page` with the total count.
 #[instrument]
-pub async fn get_questions(
+pub async fn get_questions<S: Store>(
+    pairs: Vec<(String, String)>,
+    config: PaginationConfig,
+    store: S,
+) -> Result<Response, warp::Rejection> {
+    event!(target: "practical_rust_book", Level::INFO, "querying questions");
+    let query = extract_question_query(&pairs)?;
+    let params: HashMap<String, String> = pairs.iter().cloned().collect();
+
+    if let Some(pagination) = extract_cursor_pagination(&params, &config)?
+    {
+        event!(Level::INFO, cursor = true);
+        return get_questions_page(query, pagination, store).await;
+    }
+
+    let envelope = match params.get("envelope") {
+        None => false,
+        Some(envelope) => envelope.parse::<bool>().map_err(|_| {
+            error_handlers::Error::InvalidParameter("envelope".to_string())
+        })?,
+    };
+
+    let mut pagination = Pagination::default();
+
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params, &config)?;
+    }
+
+    let questions = store
+        .get_questions(&query, pagination.limit, pagination.offset)
+        .await?;
+
+    // Counting is an extra query, only done when the total is needed
+    if !envelope && pagination.limit.is_none() {
+        return Ok(warp::reply::json(&questions).into_response());
+    }
+    let total = store.count_questions(&query).await?;
+
+    let reply = if envelope {
+        warp::reply::json(&Page {
+            items: questions,
+            total,
+            limit: pagination.limit,
+            offset: pagination.offset,
+        })
+    } else {
+        warp::reply::json(&questions)
+    };
+
+    match link_header("/questions", &pairs, &pagination, total) {
+        Some(links) => {
+            Ok(warp::reply::with_header(reply, LINK, links)
+                .into_response())
+        }
+        None => Ok(reply.into_response()),
+    }
+}
+
+/// A page of questions for keyset pagination, with the cursor of the
+/// next page
+async fn get_questions_page<S: Store>(
+    query: QuestionQuery,
+    pagination: CursorPagination,
+    store: S,
+) -> Result<Response, warp::Rejection> {
+    // Cursors follow the order in which the questions were asked
+    if query.sort != QuestionSort::None {
+        return Err(warp::reject::custom(
+            error_handlers::Error::InvalidParameter("sort".to_string()),
+        ));
+    }
+    if pagination.limit <= 0 {
+        return Err(warp::reject::custom(
+            error_handlers::Error::InvalidParameter("limit".to_string()),
+        ));
+    }
+
+    // One more than asked for tells whether there is a next page
+    let mut questions = store
+        .get_questions_after(
+            &query,
+            pagination.after.as_ref(),
+            pagination.limit + 1,
+        )
+        .await?;
+
+    let mut next = None;
+    if questions.len() > pagination.limit as usize {
+        questions.truncate(pagination.limit as usize);
+        next = questions.last().map(|(_, cursor)| cursor.encode());
+    }
+
+    Ok(warp::reply::json(&CursorPage {
+        items: questions
+            .into_iter()
+            .map(|(question, _)| question)
+            .collect::<Vec<_>>(),
+        next,
+    })
+    .into_response())
+}
+
+/// Returns one question with its answers. The answers can be paginated
+/// with the same `limit` and `offset` parameters as `/questions`.
+#[instrument]
+pub async fn get_question<S: Store>(
+    id: i32,
     params: HashMap<String, String>,
-    store: Store,
+    config: PaginationConfig,
+    store: S,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    event!(target: "practical_rust_book", Level::INFO, "querying questions");
+    event!(target: "practical_rust_book", Level::INFO, "querying question {}", id);
     let mut pagination = Pagination::default();
 
     if !params.is_empty() {
         event!(Level::INFO, pagination = true);
-        pagination = extract_pagination(params)?;
+        pagination = extract_pagination(params, &config)?;
     }
 
+    let question = match store.get_question(id).await? {
+        Some(question) => question,
+        None => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::QuestionNotFound,
+            ))
+        }
+    };
+
     match store
-        .get_questions(pagination.limit, pagination.offset)
+        .get_answers(id, pagination.limit, pagination.offset)
         .await
     {
-        Ok(res) => Ok(warp::reply::json(&res)),
+        Ok(answers) => Ok(warp::reply::json(&QuestionWithAnswers {
+            question,
+            answers,
+        })),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
-pub async fn update_question(
+pub async fn update_question<S: Store>(
     id: i32,
     session: Session,
-    store: Store,
+    store: S,
+    moderator: Arc<dyn ContentModerator>,
+    mode: ModerationMode,
     question: Question,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let account_id = session.account_id;
-    if store.is_question_owner(id, &account_id).await? {
-        let title = check_profanity(question.title);
-        let content = check_profanity(question.content);
+    let is_owner =
+        store.is_question_owner(id, &session.account_id).await?;
+    let owner = authorize_owner(&session, "update question", is_owner)?;
 
-        let (title, content) = tokio::join!(title, content);
+    let title = moderator.moderate(question.title);
+    let content = moderator.moderate(question.content);
 
-        if title.is_ok() && content.is_ok() {
+    let (title, content) = tokio::join!(title, content);
+
+    match (title, content) {
+        (Ok(title), Ok(content)) => {
             let question = Question {
                 id: question.id,
-                title: title.unwrap(),
-                content: content.unwrap(),
-                tags: question.tags,
+                title: title.censored_content.clone(),
+                content: content.censored_content.clone(),
+                tags: normalize_tags(question.tags),
+                score: question.score,
+                accepted_answer_id: question.accepted_answer_id,
             };
-            match store.update_question(question, id, account_id).await {
-                Ok(res) => Ok(warp::reply::json(&res)),
+            let review = review_for(mode, Some(&title), &content);
+            let pending = review.is_some();
+            match store.update_question(question, id, owner, review).await
+            {
+                Ok(Some(res)) => {
+                    let status = posted_status(
+                        ReviewTarget::Question(res.id.clone()),
+                        pending,
+                        title.unchecked || content.unchecked,
+                    );
+                    Ok(warp::reply::with_status(
+                        warp::reply::json(&res),
+                        status,
+                    ))
+                }
+                Ok(None) => Err(warp::reject::custom(
+                    error_handlers::Error::QuestionNotFound,
+                )),
                 Err(e) => Err(warp::reject::custom(e)),
             }
-        } else {
-            Err(warp::reject::custom(
-                title.expect_err("Expected API call to have failed here"),
-            ))
         }
-    } else {
-        Err(warp::reject::custom(error_handlers::Error::Unauthorized))
+        (Err(e), _) | (_, Err(e)) => Err(warp::reject::custom(e)),
     }
 }
 
-pub async fn delete_question(
+pub async fn delete_question<S: Store>(
     id: i32,
     session: Session,
-    store: Store,
+    store: S,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let account_id = session.account_id;
-    if store.is_question_owner(id, &account_id).await? {
-        match store.delete_question(id, account_id).await {
-            Ok(_) => Ok(warp::reply::with_status(
-                format!("Question {} deleted", id),
-                StatusCode::OK,
-            )),
-            Err(e) => Err(warp::reject::custom(e)),
-        }
-    } else {
-        Err(warp::reject::custom(error_handlers::Error::Unauthorized))
+    let is_owner =
+        store.is_question_owner(id, &session.account_id).await?;
+    let owner = authorize_owner(&session, "delete question", is_owner)?;
+
+    match store.delete_question(id, owner).await {
+        Ok(true) => Ok(warp::reply::with_status(
+            format!("Question {} deleted", id),
+            StatusCode::OK,
+        )),
+        Ok(false) => Err(warp::reject::custom(
+            error_handlers::Error::QuestionNotFound,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
-pub async fn add_question(
+pub async fn add_question<S: Store>(
     session: Session,
-    store: Store,
+    store: S,
+    moderator: Arc<dyn ContentModerator>,
+    mode: ModerationMode,
     new_question: NewQuestion,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let account_id = session.account_id;
-    let title = match check_profanity(new_question.title).await {
+    let title = match moderator.moderate(new_question.title).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    let content = match check_profanity(new_question.content).await {
+    let content = match moderator.moderate(new_question.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
     let question = NewQuestion {
-        title,
-        content,
-        tags: new_question.tags,
+        title: title.censored_content.clone(),
+        content: content.censored_content.clone(),
+        tags: normalize_tags(new_question.tags),
     };
 
-    match store.add_question(question, account_id).await {
+    let review = review_for(mode, Some(&title), &content);
+    let pending = review.is_some();
+    match store.add_question(question, account_id, review).await {
+        Ok(question) => {
+            let status = posted_status(
+                ReviewTarget::Question(question.id.clone()),
+                pending,
+                title.unchecked || content.unchecked,
+            );
+            Ok(warp::reply::with_status(
+                warp::reply::json(&question),
+                status,
+            ))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Marks an answer to the question as accepted. Only the owner of the
+/// question may do so.
+pub async fn accept_answer<S: Store>(
+    id: i32,
+    answer_id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let is_owner =
+        store.is_question_owner(id, &session.account_id).await?;
+    authorize(&session, "accept answer", is_owner)?;
+
+    match store.get_answer(answer_id).await? {
+        Some(answer) if answer.question_id.0 == id => (),
+        _ => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::AnswerNotFound,
+            ))
+        }
+    }
+
+    match store.set_accepted_answer(id, Some(answer_id)).await {
         Ok(question) => Ok(warp::reply::json(&question)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+/// Removes the accepted mark from the answer. Does nothing if the
+/// answer is not the accepted one.
+pub async fn unaccept_answer<S: Store>(
+    id: i32,
+    answer_id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let is_owner =
+        store.is_question_owner(id, &session.account_id).await?;
+    authorize(&session, "unaccept answer", is_owner)?;
+
+    let question = match store.get_question(id).await? {
+        Some(question) => question,
+        None => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::QuestionNotFound,
+            ))
+        }
+    };
+
+    if question.accepted_answer_id != Some(AnswerId(answer_id)) {
+        return Ok(warp::reply::json(&question));
+    }
+
+    match store.set_accepted_answer(id, None).await {
+        Ok(question) => Ok(warp::reply::json(&question)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod question_tests {
+    use crate::types::pagination::PaginationConfig;
+
+    use crate::routes::authentication::test_token;
+    use crate::store::{AnswerStore, InMemoryStore, QuestionStore};
+    use crate::types::account::{AccountId, Role};
+    use crate::types::answer::{AnswerId, NewAnswer};
+    use crate::types::pagination::{CursorPage, Page};
+    use crate::types::question::{NewQuestion, Question, QuestionId};
+
+    async fn seeded_store() -> InMemoryStore {
+        let store = InMemoryStore::new();
+        for i in 1..=3 {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: format!("Question {}", i),
+                        content: "How does this work?".to_string(),
+                        tags: None,
+                    },
+                    AccountId(1),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn get_questions_paginated() {
+        let filter = crate::test_routes(seeded_store().await);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions?limit=2&offset=1")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(res.status(), 200);
+        let questions: Vec<Question> =
+            serde_json::from_slice(res.body()).unwrap();
+        let titles: Vec<String> =
+            questions.into_iter().map(|q| q.title).collect();
+        assert_eq!(titles, vec!["Question 2", "Question 3"]);
+    }
+
+    #[tokio::test]
+    async fn get_questions_envelope() {
+        let filter = crate::build_routes(
+            seeded_store().await,
+            PaginationConfig { max_limit: 2 },
+            crate::mailer::test_mailer(),
+            crate::profanity::test_moderator(),
+            crate::profanity::ModerationMode::Censor,
+            crate::keyring::test_keyring(),
+            None,
+        );
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions?envelope=true&limit=2&offset=0")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers()["link"],
+            "</questions?envelope=true&limit=2&offset=0>; rel=\"first\", \
+            </questions?envelope=true&limit=2&offset=2>; rel=\"next\", \
+            </questions?envelope=true&limit=2&offset=2>; rel=\"last\""
+        );
+        let page: Page<Question> =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.limit, Some(2));
+        assert_eq!(page.offset, 0);
+
+        for path in [
+            "/questions?limit=3&offset=0",
+            "/questions?limit=2&offset=-1",
+        ] {
+            let res = warp::test::request()
+                .method("GET")
+                .path(path)
+                .reply(&filter)
+                .await;
+            assert_eq!(res.status(), 422);
+        }
+    }
+
+    #[tokio::test]
+    async fn get_questions_by_cursor() {
+        let filter = crate::test_routes(seeded_store().await);
+
+        let page = |path: String| {
+            let filter = filter.clone();
+            async move {
+                let res = warp::test::request()
+                    .method("GET")
+                    .path(&path)
+                    .reply(&filter)
+                    .await;
+                assert_eq!(res.status(), 200);
+                let page: CursorPage<Question> =
+                    serde_json::from_slice(res.body()).unwrap();
+                page
+            }
+        };
+
+        let first = page("/questions?cursor=&limit=2".to_string()).await;
+        let ids: Vec<i32> = first.items.iter().map(|q| q.id.0).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        let path =
+            format!("/questions?cursor={}&limit=2", first.next.unwrap());
+        let second = page(path).await;
+        let ids: Vec<i32> = second.items.iter().map(|q| q.id.0).collect();
+        assert_eq!(ids, vec![3]);
+        assert!(second.next.is_none());
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions?cursor=garbage&limit=2")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 422);
+    }
+
+    #[tokio::test]
+    async fn get_question_with_answers() {
+        let store = seeded_store().await;
+        for (question_id, content) in
+            [(2, "First"), (1, "Elsewhere"), (2, "Second"), (2, "Third")]
+        {
+            store
+                .add_answer(
+                    NewAnswer {
+                        content: content.to_string(),
+                        question_id: QuestionId(question_id),
+                    },
+                    AccountId(2),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let filter = crate::test_routes(store);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions/2?limit=2&offset=1")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["title"], "Question 2");
+        let answers: Vec<&str> = body["answers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["content"].as_str().unwrap())
+            .collect();
+        assert_eq!(answers, vec!["Second", "Third"]);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions/42")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(res.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn delete_question_of_other_account() {
+        let store = seeded_store().await;
+        let filter = crate::test_routes(store.clone());
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/questions/1")
+            .header("Authorization", test_token(AccountId(2), Role::User))
+            .reply(&filter)
+            .await;
+
+        assert_eq!(res.status(), 401);
+        assert!(store.is_question_owner(1, &AccountId(1)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn moderators_delete_any_question() {
+        let store = seeded_store().await;
+        let filter = crate::test_routes(store.clone());
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/questions/1")
+            .header(
+                "Authorization",
+                test_token(AccountId(2), Role::Moderator),
+            )
+            .reply(&filter)
+            .await;
+
+        assert_eq!(res.status(), 200);
+        assert!(store.get_question(1).await.unwrap().is_none());
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/questions/1")
+            .header(
+                "Authorization",
+                test_token(AccountId(2), Role::Moderator),
+            )
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn accept_answer() {
+        let store = seeded_store().await;
+        for question_id in [1, 2] {
+            store
+                .add_answer(
+                    NewAnswer {
+                        content: "Like this".to_string(),
+                        question_id: QuestionId(question_id),
+                    },
+                    AccountId(2),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let filter = crate::test_routes(store);
+
+        let accept = |path: &str, account_id: i32| {
+            warp::test::request().method("POST").path(path).header(
+                "Authorization",
+                test_token(AccountId(account_id), Role::User),
+            )
+        };
+
+        // Only the owner of the question may accept
+        let res = accept("/questions/1/accept/1", 2).reply(&filter).await;
+        assert_eq!(res.status(), 401);
+        // The answer must belong to the question
+        let res = accept("/questions/1/accept/2", 1).reply(&filter).await;
+        assert_eq!(res.status(), 404);
+
+        let res = accept("/questions/1/accept/1", 1).reply(&filter).await;
+        assert_eq!(res.status(), 200);
+        let question: Question =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(question.accepted_answer_id, Some(AnswerId(1)));
+
+        let titles = |path: &'static str| {
+            let filter = filter.clone();
+            async move {
+                let res = warp::test::request()
+                    .method("GET")
+                    .path(path)
+                    .reply(&filter)
+                    .await;
+                let questions: Vec<Question> =
+                    serde_json::from_slice(res.body()).unwrap();
+                questions.into_iter().map(|q| q.title).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(
+            titles("/questions?answered=true").await,
+            vec!["Question 1"]
+        );
+        assert_eq!(
+            titles("/questions?answered=false").await,
+            vec!["Question 2", "Question 3"]
+        );
+
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/questions/1/accept/1")
+            .header("Authorization", test_token(AccountId(1), Role::User))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let question: Question =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(question.accepted_answer_id, None);
+        assert_eq!(titles("/questions?answered=true").await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn add_question_censored() {
+        let store = InMemoryStore::new();
+        let filter = crate::test_routes(store.clone());
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("Authorization", test_token(AccountId(1), Role::User))
+            .json(&serde_json::json!({
+                "title": "Sh1tty title",
+                "content": "A shiiit question",
+            }))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        let question = store.get_question(1).await.unwrap().unwrap();
+        assert_eq!(question.title, "****** title");
+        assert_eq!(question.content, "A ****** question");
+    }
+}
@@ -0,0 +1,439 @@
+use warp::http::StatusCode;
+use warp::{Rejection, Reply};
+
+use error_handlers::Error;
+
+use crate::profanity::{flag_for_review, Moderation, ModerationMode};
+use crate::store::Store;
+use crate::types::account::Session;
+use crate::types::review::{
+    BadWords, NewReview, Review, ReviewId, ReviewTarget, ReviewText,
+};
+
+/// The review to hold a post back with in review mode, if the
+/// moderation found bad words in it or could not check it. `title` is
+/// `None` for answers. Censor mode holds nothing back.
+pub fn review_for(
+    mode: ModerationMode,
+    title: Option<&Moderation>,
+    content: &Moderation,
+) -> Option<NewReview> {
+    let moderations = || title.into_iter().chain(Some(content));
+    let unchecked = moderations().any(|m| m.unchecked);
+    let flagged = moderations().any(|m| !m.bad_words_list.is_empty());
+    if mode == ModerationMode::Censor || !(flagged || unchecked) {
+        return None;
+    }
+
+    Some(NewReview {
+        original: ReviewText {
+            title: title.map(|t| t.content.clone()),
+            content: content.content.clone(),
+        },
+        censored: ReviewText {
+            title: title.map(|t| t.censored_content.clone()),
+            content: content.censored_content.clone(),
+        },
+        bad_words: BadWords {
+            title: title
+                .map(|t| t.bad_words_list.clone())
+                .unwrap_or_default(),
+            content: content.bad_words_list.clone(),
+        },
+        unchecked,
+    })
+}
+
+/// Logs the moderation of a stored post and answers `202 Accepted` for
+/// a post held back for review, `200 OK` otherwise. Posts taken
+/// unchecked in censor mode are only flagged in the log.
+pub fn posted_status(
+    target: ReviewTarget,
+    pending: bool,
+    unchecked: bool,
+) -> StatusCode {
+    let (kind, id) = match target {
+        ReviewTarget::Question(id) => ("Question", id.0),
+        ReviewTarget::Answer(id) => ("Answer", id.0),
+    };
+
+    if pending {
+        tracing::event!(
+            tracing::Level::INFO,
+            unchecked,
+            "{} {} held back for review",
+            kind,
+            id
+        );
+        StatusCode::ACCEPTED
+    } else {
+        if unchecked {
+            flag_for_review(kind, id);
+        }
+        StatusCode::OK
+    }
+}
+
+/// The pending posts, oldest first, for moderators
+pub async fn get_reviews<S: Store>(
+    _session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    match store.get_reviews().await {
+        Ok(reviews) => Ok(warp::reply::json(&reviews)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+async fn find_review<S: Store>(
+    store: &S,
+    id: i32,
+) -> Result<Review, Rejection> {
+    match store.get_review(&ReviewId(id)).await? {
+        Some(review) => Ok(review),
+        None => Err(warp::reject::custom(Error::ReviewNotFound)),
+    }
+}
+
+/// Publishes the post as it was posted, bad words included
+pub async fn approve_review<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let review = find_review(&store, id).await?;
+    tracing::event!(
+        tracing::Level::INFO,
+        moderator = session.account_id.0,
+        review_id = id,
+        "Approving post"
+    );
+
+    if !store.publish_review(&review.id, review.original).await? {
+        return Err(warp::reject::custom(Error::ReviewNotFound));
+    }
+    Ok(warp::reply::with_status(
+        format!("Review {} approved", id),
+        StatusCode::OK,
+    ))
+}
+
+/// Publishes the post with the text of the moderator. Without a title a
+/// question keeps its censored title, answers have none.
+pub async fn edit_review<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+    text: ReviewText,
+) -> Result<impl Reply, Rejection> {
+    let review = find_review(&store, id).await?;
+    tracing::event!(
+        tracing::Level::INFO,
+        moderator = session.account_id.0,
+        review_id = id,
+        "Publishing edited post"
+    );
+
+    if !store.publish_review(&review.id, text).await? {
+        return Err(warp::reject::custom(Error::ReviewNotFound));
+    }
+    Ok(warp::reply::with_status(
+        format!("Review {} edited and published", id),
+        StatusCode::OK,
+    ))
+}
+
+/// Deletes the post, which drops the review with it
+pub async fn reject_review<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let review = find_review(&store, id).await?;
+    tracing::event!(
+        tracing::Level::INFO,
+        moderator = session.account_id.0,
+        review_id = id,
+        "Rejecting post"
+    );
+
+    let deleted = match review.target {
+        ReviewTarget::Question(question_id) => {
+            store.delete_question(question_id.0, None).await?
+        }
+        ReviewTarget::Answer(answer_id) => {
+            store.delete_answer(answer_id.0, None).await?.is_some()
+        }
+    };
+    if !deleted {
+        return Err(warp::reject::custom(Error::ReviewNotFound));
+    }
+    Ok(warp::reply::with_status(
+        format!("Review {} rejected", id),
+        StatusCode::OK,
+    ))
+}
+
+#[cfg(test)]
+mod review_tests {
+    use crate::profanity::ModerationMode;
+    use crate::routes::authentication::test_token;
+    use crate::store::InMemoryStore;
+    use crate::types::account::{AccountId, Role};
+    use crate::types::answer::Answer;
+    use crate::types::pagination::PaginationConfig;
+    use crate::types::question::Question;
+    use crate::types::review::{Review, ReviewTarget};
+
+    #[tokio::test]
+    async fn review_queue() {
+        let filter = crate::build_routes(
+            InMemoryStore::new(),
+            PaginationConfig::default(),
+            crate::mailer::test_mailer(),
+            crate::profanity::test_moderator(),
+            ModerationMode::Review,
+            crate::keyring::test_keyring(),
+            None,
+        );
+        let user = test_token(AccountId(1), Role::User);
+        let moderator = test_token(AccountId(2), Role::Moderator);
+        let post = |path: &'static str, body: serde_json::Value| {
+            warp::test::request()
+                .method("POST")
+                .path(path)
+                .header("Authorization", user.clone())
+                .json(&body)
+        };
+        let questions = || async {
+            let res = warp::test::request()
+                .path("/questions")
+                .reply(&filter)
+                .await;
+            serde_json::from_slice::<Vec<Question>>(res.body()).unwrap()
+        };
+        let reviews = || async {
+            let res = warp::test::request()
+                .path("/reviews")
+                .header("Authorization", moderator.clone())
+                .reply(&filter)
+                .await;
+            assert_eq!(res.status(), 200);
+            serde_json::from_slice::<Vec<Review>>(res.body()).unwrap()
+        };
+
+        // Flagged posts are pending, clean ones published
+        let res = post(
+            "/questions",
+            serde_json::json!({
+                "title": "Sh1tty title",
+                "content": "A fine question",
+            }),
+        )
+        .reply(&filter)
+        .await;
+        assert_eq!(res.status(), 202);
+        let res = post(
+            "/questions",
+            serde_json::json!({"title": "Title", "content": "Content"}),
+        )
+        .reply(&filter)
+        .await;
+        assert_eq!(res.status(), 200);
+        let titles: Vec<String> =
+            questions().await.into_iter().map(|q| q.title).collect();
+        assert_eq!(titles, vec!["Title"]);
+        let res = warp::test::request()
+            .path("/questions/1")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+
+        let res = warp::test::request()
+            .path("/reviews")
+            .header("Authorization", user.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 401);
+        let review = reviews().await.remove(0);
+        assert_eq!(
+            review.target,
+            ReviewTarget::Question(crate::types::question::QuestionId(1))
+        );
+        assert_eq!(review.original.title.as_deref(), Some("Sh1tty title"));
+        assert_eq!(review.censored.title.as_deref(), Some("****** title"));
+        assert_eq!(review.bad_words.title[0].word, "shitty");
+        assert!(review.bad_words.content.is_empty());
+
+        // Approved as posted
+        let res = warp::test::request()
+            .method("POST")
+            .path(&format!("/reviews/{}/approve", review.id.0))
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let titles: Vec<String> =
+            questions().await.into_iter().map(|q| q.title).collect();
+        assert_eq!(titles, vec!["Sh1tty title", "Title"]);
+        assert!(reviews().await.is_empty());
+
+        // Edited by the moderator
+        let res = warp::test::request()
+            .method("POST")
+            .path("/answers")
+            .header("Authorization", user.clone())
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("content=shit&question_id=2")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 202);
+        let answers = || async {
+            let res = warp::test::request()
+                .path("/questions/2/answers")
+                .reply(&filter)
+                .await;
+            serde_json::from_slice::<Vec<Answer>>(res.body()).unwrap()
+        };
+        assert!(answers().await.is_empty());
+        let review = reviews().await.remove(0);
+        let res = warp::test::request()
+            .method("PUT")
+            .path(&format!("/reviews/{}", review.id.0))
+            .header("Authorization", moderator.clone())
+            .json(&serde_json::json!({"content": "Oops"}))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(answers().await[0].content, "Oops");
+
+        // Rejected and deleted
+        let res = post(
+            "/questions",
+            serde_json::json!({"title": "Title", "content": "shit"}),
+        )
+        .reply(&filter)
+        .await;
+        assert_eq!(res.status(), 202);
+        let review = reviews().await.remove(0);
+        let res = warp::test::request()
+            .method("POST")
+            .path(&format!("/reviews/{}/reject", review.id.0))
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request()
+            .path("/questions/3")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+        assert!(reviews().await.is_empty());
+        let answer = |question_id: i32| {
+            warp::test::request()
+                .method("POST")
+                .path("/answers")
+                .header("Authorization", user.clone())
+                .header(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded",
+                )
+                .body(format!("content=Fine&question_id={}", question_id))
+        };
+        let res = answer(3).reply(&filter).await;
+        assert_eq!(res.status(), 404);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path(&format!("/reviews/{}/approve", review.id.0))
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+
+        // A clean edit publishes the post and drops its review
+        let res = post(
+            "/questions",
+            serde_json::json!({"title": "Title", "content": "shit"}),
+        )
+        .reply(&filter)
+        .await;
+        assert_eq!(res.status(), 202);
+        assert_eq!(reviews().await.len(), 1);
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/questions/4")
+            .header("Authorization", user.clone())
+            .json(&serde_json::json!({
+                "id": 4,
+                "title": "Title",
+                "content": "Fixed",
+                "tags": null,
+            }))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        assert!(reviews().await.is_empty());
+        let res = warp::test::request()
+            .path("/questions/4")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        // Pending questions cannot be answered
+        let res = post(
+            "/questions",
+            serde_json::json!({"title": "Title", "content": "shit"}),
+        )
+        .reply(&filter)
+        .await;
+        assert_eq!(res.status(), 202);
+        let res = answer(5).reply(&filter).await;
+        assert_eq!(res.status(), 404);
+        let review = reviews().await.remove(0);
+        let res = warp::test::request()
+            .method("POST")
+            .path(&format!("/reviews/{}/reject", review.id.0))
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+
+        // Rejecting an answered question deletes its answers too
+        let res = answer(4).reply(&filter).await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request()
+            .method("PUT")
+            .path("/questions/4")
+            .header("Authorization", user.clone())
+            .json(&serde_json::json!({
+                "id": 4,
+                "title": "Title",
+                "content": "shit",
+                "tags": null,
+            }))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 202);
+        let review = reviews().await.remove(0);
+        let res = warp::test::request()
+            .method("POST")
+            .path(&format!("/reviews/{}/reject", review.id.0))
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let res = warp::test::request()
+            .path("/questions/4/answers")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/answers/2")
+            .header("Authorization", moderator.clone())
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 404);
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use tracing::{event, instrument, Level};
+
+use crate::store::Store;
+use crate::types::pagination::{
+    extract_pagination, Pagination, PaginationConfig,
+};
+use crate::types::search::extract_search_query;
+
+/// Full-text search over questions and their answers, most relevant
+/// questions first. Paginated like `/questions`.
+#[instrument]
+pub async fn search<S: Store>(
+    params: HashMap<String, String>,
+    config: PaginationConfig,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let query = extract_search_query(&params)?;
+    event!(target: "practical_rust_book", Level::INFO, "searching for {}", query.q);
+    let mut pagination = Pagination::default();
+
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params, &config)?;
+    }
+
+    match store
+        .search(&query, pagination.limit, pagination.offset)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod search_route_tests {
+    use crate::store::{AnswerStore, InMemoryStore, QuestionStore};
+    use crate::types::account::AccountId;
+    use crate::types::answer::NewAnswer;
+    use crate::types::question::{NewQuestion, QuestionId};
+    use crate::types::search::SearchResult;
+
+    #[tokio::test]
+    async fn search_questions_and_answers() {
+        let store = InMemoryStore::new();
+        for (title, tags, account_id) in [
+            ("How do lifetimes work?", vec!["rust"], 1),
+            ("Borrowing in loops", vec!["rust"], 2),
+            ("Lifetimes of Python objects", vec!["python"], 1),
+        ] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: title.to_string(),
+                        content: "Please help".to_string(),
+                        tags: Some(
+                            tags.into_iter().map(String::from).collect(),
+                        ),
+                    },
+                    AccountId(account_id),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        store
+            .add_answer(
+                NewAnswer {
+                    content: "Think about the lifetimes".to_string(),
+                    question_id: QuestionId(2),
+                },
+                AccountId(3),
+                None,
+            )
+            .await
+            .unwrap();
+        let filter = crate::test_routes(store);
+
+        let search = |path: &'static str| {
+            let filter = filter.clone();
+            async move {
+                let res = warp::test::request()
+                    .method("GET")
+                    .path(path)
+                    .reply(&filter)
+                    .await;
+                assert_eq!(res.status(), 200);
+                let results: Vec<SearchResult> =
+                    serde_json::from_slice(res.body()).unwrap();
+                results
+                    .into_iter()
+                    .map(|r| r.question.id.0)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // Matches in the question rank above matches in an answer
+        assert_eq!(search("/search?q=LIFETIMES").await, vec![1, 3, 2]);
+        assert_eq!(
+            search("/search?q=lifetimes&tag=rust").await,
+            vec![1, 2]
+        );
+        assert_eq!(
+            search("/search?q=lifetimes&author=1").await,
+            vec![1, 3]
+        );
+        assert_eq!(
+            search("/search?q=lifetimes&limit=1&offset=1").await,
+            vec![3]
+        );
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/search")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 422);
+    }
+}
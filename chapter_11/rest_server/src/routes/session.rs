@@ -0,0 +1,162 @@
+use warp::{Rejection, Reply};
+
+use error_handlers::Error;
+
+use crate::routes::authentication::{end_account_sessions, end_session};
+use crate::store::Store;
+use crate::types::account::{Session, SessionInfo};
+
+/// The logins of the account, the one of the request marked `current`
+pub async fn get_sessions<S: Store>(
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let sessions: Vec<SessionInfo> = store
+        .get_sessions(&session.account_id)
+        .await?
+        .into_iter()
+        .map(|info| SessionInfo {
+            current: info.session_id == session.session_id,
+            ..info
+        })
+        .collect();
+
+    Ok(warp::reply::json(&sessions))
+}
+
+/// Ends a login of the account, its access tokens stop working right
+/// away
+pub async fn delete_session<S: Store>(
+    id: String,
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let sessions = store.get_sessions(&session.account_id).await?;
+    if !sessions.iter().any(|info| info.session_id == id) {
+        return Err(warp::reject::custom(Error::SessionNotFound));
+    }
+
+    end_session(&store, &id).await?;
+    Ok(warp::reply::json(&format!("Session {} revoked", id)))
+}
+
+/// Ends every login of the account except the one of the request
+pub async fn delete_other_sessions<S: Store>(
+    session: Session,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    end_account_sessions(
+        &store,
+        &session.account_id,
+        Some(&session.session_id),
+    )
+    .await?;
+
+    Ok(warp::reply::json(&"Other sessions revoked".to_string()))
+}
+
+#[cfg(test)]
+mod session_tests {
+    use crate::keyring::test_keyring;
+    use crate::routes::authentication::{hash_password, verify_token};
+    use crate::store::{AccountStore, InMemoryStore};
+    use crate::types::account::{Account, SessionInfo};
+    use crate::types::token::TokenPair;
+
+    #[tokio::test]
+    async fn sessions() {
+        let store = InMemoryStore::new();
+        store
+            .add_account(Account {
+                id: None,
+                email: "test@example.com".to_string(),
+                password: hash_password(b"secret"),
+                role: Default::default(),
+                verified: true,
+            })
+            .await
+            .unwrap();
+        let routes = crate::test_routes(store);
+
+        let mut tokens = Vec::new();
+        for user_agent in ["phone", "laptop", "tablet"] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/login")
+                .remote_addr("192.0.2.1:4711".parse().unwrap())
+                .header("User-Agent", user_agent)
+                .json(&serde_json::json!({
+                    "email": "test@example.com",
+                    "password": "secret",
+                }))
+                .reply(&routes)
+                .await;
+            assert_eq!(res.status(), 200);
+            let pair: TokenPair =
+                serde_json::from_slice(res.body()).unwrap();
+            tokens.push(pair);
+        }
+        let session_id = |pair: &TokenPair| {
+            verify_token(&test_keyring(), pair.access_token.clone())
+                .unwrap()
+                .session_id
+        };
+        let request = |method: &str, path: &str, token: &str| {
+            warp::test::request()
+                .method(method)
+                .path(path)
+                .header("Authorization", token)
+                .reply(&routes)
+        };
+        let (phone, laptop, tablet) =
+            (&tokens[0].access_token, &tokens[1], &tokens[2]);
+
+        let res = request("GET", "/account/sessions", phone).await;
+        assert_eq!(res.status(), 200);
+        let sessions: Vec<SessionInfo> =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(sessions.len(), 3);
+        let current: Vec<_> =
+            sessions.iter().filter(|s| s.current).collect();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].session_id, session_id(&tokens[0]));
+        assert_eq!(current[0].user_agent.as_deref(), Some("phone"));
+        assert_eq!(current[0].ip.as_deref(), Some("192.0.2.1"));
+
+        // The access token of a revoked session stops working at once
+        let path = format!("/account/sessions/{}", session_id(laptop));
+        assert_eq!(request("DELETE", &path, phone).await.status(), 200);
+        assert_eq!(request("DELETE", &path, phone).await.status(), 404);
+        assert_eq!(
+            request("GET", "/account/sessions", &laptop.access_token)
+                .await
+                .status(),
+            401
+        );
+        let res = warp::test::request()
+            .method("POST")
+            .path("/token/refresh")
+            .json(&serde_json::json!({
+                "refresh_token": laptop.refresh_token,
+            }))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+
+        assert_eq!(
+            request("DELETE", "/account/sessions", phone).await.status(),
+            200
+        );
+        assert_eq!(
+            request("GET", "/account/sessions", &tablet.access_token)
+                .await
+                .status(),
+            401
+        );
+        let res = request("GET", "/account/sessions", phone).await;
+        let sessions: Vec<SessionInfo> =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].current);
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use crate::profanity::ContentModerator;
+
+/// How the dependencies of the service are doing, for monitoring
+pub async fn get_status(
+    moderator: Arc<dyn ContentModerator>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&json!({
+        "moderation": moderator.status(),
+    })))
+}
+
+#[cfg(test)]
+mod status_tests {
+    use crate::store::InMemoryStore;
+
+    #[tokio::test]
+    async fn status() {
+        let filter = crate::test_routes(InMemoryStore::new());
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/status")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let status: serde_json::Value =
+            serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            status,
+            serde_json::json!({ "moderation": { "backend": "dictionary" } })
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use tracing::{event, instrument, Level};
+
+use crate::store::Store;
+
+/// Every tag in use with the number of questions carrying it
+#[instrument]
+pub async fn get_tags<S: Store>(
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "practical_rust_book", Level::INFO, "querying tags");
+
+    match store.get_tags().await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod tag_route_tests {
+    use crate::store::{InMemoryStore, QuestionStore};
+    use crate::types::account::AccountId;
+    use crate::types::question::{NewQuestion, Question};
+    use crate::types::tag::TagCount;
+
+    #[tokio::test]
+    async fn tags_and_tag_filters() {
+        let store = InMemoryStore::new();
+        for tags in [vec!["rust", "warp"], vec!["rust"], vec!["python"]] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: "Question".to_string(),
+                        content: "How does this work?".to_string(),
+                        tags: Some(
+                            tags.into_iter().map(String::from).collect(),
+                        ),
+                    },
+                    AccountId(1),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let filter = crate::test_routes(store);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/tags")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        let tags: Vec<TagCount> =
+            serde_json::from_slice(res.body()).unwrap();
+        let tags: Vec<(&str, i64)> =
+            tags.iter().map(|t| (t.tag.as_str(), t.count)).collect();
+        assert_eq!(tags, vec![("rust", 2), ("python", 1), ("warp", 1)]);
+
+        let ids = |path: &'static str| {
+            let filter = filter.clone();
+            async move {
+                let res = warp::test::request()
+                    .method("GET")
+                    .path(path)
+                    .reply(&filter)
+                    .await;
+                assert_eq!(res.status(), 200);
+                let questions: Vec<Question> =
+                    serde_json::from_slice(res.body()).unwrap();
+                questions.into_iter().map(|q| q.id.0).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            ids("/questions?tag=WARP&tag=python").await,
+            vec![1, 3]
+        );
+        assert_eq!(
+            ids("/questions?tag=rust&tag=warp&tag_match=all").await,
+            vec![1]
+        );
+        assert_eq!(
+            ids("/questions?tag=rust&limit=1&offset=1").await,
+            vec![2]
+        );
+    }
+}
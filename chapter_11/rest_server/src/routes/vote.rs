@@ -0,0 +1,207 @@
+use crate::store::Store;
+use crate::types::account::Session;
+use crate::types::vote::NewVote;
+
+pub async fn vote_question<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+    new_vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut question = match store.get_question(id).await? {
+        Some(question) => question,
+        None => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::QuestionNotFound,
+            ))
+        }
+    };
+
+    match store
+        .vote_question(id, &session.account_id, new_vote.vote)
+        .await
+    {
+        Ok(score) => {
+            question.score = score;
+            Ok(warp::reply::json(&question))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn retract_question_vote<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut question = match store.get_question(id).await? {
+        Some(question) => question,
+        None => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::QuestionNotFound,
+            ))
+        }
+    };
+
+    match store.retract_question_vote(id, &session.account_id).await {
+        Ok(score) => {
+            question.score = score;
+            Ok(warp::reply::json(&question))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn vote_answer<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+    new_vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut answer = match store.get_answer(id).await? {
+        Some(answer) => answer,
+        None => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::AnswerNotFound,
+            ))
+        }
+    };
+
+    match store
+        .vote_answer(id, &session.account_id, new_vote.vote)
+        .await
+    {
+        Ok(score) => {
+            answer.score = score;
+            Ok(warp::reply::json(&answer))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn retract_answer_vote<S: Store>(
+    id: i32,
+    session: Session,
+    store: S,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut answer = match store.get_answer(id).await? {
+        Some(answer) => answer,
+        None => {
+            return Err(warp::reject::custom(
+                error_handlers::Error::AnswerNotFound,
+            ))
+        }
+    };
+
+    match store.retract_answer_vote(id, &session.account_id).await {
+        Ok(score) => {
+            answer.score = score;
+            Ok(warp::reply::json(&answer))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod vote_tests {
+    use crate::routes::authentication::test_token;
+    use crate::store::{InMemoryStore, QuestionStore};
+    use crate::types::account::{AccountId, Role};
+    use crate::types::question::{NewQuestion, Question};
+
+    async fn vote(
+        filter: &(impl warp::Filter<
+            Extract = (impl warp::Reply,),
+            Error = warp::Rejection,
+        > + Clone
+              + 'static),
+        method: &str,
+        path: &str,
+        account_id: i32,
+        body: &str,
+    ) -> Question {
+        let res = warp::test::request()
+            .method(method)
+            .path(path)
+            .header(
+                "Authorization",
+                test_token(AccountId(account_id), Role::User),
+            )
+            .body(body)
+            .reply(filter)
+            .await;
+        assert_eq!(res.status(), 200);
+        serde_json::from_slice(res.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn vote_on_questions() {
+        let store = InMemoryStore::new();
+        for title in ["Question 1", "Question 2"] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: title.to_string(),
+                        content: "How does this work?".to_string(),
+                        tags: None,
+                    },
+                    AccountId(1),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let filter = crate::test_routes(store);
+
+        let up = r#"{"vote": "up"}"#;
+        let down = r#"{"vote": "down"}"#;
+        assert_eq!(
+            vote(&filter, "POST", "/questions/2/vote", 2, up)
+                .await
+                .score,
+            1
+        );
+        assert_eq!(
+            vote(&filter, "POST", "/questions/2/vote", 3, up)
+                .await
+                .score,
+            2
+        );
+        // Voting again replaces the earlier vote of the account
+        assert_eq!(
+            vote(&filter, "POST", "/questions/2/vote", 3, down)
+                .await
+                .score,
+            0
+        );
+        assert_eq!(
+            vote(&filter, "DELETE", "/questions/2/vote", 3, "")
+                .await
+                .score,
+            1
+        );
+        assert_eq!(
+            vote(&filter, "POST", "/questions/1/vote", 2, down)
+                .await
+                .score,
+            -1
+        );
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/questions?sort=score")
+            .reply(&filter)
+            .await;
+        let questions: Vec<Question> =
+            serde_json::from_slice(res.body()).unwrap();
+        let scores: Vec<(String, i32)> =
+            questions.into_iter().map(|q| (q.title, q.score)).collect();
+        assert_eq!(
+            scores,
+            vec![
+                ("Question 2".to_string(), 1),
+                ("Question 1".to_string(), -1)
+            ]
+        );
+    }
+}
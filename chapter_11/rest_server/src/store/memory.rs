@@ -0,0 +1,1678 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, SubsecRound, Utc};
+use tokio::sync::RwLock;
+
+use error_handlers::Error;
+
+use crate::store::{
+    AccountStore, AnswerStore, ApiKeyStore, IdentityStore,
+    LoginAttemptStore, QuestionStore, ReviewStore, SearchStore,
+    SessionStore, TokenStore, VoteStore,
+};
+use crate::types::{
+    account::{Account, AccountId, Role, SessionInfo},
+    answer::{Answer, AnswerId, NewAnswer},
+    api_key::{ApiKey, ApiKeyId, NewApiKey},
+    login_attempt::LoginAttempts,
+    pagination::Cursor,
+    question::{
+        NewQuestion, Question, QuestionId, QuestionQuery, QuestionSort,
+    },
+    review::{NewReview, Review, ReviewId, ReviewTarget, ReviewText},
+    search::{SearchQuery, SearchResult},
+    tag::{TagCount, TagMatch},
+    token::{AccountToken, RefreshToken, TokenPurpose},
+    vote::Vote,
+};
+
+/// A stored item together with the account which created it and when
+#[derive(Debug, Clone)]
+struct Record<T> {
+    value: T,
+    account_id: AccountId,
+    created_on: NaiveDateTime,
+    /// Held back for review
+    pending: bool,
+}
+
+impl<T> Record<T> {
+    /// Ownership check of the update and delete methods, `None` passes
+    /// for every owner
+    fn owned_by(&self, account_id: &Option<AccountId>) -> bool {
+        account_id.as_ref().is_none_or(|a| &self.account_id == a)
+    }
+}
+
+/// Store which keeps everything in memory, like the one from the
+/// earlier chapters. Useful for running the server and the route
+/// tests without a database. Data is lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    questions: Arc<RwLock<HashMap<QuestionId, Record<Question>>>>,
+    answers: Arc<RwLock<HashMap<AnswerId, Record<Answer>>>>,
+    accounts: Arc<RwLock<HashMap<String, Account>>>,
+    question_votes: Arc<RwLock<HashMap<(QuestionId, AccountId), Vote>>>,
+    answer_votes: Arc<RwLock<HashMap<(AnswerId, AccountId), Vote>>>,
+    refresh_tokens: Arc<RwLock<HashMap<String, RefreshToken>>>,
+    sessions: Arc<RwLock<HashMap<String, SessionInfo>>>,
+    revoked_tokens: Arc<RwLock<HashMap<String, NaiveDateTime>>>,
+    account_tokens: Arc<RwLock<HashMap<String, AccountToken>>>,
+    login_attempts: Arc<RwLock<HashMap<String, LoginAttempts>>>,
+    /// By the hash of the key
+    api_keys: Arc<RwLock<HashMap<String, ApiKey>>>,
+    /// By issuer and subject
+    identities: Arc<RwLock<HashMap<(String, String), AccountId>>>,
+    reviews: Arc<RwLock<HashMap<ReviewId, Review>>>,
+    question_seq: Arc<AtomicI32>,
+    answer_seq: Arc<AtomicI32>,
+    account_seq: Arc<AtomicI32>,
+    api_key_seq: Arc<AtomicI32>,
+    review_seq: Arc<AtomicI32>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore::default()
+    }
+
+    /// Questions matching the filters of the query, in its sort order,
+    /// each with its cursor
+    async fn query_questions(
+        &self,
+        query: &QuestionQuery,
+    ) -> Vec<(Question, Cursor)> {
+        let questions = self.questions.read().await;
+        let mut res: Vec<(Question, Cursor)> = questions
+            .values()
+            .filter(|r| !r.pending)
+            .filter(|r| {
+                query.answered.is_none_or(|answered| {
+                    r.value.accepted_answer_id.is_some() == answered
+                })
+            })
+            .filter(|r| {
+                let tags = r.value.tags.as_deref().unwrap_or_default();
+                match query.tag_match {
+                    _ if query.tags.is_empty() => true,
+                    TagMatch::Any => {
+                        query.tags.iter().any(|t| tags.contains(t))
+                    }
+                    TagMatch::All => {
+                        query.tags.iter().all(|t| tags.contains(t))
+                    }
+                }
+            })
+            .map(|r| {
+                let cursor = Cursor {
+                    created_on: r.created_on,
+                    id: r.value.id.0,
+                };
+                (r.value.clone(), cursor)
+            })
+            .collect();
+        match query.sort {
+            QuestionSort::None => res
+                .sort_by_key(|(_, cursor)| (cursor.created_on, cursor.id)),
+            QuestionSort::Score => {
+                res.sort_by_key(|(q, _)| (-q.score, q.id.0))
+            }
+        }
+
+        res
+    }
+
+    /// Current time with the microsecond precision of a Postgres
+    /// `TIMESTAMP`, which is also what the cursors keep
+    fn now() -> NaiveDateTime {
+        Utc::now().naive_utc().trunc_subsecs(6)
+    }
+
+    /// Hands out ids the same way a `serial` column does, starting at 1
+    fn next_id(seq: &AtomicI32) -> i32 {
+        seq.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Replaces the review of a post, without a new review the old one
+    /// is only dropped. The caller holds the lock of the post.
+    async fn replace_review(
+        &self,
+        target: ReviewTarget,
+        review: Option<NewReview>,
+    ) {
+        let mut reviews = self.reviews.write().await;
+        reviews.retain(|_, r| r.target != target);
+        if let Some(review) = review {
+            let review = Review {
+                id: ReviewId(Self::next_id(&self.review_seq)),
+                target,
+                original: review.original,
+                censored: review.censored,
+                bad_words: review.bad_words,
+                unchecked: review.unchecked,
+                created_on: Self::now(),
+            };
+            reviews.insert(review.id.clone(), review);
+        }
+    }
+}
+
+/// Replaces the vote of an account on an item, or removes it for `None`,
+/// and returns the new score of the item
+async fn cast_vote<K: Eq + Hash + Clone>(
+    votes: &RwLock<HashMap<(K, AccountId), Vote>>,
+    id: K,
+    account_id: &AccountId,
+    vote: Option<Vote>,
+) -> i32 {
+    let mut votes = votes.write().await;
+    let key = (id, account_id.clone());
+    match vote {
+        Some(vote) => votes.insert(key.clone(), vote),
+        None => votes.remove(&key),
+    };
+
+    votes
+        .iter()
+        .filter(|((item, _), _)| item == &key.0)
+        .map(|(_, vote)| i32::from(vote.value()))
+        .sum()
+}
+
+/// Applies LIMIT/OFFSET semantics to already ordered items
+fn paginate<T>(items: Vec<T>, limit: Option<i32>, offset: i32) -> Vec<T> {
+    let items = items.into_iter().skip(offset.max(0) as usize);
+    match limit {
+        Some(limit) => items.take(limit.max(0) as usize).collect(),
+        None => items.collect(),
+    }
+}
+
+#[async_trait]
+impl QuestionStore for InMemoryStore {
+    async fn get_questions(
+        &self,
+        query: &QuestionQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error> {
+        let res = self
+            .query_questions(query)
+            .await
+            .into_iter()
+            .map(|(question, _)| question)
+            .collect();
+
+        Ok(paginate(res, limit, offset))
+    }
+
+    async fn get_questions_after(
+        &self,
+        query: &QuestionQuery,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Vec<(Question, Cursor)>, Error> {
+        let query = QuestionQuery {
+            sort: QuestionSort::None,
+            ..query.clone()
+        };
+        let res = self
+            .query_questions(&query)
+            .await
+            .into_iter()
+            .filter(|(_, cursor)| {
+                after.is_none_or(|after| {
+                    (cursor.created_on, cursor.id)
+                        > (after.created_on, after.id)
+                })
+            })
+            .collect();
+
+        Ok(paginate(res, Some(limit), 0))
+    }
+
+    async fn count_questions(
+        &self,
+        query: &QuestionQuery,
+    ) -> Result<i64, Error> {
+        Ok(self.query_questions(query).await.len() as i64)
+    }
+
+    async fn get_question(
+        &self,
+        id: i32,
+    ) -> Result<Option<Question>, Error> {
+        Ok(self
+            .questions
+            .read()
+            .await
+            .get(&QuestionId(id))
+            .filter(|r| !r.pending)
+            .map(|r| r.value.clone()))
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(self
+            .questions
+            .read()
+            .await
+            .get(&QuestionId(question_id))
+            .is_some_and(|r| &r.account_id == account_id))
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+        review: Option<NewReview>,
+    ) -> Result<Question, Error> {
+        let question = Question {
+            id: QuestionId(Self::next_id(&self.question_seq)),
+            title: new_question.title,
+            content: new_question.content,
+            tags: new_question.tags,
+            score: 0,
+            accepted_answer_id: None,
+        };
+
+        let mut questions = self.questions.write().await;
+        questions.insert(
+            question.id.clone(),
+            Record {
+                value: question.clone(),
+                account_id,
+                created_on: Self::now(),
+                pending: review.is_some(),
+            },
+        );
+        self.replace_review(
+            ReviewTarget::Question(question.id.clone()),
+            review,
+        )
+        .await;
+
+        Ok(question)
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: Option<AccountId>,
+        review: Option<NewReview>,
+    ) -> Result<Option<Question>, Error> {
+        match self.questions.write().await.get_mut(&QuestionId(id)) {
+            Some(record) if record.owned_by(&account_id) => {
+                record.value.title = question.title;
+                record.value.content = question.content;
+                record.value.tags = question.tags;
+                record.pending = review.is_some();
+                self.replace_review(
+                    ReviewTarget::Question(QuestionId(id)),
+                    review,
+                )
+                .await;
+                Ok(Some(record.value.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn delete_question(
+        &self,
+        id: i32,
+        account_id: Option<AccountId>,
+    ) -> Result<bool, Error> {
+        let mut questions = self.questions.write().await;
+        if !questions
+            .get(&QuestionId(id))
+            .is_some_and(|r| r.owned_by(&account_id))
+        {
+            return Ok(false);
+        }
+
+        questions.remove(&QuestionId(id));
+        let mut answers = self.answers.write().await;
+        let answer_ids: Vec<AnswerId> = answers
+            .values()
+            .filter(|r| r.value.question_id == QuestionId(id))
+            .map(|r| r.value.id.clone())
+            .collect();
+        for answer_id in &answer_ids {
+            answers.remove(answer_id);
+        }
+        // Mirrors `ON DELETE CASCADE` on the votes and reviews of the
+        // question and its answers
+        self.question_votes
+            .write()
+            .await
+            .retain(|(question_id, _), _| question_id != &QuestionId(id));
+        self.answer_votes
+            .write()
+            .await
+            .retain(|(answer_id, _), _| !answer_ids.contains(answer_id));
+        self.reviews.write().await.retain(|_, review| {
+            match &review.target {
+                ReviewTarget::Question(question_id) => {
+                    question_id != &QuestionId(id)
+                }
+                ReviewTarget::Answer(answer_id) => {
+                    !answer_ids.contains(answer_id)
+                }
+            }
+        });
+
+        Ok(true)
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for record in self.questions.read().await.values() {
+            if record.pending {
+                continue;
+            }
+            for tag in record.value.tags.iter().flatten() {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then(a.tag.cmp(&b.tag)));
+
+        Ok(tags)
+    }
+
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error> {
+        match self
+            .questions
+            .write()
+            .await
+            .get_mut(&QuestionId(question_id))
+        {
+            Some(record) => {
+                record.value.accepted_answer_id = answer_id.map(AnswerId);
+                Ok(record.value.clone())
+            }
+            None => {
+                Err(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AnswerStore for InMemoryStore {
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error> {
+        let answers = self.answers.read().await;
+        let mut res: Vec<Answer> = answers
+            .values()
+            .filter(|r| r.value.question_id == QuestionId(question_id))
+            .filter(|r| !r.pending)
+            .map(|r| r.value.clone())
+            .collect();
+        // Ids are handed out in order, so this is the creation order
+        res.sort_by_key(|a| a.id.0);
+
+        Ok(paginate(res, limit, offset))
+    }
+
+    async fn get_answer(&self, id: i32) -> Result<Option<Answer>, Error> {
+        Ok(self
+            .answers
+            .read()
+            .await
+            .get(&AnswerId(id))
+            .filter(|r| !r.pending)
+            .map(|r| r.value.clone()))
+    }
+
+    async fn is_answer_owner(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(self
+            .answers
+            .read()
+            .await
+            .get(&AnswerId(answer_id))
+            .is_some_and(|r| &r.account_id == account_id))
+    }
+
+    async fn add_answer(
+        &self,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+        review: Option<NewReview>,
+    ) -> Result<Answer, Error> {
+        // Mirrors the foreign key on `answers.corresponding_question`
+        let questions = self.questions.read().await;
+        if !questions.contains_key(&new_answer.question_id) {
+            return Err(Error::DatabaseQueryError(
+                sqlx::Error::RowNotFound,
+            ));
+        }
+
+        let answer = Answer {
+            id: AnswerId(Self::next_id(&self.answer_seq)),
+            content: new_answer.content,
+            question_id: new_answer.question_id,
+            score: 0,
+        };
+
+        let mut answers = self.answers.write().await;
+        answers.insert(
+            answer.id.clone(),
+            Record {
+                value: answer.clone(),
+                account_id,
+                created_on: Self::now(),
+                pending: review.is_some(),
+            },
+        );
+        self.replace_review(
+            ReviewTarget::Answer(answer.id.clone()),
+            review,
+        )
+        .await;
+
+        Ok(answer)
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        id: i32,
+        account_id: Option<AccountId>,
+        review: Option<NewReview>,
+    ) -> Result<Option<Answer>, Error> {
+        match self.answers.write().await.get_mut(&AnswerId(id)) {
+            Some(record) if record.owned_by(&account_id) => {
+                record.value.content = answer.content;
+                record.pending = review.is_some();
+                self.replace_review(
+                    ReviewTarget::Answer(AnswerId(id)),
+                    review,
+                )
+                .await;
+                Ok(Some(record.value.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn delete_answer(
+        &self,
+        id: i32,
+        account_id: Option<AccountId>,
+    ) -> Result<Option<Answer>, Error> {
+        // Questions are always locked before answers
+        let mut questions = self.questions.write().await;
+        let mut answers = self.answers.write().await;
+        match answers.get(&AnswerId(id)) {
+            Some(record) if record.owned_by(&account_id) => {
+                // Mirrors `ON DELETE SET NULL` on the accepted answer
+                for question in questions.values_mut() {
+                    if question.value.accepted_answer_id
+                        == Some(AnswerId(id))
+                    {
+                        question.value.accepted_answer_id = None;
+                    }
+                }
+                self.answer_votes.write().await.retain(
+                    |(answer_id, _), _| answer_id != &AnswerId(id),
+                );
+                self.reviews.write().await.retain(|_, review| {
+                    review.target != ReviewTarget::Answer(AnswerId(id))
+                });
+                Ok(answers.remove(&AnswerId(id)).map(|r| r.value))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl VoteStore for InMemoryStore {
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+        vote: Vote,
+    ) -> Result<i32, Error> {
+        let mut questions = self.questions.write().await;
+        let record = questions
+            .get_mut(&QuestionId(question_id))
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))?;
+        record.value.score = cast_vote(
+            &self.question_votes,
+            QuestionId(question_id),
+            account_id,
+            Some(vote),
+        )
+        .await;
+
+        Ok(record.value.score)
+    }
+
+    async fn retract_question_vote(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<i32, Error> {
+        let mut questions = self.questions.write().await;
+        let record = questions
+            .get_mut(&QuestionId(question_id))
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))?;
+        record.value.score = cast_vote(
+            &self.question_votes,
+            QuestionId(question_id),
+            account_id,
+            None,
+        )
+        .await;
+
+        Ok(record.value.score)
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+        vote: Vote,
+    ) -> Result<i32, Error> {
+        let mut answers = self.answers.write().await;
+        let record = answers
+            .get_mut(&AnswerId(answer_id))
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))?;
+        record.value.score = cast_vote(
+            &self.answer_votes,
+            AnswerId(answer_id),
+            account_id,
+            Some(vote),
+        )
+        .await;
+
+        Ok(record.value.score)
+    }
+
+    async fn retract_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<i32, Error> {
+        let mut answers = self.answers.write().await;
+        let record = answers
+            .get_mut(&AnswerId(answer_id))
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))?;
+        record.value.score = cast_vote(
+            &self.answer_votes,
+            AnswerId(answer_id),
+            account_id,
+            None,
+        )
+        .await;
+
+        Ok(record.value.score)
+    }
+}
+
+#[async_trait]
+impl SearchStore for InMemoryStore {
+    /// Same matching and ranking as the SQLite store: the whole search
+    /// text case-insensitively, matches in the question above matches in
+    /// its answers only
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let q = query.q.to_lowercase();
+        let questions = self.questions.read().await;
+        let answers = self.answers.read().await;
+
+        let mut res: Vec<SearchResult> = questions
+            .values()
+            .filter(|r| !r.pending)
+            .filter(|r| {
+                query.tag.as_ref().is_none_or(|tag| {
+                    r.value.tags.as_ref().is_some_and(|t| t.contains(tag))
+                })
+            })
+            .filter(|r| {
+                query.author.as_ref().is_none_or(|a| &r.account_id == a)
+            })
+            .filter_map(|r| {
+                let question = &r.value;
+                let text =
+                    format!("{} {}", question.title, question.content);
+                let rank = if text.to_lowercase().contains(&q) {
+                    1.0
+                } else if answers.values().any(|a| {
+                    !a.pending
+                        && a.value.question_id == question.id
+                        && a.value.content.to_lowercase().contains(&q)
+                }) {
+                    0.5
+                } else {
+                    return None;
+                };
+
+                Some(SearchResult {
+                    question: question.clone(),
+                    rank,
+                })
+            })
+            .collect();
+        res.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then(a.question.id.0.cmp(&b.question.id.0))
+        });
+
+        Ok(paginate(res, limit, offset))
+    }
+}
+
+#[async_trait]
+impl ReviewStore for InMemoryStore {
+    async fn get_reviews(&self) -> Result<Vec<Review>, Error> {
+        let mut reviews: Vec<Review> =
+            self.reviews.read().await.values().cloned().collect();
+        reviews.sort_by_key(|r| r.id.0);
+
+        Ok(reviews)
+    }
+
+    async fn get_review(
+        &self,
+        id: &ReviewId,
+    ) -> Result<Option<Review>, Error> {
+        Ok(self.reviews.read().await.get(id).cloned())
+    }
+
+    async fn publish_review(
+        &self,
+        id: &ReviewId,
+        text: ReviewText,
+    ) -> Result<bool, Error> {
+        let mut questions = self.questions.write().await;
+        let mut answers = self.answers.write().await;
+        let Some(review) = self.reviews.write().await.remove(id) else {
+            return Ok(false);
+        };
+
+        match review.target {
+            ReviewTarget::Question(id) => {
+                if let Some(record) = questions.get_mut(&id) {
+                    if let Some(title) = text.title {
+                        record.value.title = title;
+                    }
+                    record.value.content = text.content;
+                    record.pending = false;
+                }
+            }
+            ReviewTarget::Answer(id) => {
+                if let Some(record) = answers.get_mut(&id) {
+                    record.value.content = text.content;
+                    record.pending = false;
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl AccountStore for InMemoryStore {
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write().await;
+        if accounts.contains_key(&account.email) {
+            return Err(Error::AccountAlreadyExists);
+        }
+
+        let account = Account {
+            id: Some(AccountId(Self::next_id(&self.account_seq))),
+            email: account.email,
+            password: account.password,
+            role: account.role,
+            verified: account.verified,
+        };
+        accounts.insert(account.email.clone(), account);
+
+        Ok(true)
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        self.accounts
+            .read()
+            .await
+            .get(&email)
+            .cloned()
+            .ok_or(Error::DatabaseQueryError(sqlx::Error::RowNotFound))
+    }
+
+    async fn get_account_by_id(
+        &self,
+        id: &AccountId,
+    ) -> Result<Option<Account>, Error> {
+        Ok(self
+            .accounts
+            .read()
+            .await
+            .values()
+            .find(|a| a.id.as_ref() == Some(id))
+            .cloned())
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, Error> {
+        let mut accounts: Vec<Account> =
+            self.accounts.read().await.values().cloned().collect();
+        accounts.sort_by_key(|a| a.id.as_ref().map(|id| id.0));
+
+        Ok(accounts)
+    }
+
+    async fn set_role(
+        &self,
+        id: &AccountId,
+        role: Role,
+    ) -> Result<Option<Account>, Error> {
+        Ok(self
+            .accounts
+            .write()
+            .await
+            .values_mut()
+            .find(|a| a.id.as_ref() == Some(id))
+            .map(|account| {
+                account.role = role;
+                account.clone()
+            }))
+    }
+
+    async fn delete_account(&self, id: &AccountId) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write().await;
+        let before = accounts.len();
+        accounts.retain(|_, a| a.id.as_ref() != Some(id));
+        // Mirrors `ON DELETE CASCADE` on the account tokens, API keys,
+        // identities and sessions
+        self.account_tokens
+            .write()
+            .await
+            .retain(|_, t| &t.account_id != id);
+        self.api_keys
+            .write()
+            .await
+            .retain(|_, k| &k.account_id != id);
+        self.identities
+            .write()
+            .await
+            .retain(|_, account_id| account_id != id);
+        self.sessions
+            .write()
+            .await
+            .retain(|_, s| &s.account_id != id);
+
+        Ok(accounts.len() < before)
+    }
+
+    async fn set_password(
+        &self,
+        id: &AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        Ok(self
+            .accounts
+            .write()
+            .await
+            .values_mut()
+            .find(|a| a.id.as_ref() == Some(id))
+            .map(|account| account.password = password)
+            .is_some())
+    }
+
+    async fn set_verified(&self, id: &AccountId) -> Result<bool, Error> {
+        Ok(self
+            .accounts
+            .write()
+            .await
+            .values_mut()
+            .find(|a| a.id.as_ref() == Some(id))
+            .map(|account| account.verified = true)
+            .is_some())
+    }
+}
+
+#[async_trait]
+impl TokenStore for InMemoryStore {
+    async fn add_refresh_token(
+        &self,
+        token: RefreshToken,
+    ) -> Result<(), Error> {
+        let now = Self::now();
+        let mut tokens = self.refresh_tokens.write().await;
+        tokens.retain(|_, t| t.expires_on >= now);
+        tokens.insert(token.token_hash.clone(), token);
+
+        Ok(())
+    }
+
+    async fn use_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, Error> {
+        let mut tokens = self.refresh_tokens.write().await;
+        Ok(tokens.get_mut(token_hash).map(|token| {
+            let before = token.clone();
+            token.used = true;
+            before
+        }))
+    }
+
+    async fn revoke_session(&self, session_id: &str) -> Result<(), Error> {
+        self.refresh_tokens
+            .write()
+            .await
+            .retain(|_, t| t.session_id != session_id);
+        self.sessions.write().await.remove(session_id);
+
+        Ok(())
+    }
+
+    async fn revoke_token(
+        &self,
+        token_id: &str,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let now = Self::now();
+        let mut revoked = self.revoked_tokens.write().await;
+        revoked.retain(|_, expires_on| *expires_on >= now);
+        revoked.insert(token_id.to_string(), expires_on);
+
+        Ok(())
+    }
+
+    async fn is_token_revoked(
+        &self,
+        token_id: &str,
+    ) -> Result<bool, Error> {
+        Ok(self.revoked_tokens.read().await.contains_key(token_id))
+    }
+
+    async fn revoke_account_sessions(
+        &self,
+        account_id: &AccountId,
+        except: Option<&str>,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let mut revoked = Vec::new();
+        self.refresh_tokens.write().await.retain(|_, t| {
+            let keep = &t.account_id != account_id
+                || Some(t.session_id.as_str()) == except;
+            if !keep {
+                revoked.push(t.session_id.clone());
+            }
+            keep
+        });
+        self.sessions.write().await.retain(|_, s| {
+            let keep = &s.account_id != account_id
+                || Some(s.session_id.as_str()) == except;
+            if !keep {
+                revoked.push(s.session_id.clone());
+            }
+            keep
+        });
+        for session_id in revoked {
+            self.revoke_token(&session_id, expires_on).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn add_account_token(
+        &self,
+        token: AccountToken,
+    ) -> Result<(), Error> {
+        self.account_tokens
+            .write()
+            .await
+            .insert(token.token_hash.clone(), token);
+
+        Ok(())
+    }
+
+    async fn take_account_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> Result<Option<AccountToken>, Error> {
+        let mut tokens = self.account_tokens.write().await;
+        match tokens.get(token_hash) {
+            Some(token) if token.purpose == purpose => {
+                Ok(tokens.remove(token_hash))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for InMemoryStore {
+    async fn add_api_key(
+        &self,
+        account_id: &AccountId,
+        new_key: NewApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey, Error> {
+        let api_key = ApiKey {
+            id: ApiKeyId(Self::next_id(&self.api_key_seq)),
+            account_id: account_id.clone(),
+            name: new_key.name,
+            prefix: prefix.to_string(),
+            scopes: new_key.scopes,
+            created_on: Utc::now().naive_utc().trunc_subsecs(6),
+        };
+        self.api_keys
+            .write()
+            .await
+            .insert(key_hash.to_string(), api_key.clone());
+
+        Ok(api_key)
+    }
+
+    async fn get_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<ApiKey>, Error> {
+        let mut api_keys: Vec<ApiKey> = self
+            .api_keys
+            .read()
+            .await
+            .values()
+            .filter(|k| &k.account_id == account_id)
+            .cloned()
+            .collect();
+        api_keys.sort_by_key(|k| k.id.0);
+
+        Ok(api_keys)
+    }
+
+    async fn get_api_key_by_hash(
+        &self,
+        key_hash: &str,
+    ) -> Result<Option<ApiKey>, Error> {
+        Ok(self.api_keys.read().await.get(key_hash).cloned())
+    }
+
+    async fn delete_api_key(
+        &self,
+        id: &ApiKeyId,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let before = api_keys.len();
+        api_keys
+            .retain(|_, k| !(&k.id == id && &k.account_id == account_id));
+
+        Ok(api_keys.len() < before)
+    }
+}
+
+#[async_trait]
+impl SessionStore for InMemoryStore {
+    async fn add_session(
+        &self,
+        session: SessionInfo,
+    ) -> Result<(), Error> {
+        self.sessions
+            .write()
+            .await
+            .insert(session.session_id.clone(), session);
+
+        Ok(())
+    }
+
+    async fn touch_session(
+        &self,
+        session_id: &str,
+        now: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<(), Error> {
+        if let Some(session) =
+            self.sessions.write().await.get_mut(session_id)
+        {
+            if session.last_used < stale_before {
+                session.last_used = now;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_sessions(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<SessionInfo>, Error> {
+        let now = Utc::now().naive_utc();
+        let refresh_tokens = self.refresh_tokens.read().await;
+        let mut sessions: Vec<SessionInfo> = self
+            .sessions
+            .read()
+            .await
+            .values()
+            .filter(|s| &s.account_id == account_id)
+            .filter(|s| {
+                refresh_tokens.values().any(|t| {
+                    t.session_id == s.session_id
+                        && !t.used
+                        && t.expires_on >= now
+                })
+            })
+            .cloned()
+            .collect();
+        sessions.sort_by(|a, b| {
+            (b.last_used, b.created_on).cmp(&(a.last_used, a.created_on))
+        });
+
+        Ok(sessions)
+    }
+}
+
+#[async_trait]
+impl IdentityStore for InMemoryStore {
+    async fn get_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<AccountId>, Error> {
+        let key = (issuer.to_string(), subject.to_string());
+        Ok(self.identities.read().await.get(&key).cloned())
+    }
+
+    async fn add_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        account_id: &AccountId,
+    ) -> Result<(), Error> {
+        self.identities.write().await.insert(
+            (issuer.to_string(), subject.to_string()),
+            account_id.clone(),
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for InMemoryStore {
+    async fn get_login_attempts(
+        &self,
+        key: &str,
+    ) -> Result<Option<LoginAttempts>, Error> {
+        Ok(self.login_attempts.read().await.get(key).cloned())
+    }
+
+    async fn add_failed_login(
+        &self,
+        key: &str,
+        now: NaiveDateTime,
+        reset_before: NaiveDateTime,
+    ) -> Result<LoginAttempts, Error> {
+        let mut attempts = self.login_attempts.write().await;
+        let entry =
+            attempts.entry(key.to_string()).or_insert(LoginAttempts {
+                key: key.to_string(),
+                failures: 0,
+                last_failure: now,
+            });
+        if entry.last_failure < reset_before {
+            entry.failures = 0;
+        }
+        entry.failures += 1;
+        entry.last_failure = now;
+
+        Ok(entry.clone())
+    }
+
+    async fn clear_login_attempts(&self, key: &str) -> Result<(), Error> {
+        self.login_attempts.write().await.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod memory_tests {
+    use super::*;
+    use crate::types::review::BadWords;
+    use chrono::Duration;
+
+    fn new_question() -> NewQuestion {
+        NewQuestion {
+            title: "First question".to_string(),
+            content: "How does this work?".to_string(),
+            tags: Some(vec!["general".to_string()]),
+        }
+    }
+
+    fn new_answer(question_id: &QuestionId) -> NewAnswer {
+        NewAnswer {
+            content: "Like this".to_string(),
+            question_id: question_id.clone(),
+        }
+    }
+
+    fn new_review(content: &str) -> NewReview {
+        NewReview {
+            original: ReviewText {
+                title: Some("First question".to_string()),
+                content: content.to_string(),
+            },
+            censored: ReviewText {
+                title: None,
+                content: "*".repeat(content.len()),
+            },
+            bad_words: BadWords::default(),
+            unchecked: true,
+        }
+    }
+
+    fn new_account(email: &str) -> Account {
+        Account {
+            id: None,
+            email: email.to_string(),
+            password: "password".to_string(),
+            role: Role::User,
+            verified: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn add_and_update_question() {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        assert_eq!(question.id, QuestionId(1));
+        assert!(store
+            .is_question_owner(question.id.0, &AccountId(1))
+            .await
+            .unwrap());
+
+        let update = Question {
+            title: "Updated question".to_string(),
+            tags: None,
+            ..question.clone()
+        };
+        assert!(store
+            .update_question(
+                update.clone(),
+                question.id.0,
+                Some(AccountId(2)),
+                None
+            )
+            .await
+            .unwrap()
+            .is_none());
+        store
+            .update_question(update, question.id.0, None, None)
+            .await
+            .unwrap()
+            .unwrap();
+        let question =
+            store.get_question(question.id.0).await.unwrap().unwrap();
+        assert_eq!(question.title, "Updated question");
+        assert_eq!(question.tags, None);
+
+        assert!(!store
+            .delete_question(question.id.0, Some(AccountId(2)))
+            .await
+            .unwrap());
+        assert!(store
+            .delete_question(question.id.0, Some(AccountId(1)))
+            .await
+            .unwrap());
+        assert!(!store
+            .delete_question(question.id.0, None)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn one_vote_per_account() {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        let id = question.id.0;
+
+        for (account_id, vote, score) in
+            [(2, Vote::Up, 1), (2, Vote::Up, 1), (3, Vote::Down, 0)]
+        {
+            assert_eq!(
+                store
+                    .vote_question(id, &AccountId(account_id), vote)
+                    .await
+                    .unwrap(),
+                score
+            );
+        }
+        assert_eq!(
+            store
+                .retract_question_vote(id, &AccountId(3))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store.get_question(id).await.unwrap().unwrap().score,
+            1
+        );
+        assert!(store
+            .vote_question(42, &AccountId(2), Vote::Up)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn deleting_accepted_answer() {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        let answer = store
+            .add_answer(new_answer(&question.id), AccountId(2), None)
+            .await
+            .unwrap();
+        store
+            .set_accepted_answer(question.id.0, Some(answer.id.0))
+            .await
+            .unwrap();
+        store
+            .vote_answer(answer.id.0, &AccountId(1), Vote::Up)
+            .await
+            .unwrap();
+
+        assert!(store
+            .delete_answer(answer.id.0, Some(AccountId(1)))
+            .await
+            .unwrap()
+            .is_none());
+        store
+            .delete_answer(answer.id.0, Some(AccountId(2)))
+            .await
+            .unwrap()
+            .unwrap();
+        let question =
+            store.get_question(question.id.0).await.unwrap().unwrap();
+        assert_eq!(question.accepted_answer_id, None);
+        assert!(store.answer_votes.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deleting_question_with_answers() {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        let other = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        let answer = store
+            .add_answer(
+                new_answer(&question.id),
+                AccountId(2),
+                Some(new_review("crap")),
+            )
+            .await
+            .unwrap();
+        let kept = store
+            .add_answer(new_answer(&other.id), AccountId(2), None)
+            .await
+            .unwrap();
+        store
+            .vote_question(question.id.0, &AccountId(2), Vote::Up)
+            .await
+            .unwrap();
+        store
+            .vote_answer(answer.id.0, &AccountId(1), Vote::Up)
+            .await
+            .unwrap();
+        store
+            .vote_answer(kept.id.0, &AccountId(1), Vote::Up)
+            .await
+            .unwrap();
+
+        // Mirrors the cascades of the database stores
+        assert!(store.delete_question(question.id.0, None).await.unwrap());
+        assert!(store.get_answer(answer.id.0).await.unwrap().is_none());
+        assert!(store
+            .delete_answer(answer.id.0, None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(store.get_reviews().await.unwrap().is_empty());
+        assert!(store.question_votes.read().await.is_empty());
+        let answer_votes = store.answer_votes.read().await;
+        assert_eq!(answer_votes.len(), 1);
+        assert!(
+            answer_votes.contains_key(&(kept.id.clone(), AccountId(1)))
+        );
+        assert!(store.get_answer(kept.id.0).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn reviews() {
+        let store = InMemoryStore::new();
+
+        // Pending from the first write on
+        let question = store
+            .add_question(
+                new_question(),
+                AccountId(1),
+                Some(new_review("shit")),
+            )
+            .await
+            .unwrap();
+        let id = question.id.0;
+        assert!(store.get_question(id).await.unwrap().is_none());
+        let query = QuestionQuery::default();
+        assert_eq!(store.count_questions(&query).await.unwrap(), 0);
+        assert!(store.get_tags().await.unwrap().is_empty());
+
+        // Another flagged edit replaces the review
+        store
+            .update_question(
+                question.clone(),
+                id,
+                None,
+                Some(new_review("crap")),
+            )
+            .await
+            .unwrap();
+        let reviews = store.get_reviews().await.unwrap();
+        assert_eq!(reviews.len(), 1);
+        let review = reviews[0].clone();
+        assert_eq!(review.target, ReviewTarget::Question(question.id));
+        assert_eq!(review.original.content, "crap");
+
+        assert!(store
+            .publish_review(
+                &review.id,
+                ReviewText {
+                    title: None,
+                    content: "Edited".to_string(),
+                },
+            )
+            .await
+            .unwrap());
+        assert!(!store
+            .publish_review(
+                &review.id,
+                ReviewText {
+                    title: None,
+                    content: "Again".to_string(),
+                },
+            )
+            .await
+            .unwrap());
+        let question = store.get_question(id).await.unwrap().unwrap();
+        assert_eq!(question.title, "First question");
+        assert_eq!(question.content, "Edited");
+
+        // A clean edit publishes the post and drops its review
+        store
+            .update_question(
+                question.clone(),
+                id,
+                None,
+                Some(new_review("crap")),
+            )
+            .await
+            .unwrap();
+        store
+            .update_question(question, id, None, None)
+            .await
+            .unwrap();
+        assert!(store.get_reviews().await.unwrap().is_empty());
+        assert!(store.get_question(id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn search() {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        store
+            .add_answer(
+                NewAnswer {
+                    content: "Use a Mutex".to_string(),
+                    question_id: question.id.clone(),
+                },
+                AccountId(2),
+                None,
+            )
+            .await
+            .unwrap();
+        let titled = store
+            .add_question(
+                NewQuestion {
+                    title: "Mutex or RwLock?".to_string(),
+                    ..new_question()
+                },
+                AccountId(1),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut query = SearchQuery {
+            q: "MUTEX".to_string(),
+            tag: Some("general".to_string()),
+            author: Some(AccountId(1)),
+        };
+        let results = store.search(&query, None, 0).await.unwrap();
+        let ranks: Vec<(QuestionId, f32)> = results
+            .into_iter()
+            .map(|r| (r.question.id, r.rank))
+            .collect();
+        assert_eq!(ranks, vec![(titled.id, 1.0), (question.id, 0.5)]);
+        assert_eq!(
+            store.search(&query, Some(1), 1).await.unwrap().len(),
+            1
+        );
+
+        query.tag = Some("unknown".to_string());
+        assert!(store.search(&query, None, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tags() {
+        let store = InMemoryStore::new();
+        for tags in [vec!["rust", "warp"], vec!["rust"], vec!["python"]] {
+            let question = NewQuestion {
+                tags: Some(tags.into_iter().map(String::from).collect()),
+                ..new_question()
+            };
+            store
+                .add_question(question, AccountId(1), None)
+                .await
+                .unwrap();
+        }
+
+        let tags = store.get_tags().await.unwrap();
+        assert_eq!(tags[0].tag, "rust");
+        assert_eq!(tags[0].count, 2);
+        assert_eq!(tags.len(), 3);
+
+        let mut query = QuestionQuery {
+            tags: vec!["warp".to_string(), "python".to_string()],
+            ..QuestionQuery::default()
+        };
+        let ids = |questions: Vec<Question>| {
+            questions.into_iter().map(|q| q.id.0).collect::<Vec<_>>()
+        };
+        let questions =
+            store.get_questions(&query, None, 0).await.unwrap();
+        assert_eq!(ids(questions), vec![1, 3]);
+        assert_eq!(store.count_questions(&query).await.unwrap(), 2);
+
+        query.tags = vec!["rust".to_string(), "warp".to_string()];
+        query.tag_match = TagMatch::All;
+        let questions =
+            store.get_questions(&query, None, 0).await.unwrap();
+        assert_eq!(ids(questions), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn questions_after_cursor() {
+        let store = InMemoryStore::new();
+        for _ in 0..3 {
+            store
+                .add_question(new_question(), AccountId(1), None)
+                .await
+                .unwrap();
+        }
+        let query = QuestionQuery::default();
+
+        let first =
+            store.get_questions_after(&query, None, 2).await.unwrap();
+        assert_eq!(first.len(), 2);
+        let (question, cursor) = &first[1];
+        assert_eq!(question.id.0, 2);
+
+        let cursor = Cursor::decode(&cursor.encode()).unwrap();
+        let second = store
+            .get_questions_after(&query, Some(&cursor), 2)
+            .await
+            .unwrap();
+        let ids: Vec<i32> = second.iter().map(|(q, _)| q.id.0).collect();
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn accounts() {
+        let store = InMemoryStore::new();
+        store
+            .add_account(new_account("test@email.com"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.add_account(new_account("test@email.com")).await,
+            Err(Error::AccountAlreadyExists)
+        ));
+        let account = store
+            .get_account("test@email.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.id, Some(AccountId(1)));
+
+        let account = store
+            .set_role(&AccountId(1), Role::Moderator)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.role, Role::Moderator);
+        assert!(store
+            .set_role(&AccountId(2), Role::Admin)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Deleting the account drops what belongs to it
+        let issuer = "https://idp.example.com";
+        store
+            .add_identity(issuer, "sub", &AccountId(1))
+            .await
+            .unwrap();
+        assert!(store.delete_account(&AccountId(1)).await.unwrap());
+        assert!(!store.delete_account(&AccountId(1)).await.unwrap());
+        assert!(store.get_accounts().await.unwrap().is_empty());
+        assert_eq!(store.get_identity(issuer, "sub").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn refresh_tokens() {
+        let store = InMemoryStore::new();
+        let token = RefreshToken {
+            token_hash: "hash".to_string(),
+            session_id: "session".to_string(),
+            account_id: AccountId(1),
+            expires_on: InMemoryStore::now() + Duration::days(1),
+            used: false,
+        };
+        store.add_refresh_token(token.clone()).await.unwrap();
+
+        let first = store.use_refresh_token("hash").await.unwrap();
+        assert_eq!(first, Some(token));
+        let second = store.use_refresh_token("hash").await.unwrap();
+        assert!(second.unwrap().used);
+        assert_eq!(store.use_refresh_token("other").await.unwrap(), None);
+
+        store.revoke_session("session").await.unwrap();
+        assert_eq!(store.use_refresh_token("hash").await.unwrap(), None);
+
+        assert!(!store.is_token_revoked("token").await.unwrap());
+        let expires_on = InMemoryStore::now() + Duration::minutes(15);
+        store.revoke_token("token", expires_on).await.unwrap();
+        assert!(store.is_token_revoked("token").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn login_attempts() {
+        let store = InMemoryStore::new();
+        let now = InMemoryStore::now();
+        let key = "email:test@email.com";
+        assert!(store.get_login_attempts(key).await.unwrap().is_none());
+
+        for failures in 1..=2 {
+            let attempts = store
+                .add_failed_login(key, now, now - Duration::hours(1))
+                .await
+                .unwrap();
+            assert_eq!(attempts.failures, failures);
+        }
+
+        // Failures from before the window are forgotten
+        let later = now + Duration::hours(2);
+        let attempts = store
+            .add_failed_login(key, later, later - Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(attempts.failures, 1);
+        assert_eq!(attempts.last_failure, later);
+
+        store.clear_login_attempts(key).await.unwrap();
+        assert!(store.get_login_attempts(key).await.unwrap().is_none());
+    }
+}
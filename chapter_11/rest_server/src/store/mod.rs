@@ -0,0 +1,547 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use error_handlers::Error;
+
+use crate::types::{
+    account::{Account, AccountId, Role, SessionInfo},
+    answer::{Answer, NewAnswer},
+    api_key::{ApiKey, ApiKeyId, NewApiKey},
+    login_attempt::LoginAttempts,
+    pagination::Cursor,
+    question::{NewQuestion, Question, QuestionQuery, QuestionSort},
+    review::{NewReview, Review, ReviewId, ReviewTarget, ReviewText},
+    search::{SearchQuery, SearchResult},
+    tag::TagCount,
+    token::{AccountToken, RefreshToken, TokenPurpose},
+    vote::Vote,
+};
+
+pub(crate) mod memory;
+pub(crate) mod postgres;
+pub(crate) mod sqlite;
+
+pub use memory::InMemoryStore;
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+
+/// Persistence of questions and their ownership. The update and delete
+/// methods only touch questions of `account_id`, `None` lifts the
+/// restriction for moderators.
+#[async_trait]
+pub trait QuestionStore {
+    async fn get_questions(
+        &self,
+        query: &QuestionQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error>;
+
+    /// Keyset pagination of `get_questions`. Ignores the sort order of
+    /// the query, the questions are ordered by `(created_on, id)` and
+    /// start after the cursor. Every question comes with its cursor.
+    async fn get_questions_after(
+        &self,
+        query: &QuestionQuery,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Vec<(Question, Cursor)>, Error>;
+
+    /// Number of questions `get_questions` returns without a limit
+    async fn count_questions(
+        &self,
+        query: &QuestionQuery,
+    ) -> Result<i64, Error>;
+
+    /// `None` if there is no question with the given id or it is
+    /// pending
+    async fn get_question(
+        &self,
+        id: i32,
+    ) -> Result<Option<Question>, Error>;
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+
+    /// With `review` the question is stored pending and held for review
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+        review: Option<NewReview>,
+    ) -> Result<Question, Error>;
+
+    /// With `review` the question is held for review, replacing an
+    /// earlier review. Without it the question is published and an
+    /// earlier review dropped. `None` if there is no such question of
+    /// `account_id`.
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: Option<AccountId>,
+        review: Option<NewReview>,
+    ) -> Result<Option<Question>, Error>;
+
+    /// Deletes the question together with its answers, `false` if there
+    /// is no such question of `account_id`
+    async fn delete_question(
+        &self,
+        id: i32,
+        account_id: Option<AccountId>,
+    ) -> Result<bool, Error>;
+
+    /// Every tag in use with the number of questions carrying it, most
+    /// used first
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error>;
+
+    /// Marks an answer as the accepted one of the question, `None`
+    /// removes the mark. The caller checks that the answer belongs to
+    /// the question.
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error>;
+}
+
+/// Persistence of answers to questions. Ownership works like in
+/// `QuestionStore`.
+#[async_trait]
+pub trait AnswerStore {
+    /// Answers to a question, oldest first
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error>;
+
+    /// `None` if there is no answer with the given id or it is pending
+    async fn get_answer(&self, id: i32) -> Result<Option<Answer>, Error>;
+
+    async fn is_answer_owner(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+
+    /// Holds the answer back with `review` like `add_question`
+    async fn add_answer(
+        &self,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+        review: Option<NewReview>,
+    ) -> Result<Answer, Error>;
+
+    /// Holds the answer back or publishes it like `update_question`
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        id: i32,
+        account_id: Option<AccountId>,
+        review: Option<NewReview>,
+    ) -> Result<Option<Answer>, Error>;
+
+    /// Returns the deleted answer, `None` if there is no such answer of
+    /// `account_id`
+    async fn delete_answer(
+        &self,
+        id: i32,
+        account_id: Option<AccountId>,
+    ) -> Result<Option<Answer>, Error>;
+}
+
+/// Votes on questions and answers. Every account has at most one vote
+/// per item, voting again replaces it. All methods return the new score
+/// of the item.
+#[async_trait]
+pub trait VoteStore {
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+        vote: Vote,
+    ) -> Result<i32, Error>;
+
+    async fn retract_question_vote(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<i32, Error>;
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+        vote: Vote,
+    ) -> Result<i32, Error>;
+
+    async fn retract_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<i32, Error>;
+}
+
+/// Full-text search over questions and their answers
+#[async_trait]
+pub trait SearchStore {
+    /// Questions matching the search, most relevant first
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchResult>, Error>;
+}
+
+/// Posts held back until a moderator publishes or deletes them. The
+/// writes of `QuestionStore` and `AnswerStore` hold posts back. Pending
+/// posts are left out of the lookups and listings of questions and
+/// answers, the tags and the search. Deleting a post drops its review.
+#[async_trait]
+pub trait ReviewStore {
+    /// Reviews of the pending posts, oldest first
+    async fn get_reviews(&self) -> Result<Vec<Review>, Error>;
+
+    /// `None` if there is no review with the given id
+    async fn get_review(
+        &self,
+        id: &ReviewId,
+    ) -> Result<Option<Review>, Error>;
+
+    /// Publishes the post of the review with the given text and drops
+    /// the review. `false` if there is no review with the given id.
+    async fn publish_review(
+        &self,
+        id: &ReviewId,
+        text: ReviewText,
+    ) -> Result<bool, Error>;
+}
+
+/// Which kind of item a vote is for. Tells the SQL stores which tables
+/// hold the item and its votes.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum VoteTarget {
+    Question,
+    Answer,
+}
+
+impl VoteTarget {
+    /// Items table, votes table and the column of the votes table which
+    /// references the item
+    pub(crate) fn tables(
+        self,
+    ) -> (&'static str, &'static str, &'static str) {
+        match self {
+            VoteTarget::Question => {
+                ("questions", "question_votes", "question_id")
+            }
+            VoteTarget::Answer => ("answers", "answer_votes", "answer_id"),
+        }
+    }
+}
+
+/// The `question_id` and `answer_id` columns of the review of a post,
+/// the same for both SQL stores
+pub(crate) fn review_columns(
+    target: &ReviewTarget,
+) -> (Option<i32>, Option<i32>) {
+    match target {
+        ReviewTarget::Question(id) => (Some(id.0), None),
+        ReviewTarget::Answer(id) => (None, Some(id.0)),
+    }
+}
+
+/// Condition for the `answered` filter of `/questions`, the same for
+/// both SQL stores
+pub(crate) fn answered_condition(
+    answered: Option<bool>,
+) -> Option<&'static str> {
+    match answered {
+        None => None,
+        Some(true) => Some("accepted_answer_id IS NOT NULL"),
+        Some(false) => Some("accepted_answer_id IS NULL"),
+    }
+}
+
+/// Joins the conditions into a `WHERE` clause, empty if there are none
+pub(crate) fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// `ORDER BY` clause for the sort order of `/questions`, the same for
+/// both SQL stores. Without a sort order the questions come in the
+/// order they were asked, which is also the order of the cursors.
+pub(crate) fn order_by(sort: QuestionSort) -> &'static str {
+    match sort {
+        QuestionSort::None => "ORDER BY created_on, id",
+        QuestionSort::Score => "ORDER BY score DESC, id",
+    }
+}
+
+/// Persistence of user accounts
+#[async_trait]
+pub trait AccountStore {
+    async fn add_account(&self, account: Account) -> Result<bool, Error>;
+
+    async fn get_account(&self, email: String) -> Result<Account, Error>;
+
+    /// `None` if there is no account with the given id
+    async fn get_account_by_id(
+        &self,
+        id: &AccountId,
+    ) -> Result<Option<Account>, Error>;
+
+    /// Every account, oldest first
+    async fn get_accounts(&self) -> Result<Vec<Account>, Error>;
+
+    /// Returns the updated account, `None` if there is no account with
+    /// the given id
+    async fn set_role(
+        &self,
+        id: &AccountId,
+        role: Role,
+    ) -> Result<Option<Account>, Error>;
+
+    /// Returns whether there was an account to delete
+    async fn delete_account(&self, id: &AccountId) -> Result<bool, Error>;
+
+    /// Replaces the password hash of an account. Returns whether there
+    /// was such an account.
+    async fn set_password(
+        &self,
+        id: &AccountId,
+        password: String,
+    ) -> Result<bool, Error>;
+
+    /// Marks the email address of an account as verified. Returns
+    /// whether there was such an account.
+    async fn set_verified(&self, id: &AccountId) -> Result<bool, Error>;
+}
+
+/// Refresh tokens and the revocation list of access tokens
+#[async_trait]
+pub trait TokenStore {
+    async fn add_refresh_token(
+        &self,
+        token: RefreshToken,
+    ) -> Result<(), Error>;
+
+    /// Marks the refresh token with the given hash as used and returns
+    /// it as it was before, `None` if there is no such token. A token
+    /// which comes back with `used` set was presented a second time.
+    async fn use_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, Error>;
+
+    /// Deletes a login and every refresh token of it
+    async fn revoke_session(&self, session_id: &str) -> Result<(), Error>;
+
+    /// Puts an access token on the revocation list. The entry can be
+    /// dropped once the token expired anyway.
+    async fn revoke_token(
+        &self,
+        token_id: &str,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error>;
+
+    async fn is_token_revoked(
+        &self,
+        token_id: &str,
+    ) -> Result<bool, Error>;
+
+    /// Deletes every login of an account with its refresh tokens,
+    /// except the login `except`. Their ids go on the revocation list
+    /// until `expires_on`, so their access tokens stop working as well.
+    async fn revoke_account_sessions(
+        &self,
+        account_id: &AccountId,
+        except: Option<&str>,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error>;
+
+    async fn add_account_token(
+        &self,
+        token: AccountToken,
+    ) -> Result<(), Error>;
+
+    /// Deletes the token with the given hash and purpose and returns it,
+    /// `None` if there is no such token. So every token works once.
+    async fn take_account_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> Result<Option<AccountToken>, Error>;
+}
+
+/// Failed logins per email address and IP address, for slowing down
+/// password guessing. Kept in the database it holds across every
+/// instance of the service.
+#[async_trait]
+pub trait LoginAttemptStore {
+    async fn get_login_attempts(
+        &self,
+        key: &str,
+    ) -> Result<Option<LoginAttempts>, Error>;
+
+    /// Counts a failed login at `now` and returns the new count.
+    /// Failures from before `reset_before` are forgotten first.
+    async fn add_failed_login(
+        &self,
+        key: &str,
+        now: NaiveDateTime,
+        reset_before: NaiveDateTime,
+    ) -> Result<LoginAttempts, Error>;
+
+    async fn clear_login_attempts(&self, key: &str) -> Result<(), Error>;
+}
+
+/// API keys of accounts. Like refresh tokens only the hash of a key
+/// is stored.
+#[async_trait]
+pub trait ApiKeyStore {
+    async fn add_api_key(
+        &self,
+        account_id: &AccountId,
+        new_key: NewApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey, Error>;
+
+    async fn get_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<ApiKey>, Error>;
+
+    /// The key with the given hash, `None` if there is no such key
+    async fn get_api_key_by_hash(
+        &self,
+        key_hash: &str,
+    ) -> Result<Option<ApiKey>, Error>;
+
+    /// Deletes a key of the account, `false` if it has no such key
+    async fn delete_api_key(
+        &self,
+        id: &ApiKeyId,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+}
+
+/// The logins of accounts, for the device listing. A login ends with
+/// `TokenStore::revoke_session` or when its refresh tokens expired.
+#[async_trait]
+pub trait SessionStore {
+    async fn add_session(&self, session: SessionInfo)
+        -> Result<(), Error>;
+
+    /// Sets the last use of a login to `now`, unless it was used after
+    /// `stale_before` already. Saves a write on every request.
+    async fn touch_session(
+        &self,
+        session_id: &str,
+        now: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<(), Error>;
+
+    /// Logins of the account which still have a refresh token, the
+    /// latest used first
+    async fn get_sessions(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<SessionInfo>, Error>;
+}
+
+/// Moderation results shared by the replicas of the service, as JSON
+/// by the hash of the content. Not part of `Store`, only the databases
+/// can share them.
+#[async_trait]
+pub trait ModerationCacheStore: std::fmt::Debug + Send + Sync {
+    /// `None` if the content was not moderated after `fresh_after`
+    async fn get_moderation(
+        &self,
+        content_hash: &str,
+        fresh_after: NaiveDateTime,
+    ) -> Result<Option<String>, Error>;
+
+    /// Caches the moderation of the content and drops the moderations
+    /// from before `stale_before`
+    async fn put_moderation(
+        &self,
+        content_hash: &str,
+        moderation: &str,
+        now: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<(), Error>;
+}
+
+/// Links between the users of OpenID Connect providers and accounts
+#[async_trait]
+pub trait IdentityStore {
+    /// The account of the user `subject` at `issuer`, `None` if the
+    /// user never logged in
+    async fn get_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<AccountId>, Error>;
+
+    async fn add_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        account_id: &AccountId,
+    ) -> Result<(), Error>;
+}
+
+/// Everything the routes need from a storage backend. Implemented
+/// automatically for any type which implements the individual stores,
+/// so the warp filters can be built generically over it.
+pub trait Store:
+    QuestionStore
+    + AnswerStore
+    + VoteStore
+    + SearchStore
+    + ReviewStore
+    + AccountStore
+    + TokenStore
+    + LoginAttemptStore
+    + ApiKeyStore
+    + IdentityStore
+    + SessionStore
+    + Clone
+    + std::fmt::Debug
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> Store for T where
+    T: QuestionStore
+        + AnswerStore
+        + VoteStore
+        + SearchStore
+        + ReviewStore
+        + AccountStore
+        + TokenStore
+        + LoginAttemptStore
+        + ApiKeyStore
+        + IdentityStore
+        + SessionStore
+        + Clone
+        + std::fmt::Debug
+        + Send
+        + Sync
+        + 'static
+{
+}
@@ -0,0 +1,2446 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{
+    sqlite::{
+        SqliteConnectOptions, SqliteConnection, SqlitePool,
+        SqlitePoolOptions, SqliteRow,
+    },
+    types::Json,
+    Row,
+};
+
+use error_handlers::Error;
+
+use crate::store::{
+    answered_condition, order_by, review_columns, where_clause,
+    AccountStore, AnswerStore, ApiKeyStore, IdentityStore,
+    LoginAttemptStore, ModerationCacheStore, QuestionStore, ReviewStore,
+    SearchStore, SessionStore, TokenStore, VoteStore, VoteTarget,
+};
+use crate::types::{
+    account::{Account, AccountId, Role, SessionInfo},
+    answer::{Answer, AnswerId, NewAnswer},
+    api_key::{
+        scopes_from_string, scopes_to_string, ApiKey, ApiKeyId, NewApiKey,
+    },
+    login_attempt::LoginAttempts,
+    pagination::Cursor,
+    question::{
+        NewQuestion, Question, QuestionId, QuestionQuery, QuestionSort,
+    },
+    review::{
+        BadWords, NewReview, Review, ReviewId, ReviewTarget, ReviewText,
+    },
+    search::{SearchQuery, SearchResult},
+    tag::{TagCount, TagMatch},
+    token::{AccountToken, RefreshToken, TokenPurpose},
+    vote::Vote,
+};
+
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pub connection: SqlitePool,
+}
+
+impl SqliteStore {
+    pub async fn new(db_url: &str) -> Result<Self, sqlx::Error> {
+        tracing::warn!("{}", db_url);
+        let options = SqliteConnectOptions::from_str(db_url)?
+            .create_if_missing(true);
+
+        // Every connection to `sqlite::memory:` opens its own empty
+        // database, so in that case the pool must hold on to a single one
+        let pool_options = if db_url.contains(":memory:") {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new().max_connections(5)
+        };
+
+        let db_pool = pool_options.connect_with(options).await?;
+
+        Ok(SqliteStore {
+            connection: db_pool,
+        })
+    }
+
+    /// Questions matching the query, each with its cursor. `after` only
+    /// returns questions which come after the cursor in
+    /// `(created_on, id)` order.
+    async fn query_questions(
+        &self,
+        query: &QuestionQuery,
+        after: Option<&Cursor>,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<(Question, Cursor)>, Error> {
+        // ?1 and ?2 are the limit and offset, the filters and the cursor
+        // take the following parameters
+        let mut params = 2;
+        let mut conditions = filter_conditions(query, &mut params);
+        if after.is_some() {
+            conditions.push(format!(
+                "(created_on, id) > (?{}, ?{})",
+                params + 1,
+                params + 2
+            ));
+        }
+
+        let sql = format!(
+            "SELECT * from questions {} {} LIMIT ?1 OFFSET ?2",
+            where_clause(&conditions),
+            order_by(query.sort)
+        );
+
+        // A negative LIMIT means no limit in SQLite
+        let mut sql_query =
+            sqlx::query(&sql).bind(limit.unwrap_or(-1)).bind(offset);
+        if !query.tags.is_empty() {
+            sql_query = sql_query.bind(Json(&query.tags));
+        }
+        if let Some(after) = after {
+            sql_query = sql_query.bind(after.created_on).bind(after.id);
+        }
+
+        match sql_query
+            .map(|row: SqliteRow| {
+                let cursor = to_cursor(&row);
+                (to_question(row), cursor)
+            })
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(questions) => Ok(questions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    /// Replaces the vote of an account on an item, or removes it for
+    /// `None`, and stores the recalculated score on the item
+    async fn cast_vote(
+        &self,
+        target: VoteTarget,
+        id: i32,
+        account_id: &AccountId,
+        vote: Option<Vote>,
+    ) -> Result<i32, Error> {
+        let (items, votes, column) = target.tables();
+
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            match vote {
+                Some(vote) => {
+                    sqlx::query(&format!(
+                        "INSERT INTO {votes} ({column}, account_id, value) VALUES (?, ?, ?)
+                        ON CONFLICT ({column}, account_id) DO UPDATE SET value = excluded.value"
+                    ))
+                    .bind(id)
+                    .bind(account_id.0)
+                    .bind(vote.value())
+                    .execute(&mut *tx)
+                    .await?
+                }
+                None => {
+                    sqlx::query(&format!(
+                        "DELETE FROM {votes} WHERE {column} = ? AND account_id = ?"
+                    ))
+                    .bind(id)
+                    .bind(account_id.0)
+                    .execute(&mut *tx)
+                    .await?
+                }
+            };
+
+            let score = sqlx::query(&format!(
+                "UPDATE {items} SET score = (SELECT COALESCE(SUM(value), 0) FROM {votes} WHERE {column} = ?)
+                WHERE id = ? RETURNING score"
+            ))
+            .bind(id)
+            .bind(id)
+            .map(|row: SqliteRow| row.get("score"))
+            .fetch_one(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok::<i32, sqlx::Error>(score)
+        }
+        .await;
+
+        match res {
+            Ok(score) => Ok(score),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+fn to_answer(row: SqliteRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("corresponding_question")),
+        score: row.get("score"),
+    }
+}
+
+fn to_account(row: SqliteRow) -> Account {
+    Account {
+        id: Some(AccountId(row.get("id"))),
+        email: row.get("email"),
+        password: row.get("password"),
+        role: row.get::<String, _>("role").parse().unwrap_or_default(),
+        verified: row.get("verified"),
+    }
+}
+
+fn to_refresh_token(row: SqliteRow) -> RefreshToken {
+    RefreshToken {
+        token_hash: row.get("token_hash"),
+        session_id: row.get("session_id"),
+        account_id: AccountId(row.get("account_id")),
+        expires_on: row.get("expires_on"),
+        used: row.get("used"),
+    }
+}
+
+/// `WHERE` conditions for the filters of the query. The parameters of
+/// the filters are numbered after `params`, which is advanced past them.
+/// Only the tags filter takes a parameter.
+fn filter_conditions(
+    query: &QuestionQuery,
+    params: &mut usize,
+) -> Vec<String> {
+    // Pending questions wait for a moderator
+    let mut conditions = vec!["NOT pending".to_string()];
+    conditions
+        .extend(answered_condition(query.answered).map(String::from));
+    // Tags are JSON arrays, the parameter is the JSON array of the
+    // wanted tags
+    if !query.tags.is_empty() {
+        *params += 1;
+        conditions.push(match query.tag_match {
+            TagMatch::Any => format!(
+                "EXISTS (SELECT 1 FROM json_each(questions.tags)
+                WHERE value IN (SELECT value FROM json_each(?{})))",
+                params
+            ),
+            TagMatch::All => format!(
+                "NOT EXISTS (SELECT 1 FROM json_each(?{})
+                WHERE value NOT IN (SELECT value FROM json_each(questions.tags)))",
+                params
+            ),
+        });
+    }
+
+    conditions
+}
+
+fn to_cursor(row: &SqliteRow) -> Cursor {
+    Cursor {
+        created_on: row.get("created_on"),
+        id: row.get("id"),
+    }
+}
+
+fn to_question(row: SqliteRow) -> Question {
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row
+            .get::<Option<Json<Vec<String>>>, _>("tags")
+            .map(|tags| tags.0),
+        score: row.get("score"),
+        accepted_answer_id: row
+            .get::<Option<i32>, _>("accepted_answer_id")
+            .map(AnswerId),
+    }
+}
+
+#[async_trait]
+impl QuestionStore for SqliteStore {
+    async fn get_questions(
+        &self,
+        query: &QuestionQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error> {
+        let questions =
+            self.query_questions(query, None, limit, offset).await?;
+
+        Ok(questions
+            .into_iter()
+            .map(|(question, _)| question)
+            .collect())
+    }
+
+    async fn get_questions_after(
+        &self,
+        query: &QuestionQuery,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Vec<(Question, Cursor)>, Error> {
+        let query = QuestionQuery {
+            sort: QuestionSort::None,
+            ..query.clone()
+        };
+
+        self.query_questions(&query, after, Some(limit), 0).await
+    }
+
+    async fn count_questions(
+        &self,
+        query: &QuestionQuery,
+    ) -> Result<i64, Error> {
+        let mut params = 0;
+        let sql = format!(
+            "SELECT COUNT(*) AS total from questions {}",
+            where_clause(&filter_conditions(query, &mut params))
+        );
+
+        let mut sql_query = sqlx::query(&sql);
+        if !query.tags.is_empty() {
+            sql_query = sql_query.bind(Json(&query.tags));
+        }
+
+        match sql_query
+            .map(|row: SqliteRow| row.get("total"))
+            .fetch_one(&self.connection)
+            .await
+        {
+            Ok(total) => Ok(total),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question(
+        &self,
+        id: i32,
+    ) -> Result<Option<Question>, Error> {
+        match sqlx::query(
+            "SELECT id, title, content, tags, score, accepted_answer_id from questions WHERE id = ? AND NOT pending",
+        )
+        .bind(id)
+        .map(to_question)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "SELECT * from questions where id = ? and account_id = ?",
+        )
+        .bind(question_id)
+        .bind(account_id.0)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+        review: Option<NewReview>,
+    ) -> Result<Question, Error> {
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            let question = sqlx::query("INSERT INTO questions (title, content, tags, account_id, pending) VALUES (?, ?, ?, ?, ?) RETURNING id, title, content, tags, score, accepted_answer_id")
+                .bind(new_question.title)
+                .bind(new_question.content)
+                .bind(new_question.tags.map(Json))
+                .bind(account_id.0)
+                .bind(review.is_some())
+                .map(to_question)
+                .fetch_one(&mut *tx)
+                .await?;
+            let target = ReviewTarget::Question(question.id.clone());
+            replace_review(&mut tx, &target, review).await?;
+
+            tx.commit().await?;
+            Ok::<Question, sqlx::Error>(question)
+        }
+        .await;
+
+        match res {
+            Ok(question) => Ok(question),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        id: i32,
+        account_id: Option<AccountId>,
+        review: Option<NewReview>,
+    ) -> Result<Option<Question>, Error> {
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            let Some(question) = sqlx::query(
+                "UPDATE questions SET title = ?1, content = ?2, tags = ?3, pending = ?6
+            WHERE id = ?4 AND (?5 IS NULL OR account_id = ?5)
+            RETURNING id, title, content, tags, score, accepted_answer_id",
+            )
+            .bind(question.title)
+            .bind(question.content)
+            .bind(question.tags.map(Json))
+            .bind(id)
+            .bind(account_id.map(|a| a.0))
+            .bind(review.is_some())
+            .map(to_question)
+            .fetch_optional(&mut *tx)
+            .await?
+            else {
+                return Ok(None);
+            };
+            let target = ReviewTarget::Question(question.id.clone());
+            replace_review(&mut tx, &target, review).await?;
+
+            tx.commit().await?;
+            Ok::<Option<Question>, sqlx::Error>(Some(question))
+        }
+        .await;
+
+        match res {
+            Ok(question) => Ok(question),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn delete_question(
+        &self,
+        id: i32,
+        account_id: Option<AccountId>,
+    ) -> Result<bool, Error> {
+        let account_id = account_id.map(|a| a.0);
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            // The answers would block the delete, their votes and
+            // reviews go with them
+            sqlx::query(
+                "DELETE FROM answers WHERE corresponding_question IN (
+                SELECT id FROM questions
+                WHERE id = ?1 AND (?2 IS NULL OR account_id = ?2)
+            )",
+            )
+            .bind(id)
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+            let res = sqlx::query(
+                "DELETE FROM questions
+            WHERE id = ?1 AND (?2 IS NULL OR account_id = ?2)",
+            )
+            .bind(id)
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok::<bool, sqlx::Error>(res.rows_affected() > 0)
+        }
+        .await;
+
+        match res {
+            Ok(deleted) => Ok(deleted),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        match sqlx::query(
+            "SELECT json_each.value AS tag, COUNT(*) AS count
+        FROM questions, json_each(questions.tags)
+        WHERE NOT pending
+        GROUP BY tag
+        ORDER BY count DESC, tag",
+        )
+        .map(|row: SqliteRow| TagCount {
+            tag: row.get("tag"),
+            count: row.get("count"),
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(tags) => Ok(tags),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions SET accepted_answer_id = ?
+        WHERE id = ?
+        RETURNING id, title, content, tags, score, accepted_answer_id",
+        )
+        .bind(answer_id)
+        .bind(question_id)
+        .map(to_question)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AnswerStore for SqliteStore {
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error> {
+        match sqlx::query(
+            "SELECT id, content, corresponding_question, score from answers
+        WHERE corresponding_question = ? AND NOT pending
+        ORDER BY created_on, id
+        LIMIT ? OFFSET ?",
+        )
+        .bind(question_id)
+        .bind(limit.unwrap_or(-1))
+        .bind(offset)
+        .map(to_answer)
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answer(&self, id: i32) -> Result<Option<Answer>, Error> {
+        match sqlx::query(
+            "SELECT id, content, corresponding_question, score from answers WHERE id = ? AND NOT pending",
+        )
+        .bind(id)
+        .map(to_answer)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_answer_owner(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "SELECT * from answers where id = ? and account_id = ?",
+        )
+        .bind(answer_id)
+        .bind(account_id.0)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(answer) => Ok(answer.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_answer(
+        &self,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+        review: Option<NewReview>,
+    ) -> Result<Answer, Error> {
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            let answer = sqlx::query(
+                "INSERT INTO answers (content, corresponding_question, account_id, pending) VALUES (?, ?, ?, ?)
+                RETURNING id, content, corresponding_question, score",
+            )
+            .bind(new_answer.content)
+            .bind(new_answer.question_id.0)
+            .bind(account_id.0)
+            .bind(review.is_some())
+            .map(to_answer)
+            .fetch_one(&mut *tx)
+            .await?;
+            let target = ReviewTarget::Answer(answer.id.clone());
+            replace_review(&mut tx, &target, review).await?;
+
+            tx.commit().await?;
+            Ok::<Answer, sqlx::Error>(answer)
+        }
+        .await;
+
+        match res {
+            Ok(answer) => Ok(answer),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        id: i32,
+        account_id: Option<AccountId>,
+        review: Option<NewReview>,
+    ) -> Result<Option<Answer>, Error> {
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            let Some(answer) = sqlx::query(
+                "UPDATE answers SET content = ?1, pending = ?4
+            WHERE id = ?2 AND (?3 IS NULL OR account_id = ?3)
+            RETURNING id, content, corresponding_question, score",
+            )
+            .bind(answer.content)
+            .bind(id)
+            .bind(account_id.map(|a| a.0))
+            .bind(review.is_some())
+            .map(to_answer)
+            .fetch_optional(&mut *tx)
+            .await?
+            else {
+                return Ok(None);
+            };
+            let target = ReviewTarget::Answer(answer.id.clone());
+            replace_review(&mut tx, &target, review).await?;
+
+            tx.commit().await?;
+            Ok::<Option<Answer>, sqlx::Error>(Some(answer))
+        }
+        .await;
+
+        match res {
+            Ok(answer) => Ok(answer),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn delete_answer(
+        &self,
+        id: i32,
+        account_id: Option<AccountId>,
+    ) -> Result<Option<Answer>, Error> {
+        match sqlx::query(
+            "DELETE FROM answers
+        WHERE id = ?1 AND (?2 IS NULL OR account_id = ?2)
+        RETURNING id, content, corresponding_question, score",
+        )
+        .bind(id)
+        .bind(account_id.map(|a| a.0))
+        .map(to_answer)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl VoteStore for SqliteStore {
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+        vote: Vote,
+    ) -> Result<i32, Error> {
+        self.cast_vote(
+            VoteTarget::Question,
+            question_id,
+            account_id,
+            Some(vote),
+        )
+        .await
+    }
+
+    async fn retract_question_vote(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<i32, Error> {
+        self.cast_vote(VoteTarget::Question, question_id, account_id, None)
+            .await
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+        vote: Vote,
+    ) -> Result<i32, Error> {
+        self.cast_vote(
+            VoteTarget::Answer,
+            answer_id,
+            account_id,
+            Some(vote),
+        )
+        .await
+    }
+
+    async fn retract_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<i32, Error> {
+        self.cast_vote(VoteTarget::Answer, answer_id, account_id, None)
+            .await
+    }
+}
+
+#[async_trait]
+impl SearchStore for SqliteStore {
+    /// SQLite has no full-text ranking like Postgres, so this matches the
+    /// whole search text case-insensitively and ranks matches in the
+    /// question above matches in its answers only
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchResult>, Error> {
+        let pattern = format!("%{}%", query.q.to_lowercase());
+
+        match sqlx::query(
+            "SELECT id, title, content, tags, score, accepted_answer_id,
+            CASE WHEN search LIKE ?1 THEN 1.0 ELSE 0.5 END AS rank
+        FROM questions
+        WHERE (search LIKE ?1 OR EXISTS (
+                SELECT 1 FROM answers
+                WHERE answers.corresponding_question = questions.id
+                AND answers.search LIKE ?1 AND NOT answers.pending
+            ))
+        AND NOT pending
+        AND (?2 IS NULL OR EXISTS (
+                SELECT 1 FROM json_each(questions.tags) WHERE value = ?2
+            ))
+        AND (?3 IS NULL OR account_id = ?3)
+        ORDER BY rank DESC, id
+        LIMIT ?4 OFFSET ?5",
+        )
+        .bind(pattern)
+        .bind(&query.tag)
+        .bind(query.author.as_ref().map(|author| author.0))
+        .bind(limit.unwrap_or(-1))
+        .bind(offset)
+        .map(|row: SqliteRow| SearchResult {
+            rank: row.get::<f64, _>("rank") as f32,
+            question: to_question(row),
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(results) => Ok(results),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ReviewStore for SqliteStore {
+    async fn get_reviews(&self) -> Result<Vec<Review>, Error> {
+        match sqlx::query("SELECT * FROM reviews ORDER BY created_on, id")
+            .map(to_review)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(reviews) => Ok(reviews),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_review(
+        &self,
+        id: &ReviewId,
+    ) -> Result<Option<Review>, Error> {
+        match sqlx::query("SELECT * FROM reviews WHERE id = ?1")
+            .bind(id.0)
+            .map(to_review)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(review) => Ok(review),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn publish_review(
+        &self,
+        id: &ReviewId,
+        text: ReviewText,
+    ) -> Result<bool, Error> {
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            let Some(review) =
+                sqlx::query("DELETE FROM reviews WHERE id = ?1 RETURNING *")
+                    .bind(id.0)
+                    .map(to_review)
+                    .fetch_optional(&mut *tx)
+                    .await?
+            else {
+                return Ok(false);
+            };
+
+            match review.target {
+                ReviewTarget::Question(question_id) => {
+                    sqlx::query(
+                        "UPDATE questions SET title = COALESCE(?1, title), content = ?2, \
+                        pending = FALSE WHERE id = ?3",
+                    )
+                    .bind(text.title)
+                    .bind(text.content)
+                    .bind(question_id.0)
+                    .execute(&mut *tx)
+                    .await?
+                }
+                ReviewTarget::Answer(answer_id) => {
+                    sqlx::query(
+                        "UPDATE answers SET content = ?1, pending = FALSE WHERE id = ?2",
+                    )
+                    .bind(text.content)
+                    .bind(answer_id.0)
+                    .execute(&mut *tx)
+                    .await?
+                }
+            };
+
+            tx.commit().await?;
+            Ok::<bool, sqlx::Error>(true)
+        }
+        .await;
+
+        match res {
+            Ok(published) => Ok(published),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+fn to_review(row: SqliteRow) -> Review {
+    let target = match row.get::<Option<i32>, _>("question_id") {
+        Some(id) => ReviewTarget::Question(QuestionId(id)),
+        None => ReviewTarget::Answer(AnswerId(row.get("answer_id"))),
+    };
+
+    Review {
+        id: ReviewId(row.get("id")),
+        target,
+        original: ReviewText {
+            title: row.get("original_title"),
+            content: row.get("original_content"),
+        },
+        censored: ReviewText {
+            title: row.get("censored_title"),
+            content: row.get("censored_content"),
+        },
+        bad_words: row.get::<Json<BadWords>, _>("bad_words").0,
+        unchecked: row.get("unchecked"),
+        created_on: row.get("created_on"),
+    }
+}
+
+/// Replaces the review of a post in the transaction writing the post.
+/// Without a new review the old one is only dropped.
+async fn replace_review(
+    tx: &mut SqliteConnection,
+    target: &ReviewTarget,
+    review: Option<NewReview>,
+) -> Result<(), sqlx::Error> {
+    let (question_id, answer_id) = review_columns(target);
+    sqlx::query(
+        "DELETE FROM reviews WHERE question_id = ?1 OR answer_id = ?2",
+    )
+    .bind(question_id)
+    .bind(answer_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(review) = review {
+        sqlx::query(
+            "INSERT INTO reviews (question_id, answer_id, original_title, original_content, \
+            censored_title, censored_content, bad_words, unchecked) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .bind(review.original.title)
+        .bind(review.original.content)
+        .bind(review.censored.title)
+        .bind(review.censored.content)
+        .bind(Json(review.bad_words))
+        .bind(review.unchecked)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl AccountStore for SqliteStore {
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO accounts (email, password, role, verified) \
+            VALUES (?, ?, ?, ?)",
+        )
+        .bind(account.email)
+        .bind(account.password)
+        .bind(account.role.as_str())
+        .bind(account.verified)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query("SELECT * from accounts where email = ?")
+            .bind(email)
+            .map(to_account)
+            .fetch_one(&self.connection)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_account_by_id(
+        &self,
+        id: &AccountId,
+    ) -> Result<Option<Account>, Error> {
+        match sqlx::query("SELECT * from accounts where id = ?")
+            .bind(id.0)
+            .map(to_account)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, Error> {
+        match sqlx::query("SELECT * from accounts ORDER BY id")
+            .map(to_account)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(accounts) => Ok(accounts),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_role(
+        &self,
+        id: &AccountId,
+        role: Role,
+    ) -> Result<Option<Account>, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET role = ? WHERE id = ? RETURNING *",
+        )
+        .bind(role.as_str())
+        .bind(id.0)
+        .map(to_account)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(account) => Ok(account),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn delete_account(&self, id: &AccountId) -> Result<bool, Error> {
+        match sqlx::query("DELETE FROM accounts WHERE id = ?")
+            .bind(id.0)
+            .execute(&self.connection)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_password(
+        &self,
+        id: &AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET password = ?1 WHERE id = ?2",
+        )
+        .bind(password)
+        .bind(id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_verified(&self, id: &AccountId) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET verified = TRUE WHERE id = ?1",
+        )
+        .bind(id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TokenStore for SqliteStore {
+    async fn add_refresh_token(
+        &self,
+        token: RefreshToken,
+    ) -> Result<(), Error> {
+        // Expired tokens are of no use anymore, clean them up on the way
+        if let Err(e) =
+            sqlx::query("DELETE FROM refresh_tokens WHERE expires_on < ?")
+                .bind(Utc::now().naive_utc())
+                .execute(&self.connection)
+                .await
+        {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        match sqlx::query(
+            "INSERT INTO refresh_tokens \
+            (token_hash, session_id, account_id, expires_on, used) \
+            VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(token.token_hash)
+        .bind(token.session_id)
+        .bind(token.account_id.0)
+        .bind(token.expires_on)
+        .bind(token.used)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn use_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, Error> {
+        // Only one of two concurrent requests with the same token gets
+        // to flip `used`, the other one sees it as reused
+        let fresh = match sqlx::query(
+            "UPDATE refresh_tokens SET used = TRUE \
+            WHERE token_hash = ? AND NOT used RETURNING *",
+        )
+        .bind(token_hash)
+        .map(to_refresh_token)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(token) => token,
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                return Err(Error::DatabaseQueryError(e));
+            }
+        };
+        if let Some(token) = fresh {
+            return Ok(Some(RefreshToken {
+                used: false,
+                ..token
+            }));
+        }
+
+        match sqlx::query(
+            "SELECT * FROM refresh_tokens WHERE token_hash = ?",
+        )
+        .bind(token_hash)
+        .map(to_refresh_token)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(token) => Ok(token),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_session(&self, session_id: &str) -> Result<(), Error> {
+        for query in [
+            "DELETE FROM refresh_tokens WHERE session_id = ?",
+            "DELETE FROM sessions WHERE session_id = ?",
+        ] {
+            if let Err(e) = sqlx::query(query)
+                .bind(session_id)
+                .execute(&self.connection)
+                .await
+            {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                return Err(Error::DatabaseQueryError(e));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_token(
+        &self,
+        token_id: &str,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error> {
+        if let Err(e) =
+            sqlx::query("DELETE FROM revoked_tokens WHERE expires_on < ?")
+                .bind(Utc::now().naive_utc())
+                .execute(&self.connection)
+                .await
+        {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        match sqlx::query(
+            "INSERT INTO revoked_tokens (token_id, expires_on) \
+            VALUES (?, ?) ON CONFLICT DO NOTHING",
+        )
+        .bind(token_id)
+        .bind(expires_on)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_token_revoked(
+        &self,
+        token_id: &str,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "SELECT 1 FROM revoked_tokens WHERE token_id = ?",
+        )
+        .bind(token_id)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(row) => Ok(row.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_account_sessions(
+        &self,
+        account_id: &AccountId,
+        except: Option<&str>,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let res = async {
+            let mut tx = self.connection.begin().await?;
+
+            // The ids go on the revocation list before their rows are
+            // gone
+            sqlx::query(
+                "INSERT OR IGNORE INTO revoked_tokens \
+                (token_id, expires_on) \
+                SELECT session_id, ?3 FROM refresh_tokens \
+                WHERE account_id = ?1 \
+                AND (?2 IS NULL OR session_id <> ?2) \
+                UNION SELECT session_id, ?3 FROM sessions \
+                WHERE account_id = ?1 \
+                AND (?2 IS NULL OR session_id <> ?2)",
+            )
+            .bind(account_id.0)
+            .bind(except)
+            .bind(expires_on)
+            .execute(&mut *tx)
+            .await?;
+            for query in [
+                "DELETE FROM refresh_tokens WHERE account_id = ?1 \
+                AND (?2 IS NULL OR session_id <> ?2)",
+                "DELETE FROM sessions WHERE account_id = ?1 \
+                AND (?2 IS NULL OR session_id <> ?2)",
+            ] {
+                sqlx::query(query)
+                    .bind(account_id.0)
+                    .bind(except)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+            Ok::<(), sqlx::Error>(())
+        }
+        .await;
+
+        match res {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_account_token(
+        &self,
+        token: AccountToken,
+    ) -> Result<(), Error> {
+        match sqlx::query(
+            "INSERT INTO account_tokens \
+            (token_hash, account_id, purpose, expires_on) \
+            VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(token.token_hash)
+        .bind(token.account_id.0)
+        .bind(token.purpose.as_str())
+        .bind(token.expires_on)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn take_account_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> Result<Option<AccountToken>, Error> {
+        match sqlx::query(
+            "DELETE FROM account_tokens \
+            WHERE token_hash = ?1 AND purpose = ?2 \
+            RETURNING token_hash, account_id, expires_on",
+        )
+        .bind(token_hash)
+        .bind(purpose.as_str())
+        .map(|row: SqliteRow| AccountToken {
+            token_hash: row.get("token_hash"),
+            account_id: AccountId(row.get("account_id")),
+            purpose,
+            expires_on: row.get("expires_on"),
+        })
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(token) => Ok(token),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for SqliteStore {
+    async fn add_api_key(
+        &self,
+        account_id: &AccountId,
+        new_key: NewApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Result<ApiKey, Error> {
+        match sqlx::query(
+            "INSERT INTO api_keys \
+            (account_id, name, prefix, key_hash, scopes) \
+            VALUES (?1, ?2, ?3, ?4, ?5) \
+            RETURNING id, account_id, name, prefix, scopes, created_on",
+        )
+        .bind(account_id.0)
+        .bind(new_key.name)
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(scopes_to_string(&new_key.scopes))
+        .map(|row: SqliteRow| to_api_key(&row))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(api_key) => Ok(api_key),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<ApiKey>, Error> {
+        match sqlx::query(
+            "SELECT id, account_id, name, prefix, scopes, created_on \
+            FROM api_keys WHERE account_id = ?1 ORDER BY id",
+        )
+        .bind(account_id.0)
+        .map(|row: SqliteRow| to_api_key(&row))
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(api_keys) => Ok(api_keys),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_api_key_by_hash(
+        &self,
+        key_hash: &str,
+    ) -> Result<Option<ApiKey>, Error> {
+        match sqlx::query(
+            "SELECT id, account_id, name, prefix, scopes, created_on \
+            FROM api_keys WHERE key_hash = ?1",
+        )
+        .bind(key_hash)
+        .map(|row: SqliteRow| to_api_key(&row))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(api_key) => Ok(api_key),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_api_key(
+        &self,
+        id: &ApiKeyId,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "DELETE FROM api_keys WHERE id = ?1 AND account_id = ?2",
+        )
+        .bind(id.0)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+fn to_api_key(row: &SqliteRow) -> ApiKey {
+    ApiKey {
+        id: ApiKeyId(row.get("id")),
+        account_id: AccountId(row.get("account_id")),
+        name: row.get("name"),
+        prefix: row.get("prefix"),
+        scopes: scopes_from_string(row.get("scopes")),
+        created_on: row.get("created_on"),
+    }
+}
+
+#[async_trait]
+impl SessionStore for SqliteStore {
+    async fn add_session(
+        &self,
+        session: SessionInfo,
+    ) -> Result<(), Error> {
+        match sqlx::query(
+            "INSERT INTO sessions \
+            (session_id, account_id, created_on, last_used, user_agent, ip) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(session.session_id)
+        .bind(session.account_id.0)
+        .bind(session.created_on)
+        .bind(session.last_used)
+        .bind(session.user_agent)
+        .bind(session.ip)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn touch_session(
+        &self,
+        session_id: &str,
+        now: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<(), Error> {
+        match sqlx::query(
+            "UPDATE sessions SET last_used = ?2 \
+            WHERE session_id = ?1 AND last_used < ?3",
+        )
+        .bind(session_id)
+        .bind(now)
+        .bind(stale_before)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_sessions(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<SessionInfo>, Error> {
+        match sqlx::query(
+            "SELECT session_id, account_id, created_on, last_used, \
+            user_agent, ip FROM sessions s WHERE account_id = ?1 \
+            AND EXISTS (SELECT 1 FROM refresh_tokens r \
+            WHERE r.session_id = s.session_id AND NOT r.used \
+            AND r.expires_on >= ?2) \
+            ORDER BY last_used DESC, created_on DESC",
+        )
+        .bind(account_id.0)
+        .bind(Utc::now().naive_utc())
+        .map(|row: SqliteRow| to_session(&row))
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(sessions) => Ok(sessions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+fn to_session(row: &SqliteRow) -> SessionInfo {
+    SessionInfo {
+        session_id: row.get("session_id"),
+        account_id: AccountId(row.get("account_id")),
+        created_on: row.get("created_on"),
+        last_used: row.get("last_used"),
+        user_agent: row.get("user_agent"),
+        ip: row.get("ip"),
+        current: false,
+    }
+}
+
+#[async_trait]
+impl IdentityStore for SqliteStore {
+    async fn get_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<AccountId>, Error> {
+        match sqlx::query(
+            "SELECT account_id FROM identities \
+            WHERE issuer = ?1 AND subject = ?2",
+        )
+        .bind(issuer)
+        .bind(subject)
+        .map(|row: SqliteRow| AccountId(row.get("account_id")))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(account_id) => Ok(account_id),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        account_id: &AccountId,
+    ) -> Result<(), Error> {
+        match sqlx::query(
+            "INSERT INTO identities (issuer, subject, account_id) \
+            VALUES (?1, ?2, ?3)",
+        )
+        .bind(issuer)
+        .bind(subject)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for SqliteStore {
+    async fn get_login_attempts(
+        &self,
+        key: &str,
+    ) -> Result<Option<LoginAttempts>, Error> {
+        match sqlx::query(
+            "SELECT key, failures, last_failure FROM login_attempts \
+            WHERE key = ?1",
+        )
+        .bind(key)
+        .map(|row: SqliteRow| to_login_attempts(&row))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(attempts) => Ok(attempts),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_failed_login(
+        &self,
+        key: &str,
+        now: NaiveDateTime,
+        reset_before: NaiveDateTime,
+    ) -> Result<LoginAttempts, Error> {
+        // One statement, so replicas counting at the same time do not
+        // lose failures
+        match sqlx::query(
+            "INSERT INTO login_attempts (key, failures, last_failure) \
+            VALUES (?1, 1, ?2) \
+            ON CONFLICT (key) DO UPDATE SET \
+            failures = CASE WHEN login_attempts.last_failure < ?3 \
+            THEN 1 ELSE login_attempts.failures + 1 END, \
+            last_failure = ?2 \
+            RETURNING key, failures, last_failure",
+        )
+        .bind(key)
+        .bind(now)
+        .bind(reset_before)
+        .map(|row: SqliteRow| to_login_attempts(&row))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(attempts) => Ok(attempts),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn clear_login_attempts(&self, key: &str) -> Result<(), Error> {
+        match sqlx::query("DELETE FROM login_attempts WHERE key = ?1")
+            .bind(key)
+            .execute(&self.connection)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+fn to_login_attempts(row: &SqliteRow) -> LoginAttempts {
+    LoginAttempts {
+        key: row.get("key"),
+        failures: row.get("failures"),
+        last_failure: row.get("last_failure"),
+    }
+}
+
+#[async_trait]
+impl ModerationCacheStore for SqliteStore {
+    async fn get_moderation(
+        &self,
+        content_hash: &str,
+        fresh_after: NaiveDateTime,
+    ) -> Result<Option<String>, Error> {
+        match sqlx::query(
+            "SELECT moderation FROM moderation_cache \
+            WHERE content_hash = ?1 AND created_on > ?2",
+        )
+        .bind(content_hash)
+        .bind(fresh_after)
+        .map(|row: SqliteRow| row.get("moderation"))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(moderation) => Ok(moderation),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn put_moderation(
+        &self,
+        content_hash: &str,
+        moderation: &str,
+        now: NaiveDateTime,
+        stale_before: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let upsert = sqlx::query(
+            "INSERT INTO moderation_cache \
+            (content_hash, moderation, created_on) VALUES (?1, ?2, ?3) \
+            ON CONFLICT (content_hash) DO UPDATE \
+            SET moderation = excluded.moderation, \
+            created_on = excluded.created_on",
+        )
+        .bind(content_hash)
+        .bind(moderation)
+        .bind(now)
+        .execute(&self.connection)
+        .await;
+        let cleanup =
+            match upsert {
+                Ok(_) => sqlx::query(
+                    "DELETE FROM moderation_cache WHERE created_on < ?1",
+                )
+                .bind(stale_before)
+                .execute(&self.connection)
+                .await,
+                Err(e) => Err(e),
+            };
+
+        match cleanup {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod sqlite_tests {
+    use super::*;
+    use chrono::{Duration, SubsecRound};
+    use warp::Reply;
+
+    async fn store() -> SqliteStore {
+        let store = SqliteStore::new("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&store.connection)
+            .await
+            .unwrap();
+        store
+    }
+
+    fn new_question() -> NewQuestion {
+        NewQuestion {
+            title: "First question".to_string(),
+            content: "How does this work?".to_string(),
+            tags: Some(vec!["general".to_string()]),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_and_update_question() {
+        let store = store().await;
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        assert_eq!(question.tags, Some(vec!["general".to_string()]));
+        assert!(store
+            .is_question_owner(question.id.0, &AccountId(1))
+            .await
+            .unwrap());
+
+        let update = Question {
+            title: "Updated question".to_string(),
+            tags: None,
+            ..question.clone()
+        };
+        assert!(store
+            .update_question(
+                update.clone(),
+                question.id.0,
+                Some(AccountId(2)),
+                None
+            )
+            .await
+            .unwrap()
+            .is_none());
+        store
+            .update_question(
+                update.clone(),
+                question.id.0,
+                Some(AccountId(1)),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let questions = store
+            .get_questions(&QuestionQuery::default(), None, 0)
+            .await
+            .unwrap();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].title, "Updated question");
+        assert_eq!(questions[0].tags, None);
+
+        // Moderators act without the ownership restriction
+        let moderated = Question {
+            title: "Moderated question".to_string(),
+            ..update
+        };
+        let question = store
+            .update_question(moderated, question.id.0, None, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(question.title, "Moderated question");
+        assert!(!store
+            .delete_question(question.id.0, Some(AccountId(2)))
+            .await
+            .unwrap());
+        assert!(store.delete_question(question.id.0, None).await.unwrap());
+        assert!(!store
+            .delete_question(question.id.0, None)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn one_vote_per_account() {
+        let store = store().await;
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        let id = question.id.0;
+
+        assert_eq!(
+            store
+                .vote_question(id, &AccountId(2), Vote::Up)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store
+                .vote_question(id, &AccountId(2), Vote::Up)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store
+                .vote_question(id, &AccountId(3), Vote::Down)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .retract_question_vote(id, &AccountId(3))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store.get_question(id).await.unwrap().unwrap().score,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn deleting_accepted_answer() {
+        let store = store().await;
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        let answer = store
+            .add_answer(
+                NewAnswer {
+                    content: "Like this".to_string(),
+                    question_id: question.id.clone(),
+                },
+                AccountId(2),
+                None,
+            )
+            .await
+            .unwrap();
+        store
+            .set_accepted_answer(question.id.0, Some(answer.id.0))
+            .await
+            .unwrap();
+
+        let answered = QuestionQuery {
+            answered: Some(true),
+            ..QuestionQuery::default()
+        };
+        let questions =
+            store.get_questions(&answered, None, 0).await.unwrap();
+        assert_eq!(
+            questions[0].accepted_answer_id,
+            Some(answer.id.clone())
+        );
+
+        store
+            .delete_answer(answer.id.0, Some(AccountId(2)))
+            .await
+            .unwrap();
+        let questions =
+            store.get_questions(&answered, None, 0).await.unwrap();
+        assert!(questions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reviews() {
+        let store = store().await;
+        let new_review = |content: &str| NewReview {
+            original: ReviewText {
+                title: Some("First question".to_string()),
+                content: content.to_string(),
+            },
+            censored: ReviewText {
+                title: None,
+                content: "*".repeat(content.len()),
+            },
+            bad_words: BadWords::default(),
+            unchecked: true,
+        };
+
+        // Pending from the first write on
+        let question = store
+            .add_question(
+                new_question(),
+                AccountId(1),
+                Some(new_review("shit")),
+            )
+            .await
+            .unwrap();
+        let id = question.id.0;
+        assert!(store.get_question(id).await.unwrap().is_none());
+        let query = QuestionQuery::default();
+        assert_eq!(store.count_questions(&query).await.unwrap(), 0);
+        assert!(store.get_tags().await.unwrap().is_empty());
+
+        // Another flagged edit replaces the review
+        store
+            .update_question(
+                question.clone(),
+                id,
+                None,
+                Some(new_review("crap")),
+            )
+            .await
+            .unwrap();
+        let reviews = store.get_reviews().await.unwrap();
+        assert_eq!(reviews.len(), 1);
+        let review = reviews[0].clone();
+        assert_eq!(review.target, ReviewTarget::Question(question.id));
+        assert_eq!(review.original.content, "crap");
+
+        assert!(store
+            .publish_review(
+                &review.id,
+                ReviewText {
+                    title: None,
+                    content: "Edited".to_string(),
+                },
+            )
+            .await
+            .unwrap());
+        assert!(store.get_review(&review.id).await.unwrap().is_none());
+        let questions =
+            store.get_questions(&query, None, 0).await.unwrap();
+        assert_eq!(questions[0].title, "First question");
+        assert_eq!(questions[0].content, "Edited");
+
+        // A clean edit publishes the post and drops its review
+        let edited = store
+            .update_question(
+                questions[0].clone(),
+                id,
+                None,
+                Some(new_review("crap")),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        store
+            .update_question(edited.clone(), id, None, None)
+            .await
+            .unwrap();
+        assert!(store.get_reviews().await.unwrap().is_empty());
+        assert!(store.get_question(id).await.unwrap().is_some());
+
+        // Pending answers are left out as well
+        let answer = store
+            .add_answer(
+                NewAnswer {
+                    content: "crap".to_string(),
+                    question_id: edited.id.clone(),
+                },
+                AccountId(2),
+                Some(new_review("crap")),
+            )
+            .await
+            .unwrap();
+        assert!(store.get_answer(answer.id.0).await.unwrap().is_none());
+        assert!(store.get_answers(id, None, 0).await.unwrap().is_empty());
+
+        // Deleting the question drops its answers and their reviews
+        store
+            .update_question(edited, id, None, Some(new_review("crap")))
+            .await
+            .unwrap();
+        assert_eq!(store.get_reviews().await.unwrap().len(), 2);
+        assert!(store.delete_question(id, None).await.unwrap());
+        assert!(store.get_reviews().await.unwrap().is_empty());
+        assert!(store
+            .delete_answer(answer.id.0, None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn search() {
+        let store = store().await;
+        let question = store
+            .add_question(new_question(), AccountId(1), None)
+            .await
+            .unwrap();
+        store
+            .add_answer(
+                NewAnswer {
+                    content: "Use a Mutex".to_string(),
+                    question_id: question.id.clone(),
+                },
+                AccountId(2),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut query = SearchQuery {
+            q: "MUTEX".to_string(),
+            tag: Some("general".to_string()),
+            author: Some(AccountId(1)),
+        };
+        let results = store.search(&query, None, 0).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].rank, 0.5);
+
+        query.tag = Some("unknown".to_string());
+        assert!(store.search(&query, None, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tags() {
+        let store = store().await;
+        for tags in [vec!["rust", "warp"], vec!["rust"], vec!["python"]] {
+            let question = NewQuestion {
+                tags: Some(tags.into_iter().map(String::from).collect()),
+                ..new_question()
+            };
+            store
+                .add_question(question, AccountId(1), None)
+                .await
+                .unwrap();
+        }
+
+        let tags = store.get_tags().await.unwrap();
+        assert_eq!(tags[0].tag, "rust");
+        assert_eq!(tags[0].count, 2);
+        assert_eq!(tags.len(), 3);
+
+        let mut query = QuestionQuery {
+            tags: vec!["warp".to_string(), "python".to_string()],
+            ..QuestionQuery::default()
+        };
+        let ids = |questions: Vec<Question>| {
+            questions.into_iter().map(|q| q.id.0).collect::<Vec<_>>()
+        };
+        let questions =
+            store.get_questions(&query, None, 0).await.unwrap();
+        assert_eq!(ids(questions), vec![1, 3]);
+        assert_eq!(store.count_questions(&query).await.unwrap(), 2);
+
+        query.tags = vec!["rust".to_string(), "warp".to_string()];
+        query.tag_match = TagMatch::All;
+        let questions =
+            store.get_questions(&query, None, 0).await.unwrap();
+        assert_eq!(ids(questions), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn questions_after_cursor() {
+        let store = store().await;
+        for _ in 0..3 {
+            store
+                .add_question(new_question(), AccountId(1), None)
+                .await
+                .unwrap();
+        }
+        let query = QuestionQuery::default();
+
+        let first =
+            store.get_questions_after(&query, None, 2).await.unwrap();
+        assert_eq!(first.len(), 2);
+        let (question, cursor) = &first[1];
+        assert_eq!(question.id.0, 2);
+
+        let cursor = Cursor::decode(&cursor.encode()).unwrap();
+        let second = store
+            .get_questions_after(&query, Some(&cursor), 2)
+            .await
+            .unwrap();
+        let ids: Vec<i32> = second.iter().map(|(q, _)| q.id.0).collect();
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn duplicate_account() {
+        let store = store().await;
+        let account = Account {
+            id: None,
+            email: "test@email.com".to_string(),
+            password: "password".to_string(),
+            role: Role::User,
+            verified: false,
+        };
+        store.add_account(account.clone()).await.unwrap();
+
+        let e = store.add_account(account).await.unwrap_err();
+        match &e {
+            Error::DatabaseQueryError(sqlx::Error::Database(e)) => {
+                assert!(e.is_unique_violation())
+            }
+            e => panic!("Expected a unique violation, got {:?}", e),
+        }
+        let reply = error_handlers::return_error(warp::reject::custom(e))
+            .await
+            .unwrap();
+        assert_eq!(reply.into_response().status(), 422);
+        let account = store
+            .get_account("test@email.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.id, Some(AccountId(1)));
+    }
+
+    #[tokio::test]
+    async fn account_roles() {
+        let store = store().await;
+        store
+            .add_account(Account {
+                id: None,
+                email: "test@email.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                verified: false,
+            })
+            .await
+            .unwrap();
+
+        let account = store
+            .set_role(&AccountId(1), Role::Moderator)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.role, Role::Moderator);
+        assert!(store
+            .set_role(&AccountId(2), Role::Admin)
+            .await
+            .unwrap()
+            .is_none());
+
+        let accounts = store.get_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert!(store.delete_account(&AccountId(1)).await.unwrap());
+        assert!(store
+            .get_account_by_id(&AccountId(1))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn refresh_tokens() {
+        let store = store().await;
+        let token = RefreshToken {
+            token_hash: "hash".to_string(),
+            session_id: "session".to_string(),
+            account_id: AccountId(1),
+            expires_on: (Utc::now() + chrono::Duration::days(1))
+                .naive_utc()
+                .trunc_subsecs(6),
+            used: false,
+        };
+        store.add_refresh_token(token.clone()).await.unwrap();
+
+        let first = store.use_refresh_token("hash").await.unwrap();
+        assert_eq!(first, Some(token));
+        let second = store.use_refresh_token("hash").await.unwrap();
+        assert!(second.unwrap().used);
+        assert_eq!(store.use_refresh_token("other").await.unwrap(), None);
+
+        store.revoke_session("session").await.unwrap();
+        assert_eq!(store.use_refresh_token("hash").await.unwrap(), None);
+
+        assert!(!store.is_token_revoked("token").await.unwrap());
+        let expires_on =
+            (Utc::now() + chrono::Duration::minutes(15)).naive_utc();
+        store.revoke_token("token", expires_on).await.unwrap();
+        assert!(store.is_token_revoked("token").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn account_tokens() {
+        let store = store().await;
+        store
+            .add_account(Account {
+                id: None,
+                email: "test@email.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                verified: false,
+            })
+            .await
+            .unwrap();
+        let token = AccountToken {
+            token_hash: "hash".to_string(),
+            account_id: AccountId(1),
+            purpose: TokenPurpose::PasswordReset,
+            expires_on: Utc::now().naive_utc().trunc_subsecs(6),
+        };
+        store.add_account_token(token.clone()).await.unwrap();
+
+        let taken = store
+            .take_account_token("hash", TokenPurpose::PasswordReset)
+            .await
+            .unwrap();
+        assert_eq!(taken, Some(token));
+        assert!(store
+            .take_account_token("hash", TokenPurpose::PasswordReset)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(store
+            .set_password(&AccountId(1), "new".to_string())
+            .await
+            .unwrap());
+        let account = store
+            .get_account("test@email.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.password, "new");
+        assert!(!account.verified);
+
+        assert!(store.set_verified(&AccountId(1)).await.unwrap());
+        assert!(!store.set_verified(&AccountId(2)).await.unwrap());
+        let account = store
+            .get_account_by_id(&AccountId(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(account.verified);
+    }
+
+    #[tokio::test]
+    async fn login_attempts() {
+        let store = store().await;
+        let now = Utc::now().naive_utc().trunc_subsecs(6);
+        let key = "email:test@email.com";
+        assert!(store.get_login_attempts(key).await.unwrap().is_none());
+
+        for failures in 1..=2 {
+            let attempts = store
+                .add_failed_login(key, now, now - Duration::hours(1))
+                .await
+                .unwrap();
+            assert_eq!(attempts.failures, failures);
+        }
+        assert_eq!(
+            store.get_login_attempts(key).await.unwrap(),
+            Some(LoginAttempts {
+                key: key.to_string(),
+                failures: 2,
+                last_failure: now,
+            })
+        );
+
+        // Failures from before the window are forgotten
+        let later = now + Duration::hours(2);
+        let attempts = store
+            .add_failed_login(key, later, later - Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(attempts.failures, 1);
+        assert_eq!(attempts.last_failure, later);
+
+        store.clear_login_attempts(key).await.unwrap();
+        assert!(store.get_login_attempts(key).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn identities() {
+        let store = store().await;
+        store
+            .add_account(Account {
+                id: None,
+                email: "test@email.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                verified: true,
+            })
+            .await
+            .unwrap();
+        let issuer = "https://idp.example.com";
+        assert_eq!(store.get_identity(issuer, "sub").await.unwrap(), None);
+
+        store
+            .add_identity(issuer, "sub", &AccountId(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_identity(issuer, "sub").await.unwrap(),
+            Some(AccountId(1))
+        );
+        // Subjects are only unique per issuer
+        assert_eq!(
+            store
+                .get_identity("https://other.example.com", "sub")
+                .await
+                .unwrap(),
+            None
+        );
+        // Not mistaken for a taken email address
+        let e = store
+            .add_identity(issuer, "sub", &AccountId(1))
+            .await
+            .unwrap_err();
+        let reply = error_handlers::return_error(warp::reject::custom(e))
+            .await
+            .unwrap();
+        assert_eq!(reply.into_response().status(), 409);
+
+        store.delete_account(&AccountId(1)).await.unwrap();
+        assert_eq!(store.get_identity(issuer, "sub").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn sessions() {
+        let store = store().await;
+        store
+            .add_account(Account {
+                id: None,
+                email: "test@email.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                verified: true,
+            })
+            .await
+            .unwrap();
+        let now = Utc::now().naive_utc().trunc_subsecs(6);
+        for session_id in ["phone", "laptop"] {
+            store
+                .add_session(SessionInfo {
+                    session_id: session_id.to_string(),
+                    account_id: AccountId(1),
+                    created_on: now,
+                    last_used: now,
+                    user_agent: Some(session_id.to_string()),
+                    ip: Some("192.0.2.1".to_string()),
+                    current: false,
+                })
+                .await
+                .unwrap();
+            store
+                .add_refresh_token(RefreshToken {
+                    token_hash: session_id.to_string(),
+                    session_id: session_id.to_string(),
+                    account_id: AccountId(1),
+                    expires_on: now + Duration::days(30),
+                    used: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        // Only stale last uses are updated
+        let later = now + Duration::seconds(30);
+        store
+            .touch_session("laptop", later, later - Duration::minutes(1))
+            .await
+            .unwrap();
+        let later = now + Duration::minutes(2);
+        store
+            .touch_session("phone", later, later - Duration::minutes(1))
+            .await
+            .unwrap();
+        let sessions = store.get_sessions(&AccountId(1)).await.unwrap();
+        let ids: Vec<_> =
+            sessions.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, ["phone", "laptop"]);
+        assert_eq!(sessions[0].last_used, later);
+        assert_eq!(sessions[1].last_used, now);
+        assert_eq!(sessions[1].user_agent.as_deref(), Some("laptop"));
+
+        store.revoke_session("laptop").await.unwrap();
+        assert_eq!(
+            store.get_sessions(&AccountId(1)).await.unwrap().len(),
+            1
+        );
+        store
+            .revoke_account_sessions(&AccountId(1), None, later)
+            .await
+            .unwrap();
+        assert!(store
+            .get_sessions(&AccountId(1))
+            .await
+            .unwrap()
+            .is_empty());
+        // The access tokens of the logins stop working as well
+        assert!(store.is_token_revoked("phone").await.unwrap());
+    }
+}
@@ -1,11 +1,44 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use error_handlers::Error;
+
+use crate::types::api_key::Scope;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Session {
     pub exp: DateTime<Utc>,
     pub account_id: AccountId,
     pub nbf: DateTime<Utc>,
+    /// Id of the access token, checked against the revocation list
+    pub token_id: String,
+    /// The login the token was issued for, see `RefreshToken`
+    pub session_id: String,
+    /// Role of the account when the token was issued
+    pub role: Role,
+    /// Whether the email address was verified when the token was issued
+    pub verified: bool,
+    /// What a request with an API key may do, `None` for logins which
+    /// may do everything
+    #[serde(default)]
+    pub scopes: Option<Vec<Scope>>,
+}
+
+/// A login as `GET /account/sessions` shows it. The id is the
+/// `session_id` of its tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub account_id: AccountId,
+    pub created_on: NaiveDateTime,
+    /// Last request with one of its access tokens or its last refresh,
+    /// to the minute
+    pub last_used: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether it is the login of the request
+    #[serde(default)]
+    pub current: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -13,7 +46,103 @@ pub struct Account {
     pub id: Option<AccountId>,
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub role: Role,
+    /// Whether the owner proved the email address is theirs
+    #[serde(default)]
+    pub verified: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AccountId(pub i32);
+
+/// What an account may do. Every role can do everything the roles
+/// before it can: moderators edit and delete any question or answer,
+/// admins also manage accounts.
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    /// How the role is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = Error;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "user" => Ok(Role::User),
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(Error::InvalidParameter("role".to_string())),
+        }
+    }
+}
+
+/// An account as the admin routes show it, without the password hash
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountInfo {
+    pub id: AccountId,
+    pub email: String,
+    pub role: Role,
+}
+
+impl From<Account> for AccountInfo {
+    fn from(account: Account) -> Self {
+        AccountInfo {
+            id: account.id.expect("id not found"),
+            email: account.email,
+            role: account.role,
+        }
+    }
+}
+
+/// Body of `PUT /accounts/{id}/role`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewRole {
+    pub role: Role,
+}
+
+/// Body of `POST /password/forgot`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// Body of `POST /password/reset`, with the token from the reset mail
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+}
+
+/// Body of `PUT /account/password`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChangePassword {
+    pub current_password: String,
+    pub new_password: String,
+}
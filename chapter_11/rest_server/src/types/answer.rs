@@ -7,6 +7,9 @@ pub struct Answer {
     pub id: AnswerId,
     pub content: String,
     pub question_id: QuestionId,
+    /// Sum of the up and down votes, maintained by the store
+    #[serde(default)]
+    pub score: i32,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -0,0 +1,93 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use error_handlers::Error;
+
+use crate::types::account::AccountId;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApiKeyId(pub i32);
+
+/// What an API key may do. Logins may do everything, API keys only
+/// what their scopes allow.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    #[serde(rename = "questions:read")]
+    QuestionsRead,
+    /// Add, change and delete questions, vote on them and accept
+    /// answers
+    #[serde(rename = "questions:write")]
+    QuestionsWrite,
+    /// Add, change and delete answers and vote on them
+    #[serde(rename = "answers:write")]
+    AnswersWrite,
+}
+
+impl Scope {
+    /// How the scope is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::QuestionsRead => "questions:read",
+            Scope::QuestionsWrite => "questions:write",
+            Scope::AnswersWrite => "answers:write",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        match scope {
+            "questions:read" => Ok(Scope::QuestionsRead),
+            "questions:write" => Ok(Scope::QuestionsWrite),
+            "answers:write" => Ok(Scope::AnswersWrite),
+            _ => Err(Error::InvalidParameter("scopes".to_string())),
+        }
+    }
+}
+
+/// An API key as its owner sees it. The key itself is only shown when
+/// it is created, the store keeps its hash.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    pub account_id: AccountId,
+    pub name: String,
+    /// Start of the key, to tell keys apart
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub created_on: NaiveDateTime,
+}
+
+/// Body of `POST /account/api-keys`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Answer of `POST /account/api-keys`, the only time the key is shown
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// Scopes as one column, separated by spaces
+pub fn scopes_to_string(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(Scope::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reverse of `scopes_to_string`, unknown scopes are skipped
+pub fn scopes_from_string(scopes: &str) -> Vec<Scope> {
+    scopes
+        .split_whitespace()
+        .filter_map(|scope| scope.parse().ok())
+        .collect()
+}
@@ -0,0 +1,76 @@
+use chrono::{Duration, NaiveDateTime};
+
+/// Failed logins in a row before logins get slowed down
+const FREE_ATTEMPTS: i32 = 3;
+/// Failed logins in a row before the key is locked out
+const LOCKOUT_ATTEMPTS: i32 = 10;
+const LOCKOUT: Duration = Duration::minutes(15);
+/// Failures older than this are forgotten
+pub const ATTEMPT_WINDOW: Duration = Duration::hours(1);
+
+/// Failed logins of one email address or IP address since the last
+/// successful login
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginAttempts {
+    /// `email:<address>` or `ip:<address>`
+    pub key: String,
+    pub failures: i32,
+    pub last_failure: NaiveDateTime,
+}
+
+impl LoginAttempts {
+    /// How long the key has to wait before the next login, `None` if it
+    /// may try now. The wait doubles with every failure after the free
+    /// attempts, until the key is locked out.
+    pub fn retry_after(&self, now: NaiveDateTime) -> Option<Duration> {
+        let wait = if self.failures >= LOCKOUT_ATTEMPTS {
+            LOCKOUT
+        } else if self.failures >= FREE_ATTEMPTS {
+            Duration::seconds(1 << (self.failures - FREE_ATTEMPTS))
+        } else {
+            return None;
+        };
+
+        let remaining = self.last_failure + wait - now;
+        (remaining > Duration::zero()).then_some(remaining)
+    }
+}
+
+#[cfg(test)]
+mod login_attempt_tests {
+    use chrono::{Duration, Utc};
+
+    use super::LoginAttempts;
+
+    #[test]
+    fn backoff() {
+        let now = Utc::now().naive_utc();
+        let attempts = |failures: i32| LoginAttempts {
+            key: "email:test@example.com".to_string(),
+            failures,
+            last_failure: now,
+        };
+
+        assert_eq!(attempts(2).retry_after(now), None);
+        assert_eq!(
+            attempts(3).retry_after(now),
+            Some(Duration::seconds(1))
+        );
+        assert_eq!(
+            attempts(5).retry_after(now),
+            Some(Duration::seconds(4))
+        );
+        assert_eq!(
+            attempts(9).retry_after(now + Duration::seconds(4)),
+            Some(Duration::seconds(60))
+        );
+        assert_eq!(
+            attempts(10).retry_after(now),
+            Some(Duration::minutes(15))
+        );
+        assert_eq!(
+            attempts(12).retry_after(now + Duration::hours(1)),
+            None
+        );
+    }
+}
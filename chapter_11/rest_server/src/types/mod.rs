@@ -1,4 +1,12 @@
+pub(crate) mod account;
 pub(crate) mod answer;
+pub(crate) mod api_key;
+pub(crate) mod login_attempt;
+pub(crate) mod oidc;
 pub(crate) mod pagination;
 pub(crate) mod question;
-pub(crate) mod account;
+pub(crate) mod review;
+pub(crate) mod search;
+pub(crate) mod tag;
+pub(crate) mod token;
+pub(crate) mod vote;
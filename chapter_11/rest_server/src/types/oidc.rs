@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+/// The part of the discovery document of an OpenID Connect provider
+/// the login needs
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// A public key of the provider, as published at `jwks_uri`. RSA keys
+/// carry `n` and `e`, Ed25519 keys `x`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Jwk {
+    pub kid: Option<String>,
+    pub kty: String,
+    pub crv: Option<String>,
+    pub n: Option<String>,
+    pub e: Option<String>,
+    pub x: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+/// Answer of the token endpoint, only the ID token is used
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProviderTokens {
+    pub id_token: String,
+}
+
+/// `aud` is a single client id or a list of them
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(audience) => audience == client_id,
+            Audience::Many(audiences) => {
+                audiences.iter().any(|audience| audience == client_id)
+            }
+        }
+    }
+}
+
+/// The claims of an ID token the login checks or uses
+#[derive(Deserialize, Debug, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    /// Id of the user at the provider, never reassigned
+    pub sub: String,
+    pub aud: Audience,
+    pub exp: i64,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+/// A login waiting for the provider to redirect back. Kept encrypted in
+/// a cookie, so any instance of the service can finish the login.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct PendingLogin {
+    /// Has to come back unchanged from the provider
+    pub state: String,
+    /// Has to be in the ID token
+    pub nonce: String,
+    /// PKCE verifier, the provider only saw its hash
+    pub code_verifier: String,
+    /// Unix time after which the login has to start over
+    pub expires_on: i64,
+}
+
+/// Query of the redirect back from the provider
+#[derive(Deserialize, Debug, Clone)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` if the provider refused the login
+    pub error: Option<String>,
+}
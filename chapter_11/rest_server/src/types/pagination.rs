@@ -1,3 +1,5 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -13,6 +15,38 @@ pub struct Pagination {
     pub offset: i32,
 }
 
+/// Limits every paginated route enforces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaginationConfig {
+    /// Largest `limit` a client may ask for
+    pub max_limit: i32,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig { max_limit: 100 }
+    }
+}
+
+/// Rejects limits above the configured maximum and negative offsets
+fn check_bounds(
+    limit: i32,
+    offset: i32,
+    config: &PaginationConfig,
+) -> Result<(), Error> {
+    if limit < 0 {
+        return Err(Error::InvalidParameter("limit".to_string()));
+    }
+    if limit > config.max_limit {
+        return Err(Error::LimitTooLarge(config.max_limit));
+    }
+    if offset < 0 {
+        return Err(Error::NegativeOffset);
+    }
+
+    Ok(())
+}
+
 /// Extract query parameters from the `/questions` route
 /// # Example query
 /// GET requests to this route can have a pagination attached so we just
@@ -25,39 +59,176 @@ pub struct Pagination {
 /// let mut query = HashMap::new();
 /// query.insert("limit".to_string(), "1".to_string());
 /// query.insert("offset".to_string(), "10".to_string());
-/// let p = pagination::extract_pagination(query).unwrap();
+/// let config = pagination::PaginationConfig::default();
+/// let p = pagination::extract_pagination(query, &config).unwrap();
 /// assert_eq!(p.limit, Some(1));
 /// assert_eq!(p.offset, 10);
 /// ```
 pub fn extract_pagination(
     params: HashMap<String, String>,
+    config: &PaginationConfig,
 ) -> Result<Pagination, Error> {
     // Could be improved in the future
     if params.contains_key("limit") && params.contains_key("offset") {
+        // Takes the "limit" parameter in the query and tries to convert it to a number
+        let limit = params
+            .get("limit")
+            .unwrap()
+            .parse()
+            .map_err(Error::ParseError)?;
+        // Takes the "offset" parameter in the query and tries to convert it to a number
+        let offset = params
+            .get("offset")
+            .unwrap()
+            .parse()
+            .map_err(Error::ParseError)?;
+        check_bounds(limit, offset, config)?;
+
         return Ok(Pagination {
-            // Takes the "limit" parameter in the query and tries to convert it to a number
-            limit: Some(
-                params
-                    .get("limit")
-                    .unwrap()
-                    .parse()
-                    .map_err(Error::ParseError)?,
-            ),
-            // Takes the "offset" parameter in the query and tries to convert it to a number
-            offset: params
-                .get("offset")
-                .unwrap()
-                .parse()
-                .map_err(Error::ParseError)?,
+            limit: Some(limit),
+            offset,
         });
     }
 
     Err(Error::MissingParameters)
 }
 
+/// A page of a `limit`/`offset` paginated listing together with the
+/// number of items in the whole listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: Option<i32>,
+    pub offset: i32,
+}
+
+/// RFC 8288 `Link` header value with the `first`, `prev`, `next` and
+/// `last` pages of a listing at `path`. `params` are the query
+/// parameters besides the pagination, which every link keeps. `None`
+/// without a limit, since everything is on one page then.
+pub fn link_header(
+    path: &str,
+    params: &[(String, String)],
+    pagination: &Pagination,
+    total: i64,
+) -> Option<String> {
+    let limit = i64::from(pagination.limit?);
+    let offset = i64::from(pagination.offset);
+
+    let link = |offset: i64, rel: &str| {
+        let mut query: Vec<(&str, String)> = params
+            .iter()
+            .filter(|(key, _)| key != "limit" && key != "offset")
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        query.push(("limit", limit.to_string()));
+        query.push(("offset", offset.to_string()));
+        let query = serde_urlencoded::to_string(query).unwrap_or_default();
+        format!("<{}?{}>; rel=\"{}\"", path, query, rel)
+    };
+
+    let last = if limit == 0 || total == 0 {
+        0
+    } else {
+        (total - 1) / limit * limit
+    };
+
+    let mut links = vec![link(0, "first")];
+    if offset > 0 {
+        links.push(link((offset - limit).max(0), "prev"));
+    }
+    if limit > 0 && offset + limit < total {
+        links.push(link(offset + limit, "next"));
+    }
+    links.push(link(last, "last"));
+
+    Some(links.join(", "))
+}
+
+/// Position in a listing ordered by `(created_on, id)`. Handed to clients
+/// as an opaque token, the next page starts after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub created_on: NaiveDateTime,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let micros = self.created_on.and_utc().timestamp_micros();
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", micros, self.id))
+    }
+
+    pub fn decode(token: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidParameter("cursor".to_string());
+
+        let decoded =
+            URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (micros, id) = decoded.split_once(':').ok_or_else(invalid)?;
+        let micros = micros.parse().map_err(|_| invalid())?;
+
+        Ok(Cursor {
+            created_on: DateTime::from_timestamp_micros(micros)
+                .ok_or_else(invalid)?
+                .naive_utc(),
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Keyset pagination, the alternative to `limit`/`offset` which stays
+/// fast and stable on large tables
+#[derive(Debug, PartialEq)]
+pub struct CursorPagination {
+    /// Maximum number of items on the page
+    pub limit: i32,
+    /// `None` for the first page
+    pub after: Option<Cursor>,
+}
+
+/// A page of a cursor-paginated listing. `next` is the cursor for the
+/// following page, `None` on the last one.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next: Option<String>,
+}
+
+/// Extract the cursor pagination, if the client asked for it with the
+/// `cursor` parameter. An empty `cursor` requests the first page.
+/// # Example query
+/// `/questions?cursor=&limit=10`, then `/questions?cursor=<next>&limit=10`
+pub fn extract_cursor_pagination(
+    params: &HashMap<String, String>,
+    config: &PaginationConfig,
+) -> Result<Option<CursorPagination>, Error> {
+    let cursor = match params.get("cursor") {
+        Some(cursor) => cursor,
+        None => return Ok(None),
+    };
+
+    let limit = params
+        .get("limit")
+        .ok_or(Error::MissingParameters)?
+        .parse()
+        .map_err(Error::ParseError)?;
+    check_bounds(limit, 0, config)?;
+    let after = match cursor.as_str() {
+        "" => None,
+        token => Some(Cursor::decode(token)?),
+    };
+
+    Ok(Some(CursorPagination { limit, after }))
+}
+
 #[cfg(test)]
 mod pagination_tests {
-    use super::{extract_pagination, HashMap, Pagination};
+    use super::{
+        extract_cursor_pagination, extract_pagination, link_header,
+        Cursor, HashMap, Pagination, PaginationConfig,
+    };
     use error_handlers::Error;
 
     #[test]
@@ -65,7 +236,8 @@ mod pagination_tests {
         let mut params = HashMap::new();
         params.insert(String::from("limit"), String::from("1"));
         params.insert(String::from("offset"), String::from("1"));
-        let pagination_result = extract_pagination(params);
+        let pagination_result =
+            extract_pagination(params, &PaginationConfig::default());
         let expected = Pagination {
             limit: Some(1),
             offset: 1,
@@ -78,10 +250,98 @@ mod pagination_tests {
         let mut params = HashMap::new();
         params.insert(String::from("limit"), String::from("1"));
 
-        let pagination_result =
-            format!("{}", extract_pagination(params).unwrap_err());
+        let pagination_result = format!(
+            "{}",
+            extract_pagination(params, &PaginationConfig::default())
+                .unwrap_err()
+        );
         let expected = format!("{}", Error::MissingParameters);
 
         assert_eq!(pagination_result, expected);
     }
+
+    #[test]
+    fn cursor_roundtrip() {
+        let cursor = Cursor {
+            created_on: chrono::DateTime::from_timestamp_micros(
+                1_650_000_000_123_456,
+            )
+            .unwrap()
+            .naive_utc(),
+            id: 42,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+    }
+
+    #[test]
+    fn cursor_pagination() {
+        let mut params = HashMap::new();
+        params.insert(String::from("limit"), String::from("10"));
+        assert_eq!(
+            extract_cursor_pagination(
+                &params,
+                &PaginationConfig::default()
+            )
+            .unwrap(),
+            None
+        );
+
+        params.insert(String::from("cursor"), String::from(""));
+        let pagination = extract_cursor_pagination(
+            &params,
+            &PaginationConfig::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(pagination.limit, 10);
+        assert_eq!(pagination.after, None);
+    }
+
+    #[test]
+    fn pagination_bounds() {
+        let config = PaginationConfig { max_limit: 10 };
+        let params = |limit: &str, offset: &str| {
+            let mut params = HashMap::new();
+            params.insert(String::from("limit"), String::from(limit));
+            params.insert(String::from("offset"), String::from(offset));
+            params
+        };
+
+        match extract_pagination(params("11", "0"), &config) {
+            Err(Error::LimitTooLarge(10)) => (),
+            res => panic!("Expected LimitTooLarge, got {:?}", res),
+        }
+        match extract_pagination(params("10", "-1"), &config) {
+            Err(Error::NegativeOffset) => (),
+            res => panic!("Expected NegativeOffset, got {:?}", res),
+        }
+        assert!(extract_pagination(params("10", "0"), &config).is_ok());
+    }
+
+    #[test]
+    fn link_headers() {
+        let params = vec![
+            (String::from("tag"), String::from("web dev")),
+            (String::from("limit"), String::from("10")),
+        ];
+        let pagination = Pagination {
+            limit: Some(10),
+            offset: 10,
+        };
+
+        let links = link_header("/questions", &params, &pagination, 25);
+        assert_eq!(
+            links.unwrap(),
+            "</questions?tag=web+dev&limit=10&offset=0>; rel=\"first\", \
+            </questions?tag=web+dev&limit=10&offset=0>; rel=\"prev\", \
+            </questions?tag=web+dev&limit=10&offset=20>; rel=\"next\", \
+            </questions?tag=web+dev&limit=10&offset=20>; rel=\"last\""
+        );
+
+        let everything = Pagination::default();
+        assert!(
+            link_header("/questions", &params, &everything, 25).is_none()
+        );
+    }
 }
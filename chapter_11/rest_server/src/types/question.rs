@@ -1,11 +1,22 @@
 use serde::{Deserialize, Serialize};
 
+use error_handlers::Error;
+
+use crate::types::answer::{Answer, AnswerId};
+use crate::types::tag::{normalize_tag, TagMatch};
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Question {
     pub id: QuestionId,
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    /// Sum of the up and down votes, maintained by the store
+    #[serde(default)]
+    pub score: i32,
+    /// Answer the owner of the question accepted, if any
+    #[serde(default)]
+    pub accepted_answer_id: Option<AnswerId>,
 }
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct QuestionId(pub i32);
@@ -16,3 +27,92 @@ pub struct NewQuestion {
     pub content: String,
     pub tags: Option<Vec<String>>,
 }
+
+/// A single question together with a page of its answers
+#[derive(Serialize, Debug, Clone)]
+pub struct QuestionWithAnswers {
+    #[serde(flatten)]
+    pub question: Question,
+    pub answers: Vec<Answer>,
+}
+
+/// Order in which `/questions` returns the questions
+#[derive(
+    Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum QuestionSort {
+    /// The order the store keeps them in
+    #[default]
+    None,
+    /// Highest score first
+    Score,
+}
+
+/// Everything besides the pagination `/questions` can be queried by
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct QuestionQuery {
+    pub sort: QuestionSort,
+    /// `Some(true)` only returns questions with an accepted answer,
+    /// `Some(false)` only those without one
+    pub answered: Option<bool>,
+    /// Only questions with these tags, normalized. Empty for all
+    /// questions.
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+}
+
+/// Extract the non-pagination query parameters of the `/questions` route.
+/// Takes the parameters as pairs because `tag` can be repeated, for the
+/// other parameters the last one wins.
+/// # Example query
+/// `/questions?sort=score&answered=false&tag=rust&tag=warp&tag_match=all&limit=10&offset=0`
+pub fn extract_question_query(
+    params: &[(String, String)],
+) -> Result<QuestionQuery, Error> {
+    let get = |key: &str| {
+        params
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let sort = match get("sort") {
+        None => QuestionSort::None,
+        Some("score") => QuestionSort::Score,
+        Some(_) => {
+            return Err(Error::InvalidParameter("sort".to_string()))
+        }
+    };
+
+    let answered = match get("answered") {
+        None => None,
+        Some(answered) => {
+            Some(answered.parse::<bool>().map_err(|_| {
+                Error::InvalidParameter("answered".to_string())
+            })?)
+        }
+    };
+
+    let tag_match = match get("tag_match") {
+        None | Some("any") => TagMatch::Any,
+        Some("all") => TagMatch::All,
+        Some(_) => {
+            return Err(Error::InvalidParameter("tag_match".to_string()))
+        }
+    };
+
+    let tags = params
+        .iter()
+        .filter(|(key, _)| key == "tag")
+        .filter_map(|(_, tag)| normalize_tag(tag))
+        .collect();
+
+    Ok(QuestionQuery {
+        sort,
+        answered,
+        tags,
+        tag_match,
+    })
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::profanity::BadWord;
+use crate::types::answer::AnswerId;
+use crate::types::question::QuestionId;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReviewId(pub i32);
+
+/// The post a review holds back
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewTarget {
+    Question(QuestionId),
+    Answer(AnswerId),
+}
+
+/// Title and content of a post, answers have no title. Also the text a
+/// moderator publishes an edited post with.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ReviewText {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub content: String,
+}
+
+/// Bad words the moderation found in the title and the content, their
+/// offsets are in the original text
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct BadWords {
+    #[serde(default)]
+    pub title: Vec<BadWord>,
+    pub content: Vec<BadWord>,
+}
+
+/// A post held back until a moderator approves, edits or rejects it.
+/// The post itself carries the censored text while it is pending.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Review {
+    pub id: ReviewId,
+    pub target: ReviewTarget,
+    pub original: ReviewText,
+    pub censored: ReviewText,
+    pub bad_words: BadWords,
+    /// Posted without moderation because it was unavailable
+    pub unchecked: bool,
+    pub created_on: NaiveDateTime,
+}
+
+/// What the moderation found in a post it holds back. The store call
+/// writing the post takes it, so the post is pending from the start.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct NewReview {
+    pub original: ReviewText,
+    pub censored: ReviewText,
+    pub bad_words: BadWords,
+    pub unchecked: bool,
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use error_handlers::Error;
+
+use crate::types::{
+    account::AccountId, question::Question, tag::normalize_tag,
+};
+
+/// Search terms and filters of the `/search` route
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    /// Searched for in question titles, question content and answers
+    pub q: String,
+    /// Only questions with this tag
+    pub tag: Option<String>,
+    /// Only questions asked by this account
+    pub author: Option<AccountId>,
+}
+
+/// A question which matched a search. Higher ranked results are more
+/// relevant and come first.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub question: Question,
+    pub rank: f32,
+}
+
+/// Extract the non-pagination query parameters of the `/search` route
+/// # Example query
+/// `/search?q=ownership&tag=rust&author=1&limit=10&offset=0`
+pub fn extract_search_query(
+    params: &HashMap<String, String>,
+) -> Result<SearchQuery, Error> {
+    let q = match params.get("q").map(|q| q.trim()) {
+        Some(q) if !q.is_empty() => q.to_string(),
+        _ => return Err(Error::MissingParameters),
+    };
+
+    let author = match params.get("author") {
+        None => None,
+        Some(author) => {
+            Some(AccountId(author.parse().map_err(Error::ParseError)?))
+        }
+    };
+
+    Ok(SearchQuery {
+        q,
+        tag: params.get("tag").and_then(|tag| normalize_tag(tag)),
+        author,
+    })
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::{extract_search_query, HashMap};
+    use crate::types::account::AccountId;
+
+    #[test]
+    fn search_query() {
+        let mut params = HashMap::new();
+        params.insert(String::from("q"), String::from(" ownership "));
+        params.insert(String::from("author"), String::from("3"));
+        let query = extract_search_query(&params).unwrap();
+        assert_eq!(query.q, "ownership");
+        assert_eq!(query.tag, None);
+        assert_eq!(query.author, Some(AccountId(3)));
+
+        params.insert(String::from("q"), String::from(" "));
+        assert!(extract_search_query(&params).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// A tag and the number of questions which carry it
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// How the tags given to `/questions` have to match
+#[derive(
+    Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// Questions with at least one of the tags
+    #[default]
+    Any,
+    /// Questions with every one of the tags
+    All,
+}
+
+/// Lowercases a tag and joins its words with `-`, so `" Rust  Lang"`
+/// becomes `"rust-lang"`. Returns `None` for blank tags.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Normalizes every tag and drops blanks and duplicates, keeping the
+/// order in which the tags were given
+pub fn normalize_tags(tags: Option<Vec<String>>) -> Option<Vec<String>> {
+    tags.map(|tags| {
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags.iter().filter_map(|tag| normalize_tag(tag)) {
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+        normalized
+    })
+}
+
+#[cfg(test)]
+mod tag_tests {
+    use super::normalize_tags;
+
+    #[test]
+    fn normalize() {
+        let tags = vec![
+            " Rust ".to_string(),
+            "Web  Dev".to_string(),
+            "rust".to_string(),
+            "  ".to_string(),
+        ];
+        assert_eq!(
+            normalize_tags(Some(tags)),
+            Some(vec!["rust".to_string(), "web-dev".to_string()])
+        );
+        assert_eq!(normalize_tags(None), None);
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::keyring::TokenMode;
+use crate::types::account::AccountId;
+
+/// A refresh token as the store keeps it. Only the hash of the token is
+/// stored, the token itself is handed to the client once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshToken {
+    pub token_hash: String,
+    /// The login the token belongs to. Stays the same when the token is
+    /// rotated, so a whole login can be revoked at once.
+    pub session_id: String,
+    pub account_id: AccountId,
+    pub expires_on: NaiveDateTime,
+    /// Whether the token was already exchanged for a new one
+    pub used: bool,
+}
+
+/// Returned by `/login` and `/token/refresh`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+}
+
+/// Body of `/token/refresh`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// What a one-time account token is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    PasswordReset,
+    EmailVerification,
+}
+
+impl TokenPurpose {
+    /// How the purpose is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::PasswordReset => "password_reset",
+            TokenPurpose::EmailVerification => "email_verification",
+        }
+    }
+}
+
+/// A single-use token sent to the email address of an account. Like
+/// refresh tokens only the hash is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountToken {
+    pub token_hash: String,
+    pub account_id: AccountId,
+    pub purpose: TokenPurpose,
+    pub expires_on: NaiveDateTime,
+}
+
+/// A public key of the public access tokens
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    /// The id in the footer of the tokens signed with the key
+    pub kid: String,
+    pub alg: String,
+    /// The raw key, base64url encoded without padding
+    pub public_key: String,
+    /// Whether new tokens are signed with the key
+    pub active: bool,
+}
+
+/// Body of `/.well-known/paseto-keys`
+#[derive(Serialize, Debug, Clone)]
+pub struct PublicKeys {
+    pub token_mode: TokenMode,
+    pub keys: Vec<PublicKey>,
+}
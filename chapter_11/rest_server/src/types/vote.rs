@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Direction of a vote on a question or an answer
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+impl Vote {
+    /// What the vote adds to the score of the item
+    pub fn value(&self) -> i16 {
+        match self {
+            Vote::Up => 1,
+            Vote::Down => -1,
+        }
+    }
+}
+
+/// Request body of the vote routes, e.g. `{ "vote": "up" }`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewVote {
+    pub vote: Vote,
+}